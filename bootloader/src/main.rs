@@ -0,0 +1,222 @@
+//! A dual-slot boot loader for the Launchpad.
+//!
+//! It lives in the first 46 KiB of flash and starts whichever application
+//! slot `common::update` selects. If there's nothing to start, or SW1 is
+//! held down at reset, it turns the blue LED on and waits for a new image on
+//! UART0 (the ICDI's virtual serial port) at 115200 bps - send one with
+//! `lp-update` (see `tools/`).
+//!
+//! Build the applications with the `slot-a` or `slot-b` feature, and have
+//! them call `board::update::confirm` once they're running. An image that
+//! hasn't confirmed itself after `MAX_BOOTS` resets is abandoned in favour
+//! of the previous one.
+//!
+//! With the `signed` feature, only images signed by `lp-sign` are accepted.
+//! The public key is built in from the file named by the `LP_PUBLIC_KEY`
+//! environment variable, and the version of the newest image to have
+//! confirmed itself is kept in the EEPROM - nothing older will be started,
+//! or accepted as an update. If the EEPROM can't be read or written, nothing
+//! is started at all.
+
+#![no_std]
+#![no_main]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate cortex_m;
+extern crate embedded_hal;
+extern crate stellaris_launchpad;
+extern crate tm4c123x_hal;
+
+use cortex_m::peripheral::SCB;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::serial::{Read, Write};
+use stellaris_launchpad::board;
+#[cfg(feature = "signed")]
+use stellaris_launchpad::board::eeprom::{self, Eeprom};
+#[cfg(feature = "signed")]
+use stellaris_launchpad::board::flash::Flash;
+#[cfg(feature = "signed")]
+use stellaris_launchpad::common::signed_image::{SignatureCheck, KEY_LEN};
+use stellaris_launchpad::common::update::{self, Updater};
+use stellaris_launchpad::common::update_protocol::{Event, Server, MAX_FRAME};
+use tm4c123x_hal::gpio::GpioExt;
+use tm4c123x_hal::serial;
+#[cfg(feature = "signed")]
+use tm4c123x_hal::sysctl::PowerControl;
+use tm4c123x_hal::time::Bps;
+#[cfg(feature = "signed")]
+use tm4c123x_hal::tm4c123x::EEPROM;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The anti-rollback version, kept in the EEPROM.
+#[cfg(feature = "signed")]
+struct AntiRollback {
+    eeprom: Eeprom,
+    minimum: u32,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// How many times a new image is started before we give up on it
+const MAX_BOOTS: u8 = 3;
+
+/// The key images must be signed with
+#[cfg(feature = "signed")]
+static PUBLIC_KEY: &[u8; KEY_LEN] = include_bytes!(env!(
+    "LP_PUBLIC_KEY",
+    "set LP_PUBLIC_KEY to the public key file written by `lp-sign keygen`"
+));
+
+/// Where the anti-rollback version lives (the last word of the EEPROM)
+#[cfg(feature = "signed")]
+const ANTI_ROLLBACK_WORD: u32 = eeprom::NUM_WORDS - 1;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+#[no_mangle]
+pub fn stellaris_main(mut board: board::Board) {
+    let flash = board.system.flash;
+    #[cfg(feature = "signed")]
+    let mut anti_rollback = AntiRollback::new(board.system.eeprom, &board.power_control);
+    #[cfg(feature = "signed")]
+    let updater = Updater::open_with(flash, update::LAUNCHPAD, anti_rollback.check());
+    #[cfg(not(feature = "signed"))]
+    let updater = Updater::open(flash, update::LAUNCHPAD);
+    let mut updater = match updater {
+        Ok(updater) => updater,
+        Err(_) => board::panic(),
+    };
+    #[cfg(feature = "signed")]
+    anti_rollback.raise_to_confirmed(&mut updater);
+
+    let wait = board.button_one.is_low().unwrap_or(false);
+    if !wait {
+        if let Ok(Some(slot)) = updater.select(MAX_BOOTS) {
+            unsafe { board::update::start(slot) };
+        }
+    }
+
+    // Nothing to start, or we've been asked to wait for an update
+    let _ = board.led_blue.set_high();
+    let mut pins_a = board.gpio.porta;
+    let mut uart = board.serial.uart0.serial(
+        pins_a.pa1.into_af_push_pull(&mut pins_a.control),
+        pins_a.pa0.into_af_push_pull(&mut pins_a.control),
+        Bps(115200),
+        serial::NewlineMode::Binary,
+        &board.power_control,
+    );
+    let mut server = Server::new(updater);
+    let mut reply = [0u8; MAX_FRAME];
+    loop {
+        let byte = match uart.read() {
+            Ok(byte) => byte,
+            Err(_) => continue,
+        };
+        match server.receive(byte, &mut reply) {
+            Event::Nothing => {}
+            Event::Reply(len) => uart.write_all(&reply[..len]),
+            Event::Boot(len) => {
+                uart.write_all(&reply[..len]);
+                while uart.flush().is_err() {}
+                // Start again, so the new image is selected (and counted)
+                SCB::sys_reset();
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+#[cfg(feature = "signed")]
+impl AntiRollback {
+    /// Read the version from the EEPROM. If we can't, we can't tell which
+    /// images are safe to start, so give up.
+    fn new(eeprom: EEPROM, power_control: &PowerControl) -> AntiRollback {
+        let mut eeprom = match Eeprom::new(eeprom, power_control) {
+            Ok(eeprom) => eeprom,
+            Err(_) => board::panic(),
+        };
+        let minimum = match eeprom.read_word(ANTI_ROLLBACK_WORD) {
+            // An erased EEPROM
+            Ok(0xFFFF_FFFF) => 0,
+            Ok(minimum) => minimum,
+            Err(_) => board::panic(),
+        };
+        AntiRollback { eeprom, minimum }
+    }
+
+    /// Check images against our key and this version.
+    fn check(&self) -> SignatureCheck {
+        SignatureCheck::new(*PUBLIC_KEY, self.minimum)
+    }
+
+    /// Never go back to anything older than the confirmed image - neither
+    /// by starting it, nor by accepting it as an update. If the new version
+    /// can't be written to the EEPROM, it would be forgotten at the next
+    /// reset, so give up.
+    fn raise_to_confirmed(&mut self, updater: &mut Updater<Flash, SignatureCheck>) {
+        let slot = match updater.state().confirmed {
+            Some(slot) => slot,
+            None => return,
+        };
+        if updater.is_valid(slot) != Ok(true) {
+            return;
+        }
+        let version = match updater.verifier().accepted() {
+            Some(header) => header.version,
+            None => return,
+        };
+        if version > self.minimum && version != 0xFFFF_FFFF {
+            if self.eeprom.write_word(ANTI_ROLLBACK_WORD, version).is_err() {
+                board::panic();
+            }
+            self.minimum = version;
+            updater.verifier_mut().raise_minimum(version);
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
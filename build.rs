@@ -1,14 +1,358 @@
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The firmware's own CRC-32, so the build-info CRC can't disagree with it
+#[path = "src/common/crc.rs"]
+#[allow(dead_code)]
+mod crc;
+
+/// Base of SRAM on the LM4F120 / TM4C123
+const RAM_BASE: u32 = 0x2000_0000;
+/// Size of SRAM on the LM4F120 / TM4C123
+const RAM_SIZE: u32 = 0x0000_8000;
+/// SRAM reserved for the relocated vector table.
+const RAM_VECTORS_SIZE: u32 = 0x0000_0400;
+/// SRAM reserved for the stack.
+const STACK_SIZE: u32 = 0x0000_1000;
+/// Size of flash on the LM4F120 / TM4C123
+const FLASH_SIZE: u32 = 0x0004_0000;
+/// Flash for the boot loader. The metadata pages follow it.
+const BOOTLOADER_SIZE: u32 = 0x0000_B800;
+/// The application slots (start, size). These, and the above, must match
+/// `common::update::LAUNCHPAD`.
+const SLOT_A: (u32, u32) = (0x0000_C000, 0x0001_A000);
+const SLOT_B: (u32, u32) = (0x0002_6000, 0x0001_A000);
+/// The boards we can be built for, as recorded in the build-info record.
+const BOARD_STELLARIS: &str = "ek-lm4f120xl";
+const BOARD_TIVA: &str = "ek-tm4c123gxl";
+/// "LPBI" - must match `common::build_info`
+const BUILD_INFO_MAGIC: u32 = 0x4942_504C;
+/// The layout of the build-info record
+const BUILD_INFO_LAYOUT: u32 = 2;
 
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
-    let memory_x = include_bytes!("memory.x.in");
+    let ram_vectors = feature("RAM_VECTORS");
+    let stack_at_bottom = feature("STACK_AT_BOTTOM");
+
+    // Pick the flash region - all of it, unless we're the boot loader or an
+    // application it starts
+    let (flash_origin, flash_length) =
+        match (feature("BOOTLOADER"), feature("SLOT_A"), feature("SLOT_B")) {
+            (false, false, false) => (0, FLASH_SIZE),
+            (true, false, false) => (0, BOOTLOADER_SIZE),
+            (false, true, false) => SLOT_A,
+            (false, false, true) => SLOT_B,
+            _ => panic!("Only one of the bootloader, slot-a and slot-b features can be enabled"),
+        };
+
+    // Carve up SRAM, from the bottom up
+    let ram_end = RAM_BASE + RAM_SIZE;
+    let mut next = RAM_BASE;
+    let (stack_limit, stack_start, heap_end) = if stack_at_bottom {
+        next += STACK_SIZE;
+        (RAM_BASE, next, ram_end)
+    } else {
+        // The stack stays inside the RAM region, at the top
+        (ram_end - STACK_SIZE, ram_end, ram_end - STACK_SIZE)
+    };
+    let vectors = next;
+    if ram_vectors {
+        next += RAM_VECTORS_SIZE;
+    }
+    let ram_origin = next;
+
+    let memory_x = include_str!("memory.x.in")
+        .replace("%FLASH_ORIGIN%", &hex(flash_origin))
+        .replace("%FLASH_LENGTH%", &hex(flash_length))
+        .replace("%RAM_ORIGIN%", &hex(ram_origin))
+        .replace("%RAM_LENGTH%", &hex(ram_end - ram_origin))
+        .replace("%RAM_VECTORS%", &hex(vectors))
+        .replace("%STACK_START%", &hex(stack_start))
+        .replace("%STACK_LIMIT%", &hex(stack_limit))
+        .replace("%HEAP_END%", &hex(heap_end));
     let mut f = File::create(out_dir.join("memory.x")).unwrap();
-    f.write_all(memory_x).unwrap();
+    f.write_all(memory_x.as_bytes()).unwrap();
+
+    let mut f = File::create(out_dir.join("build_info.rs")).unwrap();
+    f.write_all(build_info().as_bytes()).unwrap();
+
+    let mut f = File::create(out_dir.join("pinmux.rs")).unwrap();
+    f.write_all(pinmux(include_str!("pinmux.csv")).as_bytes())
+        .unwrap();
 
     println!("cargo:rustc-link-search={}", out_dir.display());
+    println!("cargo:rerun-if-changed=memory.x.in");
+    println!("cargo:rerun-if-changed=pinmux.csv");
+    for git_file in &[".git/HEAD", ".git/index"] {
+        if Path::new(git_file).exists() {
+            println!("cargo:rerun-if-changed={}", git_file);
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-env-changed=LP_BUILD_VERSION");
+    println!("cargo:rerun-if-env-changed=LP_BUILD_GIT_HASH");
+}
+
+/// Generate the build-info record (see `common::build_info`), as a Rust
+/// expression.
+fn build_info() -> String {
+    let version =
+        env::var("LP_BUILD_VERSION").unwrap_or_else(|_| env::var("CARGO_PKG_VERSION").unwrap());
+    let git_hash = env::var("LP_BUILD_GIT_HASH").unwrap_or_else(|_| git_hash());
+    // Honour SOURCE_DATE_EPOCH, for reproducible builds
+    let timestamp = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse().expect("SOURCE_DATE_EPOCH must be a number"),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+    let profile = env::var("PROFILE").unwrap_or_default();
+    let target = env::var("TARGET").unwrap_or_default();
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    let target_features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+
+    let mut record = Vec::new();
+    record.extend_from_slice(&BUILD_INFO_MAGIC.to_le_bytes());
+    record.extend_from_slice(&BUILD_INFO_LAYOUT.to_le_bytes());
+    push_str(&mut record, "version", &version, 16);
+    push_str(&mut record, "git hash", &git_hash, 48);
+    record.extend_from_slice(&timestamp.to_le_bytes());
+    push_str(&mut record, "profile", &profile, 16);
+    push_str(&mut record, "target", &target, 40);
+    push_str(&mut record, "features", &features.join(","), 256);
+    push_str(&mut record, "target features", &target_features, 256);
+    let board = if feature("TM4C123G") {
+        BOARD_TIVA
+    } else {
+        BOARD_STELLARIS
+    };
+    push_str(&mut record, "board", board, 20);
+    let crc = crc::crc32(&record);
+
+    format!("BuildInfo {{ header: {:?}, crc: 0x{:08x} }}", record, crc)
+}
+
+/// Generate the pin-mux types and tables (see `board::pinmux`) from the
+/// datasheet's table of alternate functions.
+fn pinmux(csv: &str) -> String {
+    // (pin, pctl or None for analog, function), in the order given
+    let mut rows = Vec::new();
+    for line in csv.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').collect();
+        let pctl = match fields[1] {
+            "A" => None,
+            value => Some(value.parse::<u8>().expect("bad PCTL value in pinmux.csv")),
+        };
+        rows.push((fields[0], pctl, fields[2]));
+    }
+    let mut pins: Vec<&str> = rows.iter().map(|row| row.0).collect();
+    pins.dedup();
+    let mut functions: Vec<&str> = rows.iter().map(|row| row.2).collect();
+    functions.sort();
+    functions.dedup();
+
+    let mut out = String::new();
+    for pin in &pins {
+        out += &format!(
+            "#[doc = \"Pin {pin}, as the HAL hands it out\"]\npub type {pin} = gpio::gpio{port}::{pin}<gpio::Tristate>;\n\
+             impl Pin for {pin} {{ const PORT: Port = Port::{port_upper}; const NUMBER: u8 = {number}; const NAME: &'static str = \"{pin}\"; }}\n",
+            pin = pin,
+            port = pin[1..2].to_lowercase(),
+            port_upper = &pin[1..2],
+            number = &pin[2..],
+        );
+    }
+    for function in &functions {
+        out += &format!(
+            "{cfg}#[doc = \"The {function} function\"]\npub struct {function}(());\n\
+             {cfg}impl Function for {function} {{ const NAME: &'static str = \"{function}\"; const OPEN_DRAIN: bool = {open_drain}; }}\n",
+            cfg = cfg(function),
+            function = function,
+            open_drain = function.ends_with("SDA"),
+        );
+    }
+    for (pin, pctl, function) in &rows {
+        out += &format!(
+            "{}impl OnPin<{}> for {} {{ const SELECT: Select = {}; }}\n",
+            cfg(function),
+            pin,
+            function,
+            select(*pctl)
+        );
+    }
+
+    // Ports A to E - port F has the LEDs and buttons, which `Board` has
+    let ports = ["a", "b", "c", "d", "e"];
+    let available: Vec<&str> = pins
+        .iter()
+        .cloned()
+        .filter(|pin| !pin.starts_with("PF"))
+        .collect();
+    out += "/// The pins on ports A to E, to give functions to or use with the HAL.\npub struct Pins {\n";
+    for port in &ports {
+        out += &format!(
+            "    #[doc = \"GPIO control for port {upper}\"]\n    pub port{port}_control: gpio::gpio{port}::GpioControl,\n",
+            port = port,
+            upper = port.to_uppercase()
+        );
+    }
+    for pin in &available {
+        let jtag = if ["PC0", "PC1", "PC2", "PC3"].contains(pin) {
+            " - JTAG, so giving it a function cuts off the debugger"
+        } else {
+            ""
+        };
+        out += &format!(
+            "    #[doc = \"{}{}\"]\n    pub {}: {},\n",
+            pin,
+            jtag,
+            pin.to_lowercase(),
+            pin
+        );
+    }
+    out += "}\n\nfn pins(";
+    for port in &ports {
+        out += &format!("port{port}: gpio::gpio{port}::Parts, ", port = port);
+    }
+    out += ") -> Pins {\n    Pins {\n";
+    for port in &ports {
+        out += &format!(
+            "        port{port}_control: port{port}.control,\n",
+            port = port
+        );
+    }
+    for pin in &available {
+        out += &format!(
+            "        {}: port{}.{},\n",
+            pin.to_lowercase(),
+            pin[1..2].to_lowercase(),
+            pin.to_lowercase()
+        );
+    }
+    out += "    }\n}\n\n/// One of each function.\npub struct Functions {\n";
+    for function in &functions {
+        out += &format!(
+            "    #[doc = \"{}\"]\n    {}pub {}: {},\n",
+            function,
+            cfg(function),
+            function.to_lowercase(),
+            function
+        );
+    }
+    out += "}\n\nfn functions() -> Functions {\n    Functions {\n";
+    for function in &functions {
+        out += &format!(
+            "        {}{}: {}(()),\n",
+            cfg(function),
+            function.to_lowercase(),
+            function
+        );
+    }
+    out += "    }\n}\n\n";
+
+    out += &format!(
+        "/// Every pin, with all its functions (including the TM4C123-only ones).\npub const PINS: [PinInfo; {}] = [\n",
+        pins.len()
+    );
+    for pin in &pins {
+        out += &format!(
+            "    PinInfo {{ name: \"{}\", port: Port::{}, number: {}, functions: &[",
+            pin,
+            &pin[1..2],
+            &pin[2..]
+        );
+        for (_, pctl, function) in rows.iter().filter(|row| row.0 == *pin) {
+            out += &format!("({}, \"{}\"), ", select(*pctl), function);
+        }
+        out += "] },\n";
+    }
+    out += "];\n";
+    out
+}
+
+/// The PWM and QEI modules, and the second CAN controller, are only on the
+/// TM4C123 - so their functions only exist with the `tm4c123g` feature.
+fn cfg(function: &str) -> &'static str {
+    let tm4c123_only = [
+        "M0PWM", "M1PWM", "M0FAULT", "M1FAULT", "PhA", "PhB", "IDX", "CAN1",
+    ];
+    if tm4c123_only
+        .iter()
+        .any(|prefix| function.starts_with(prefix))
+    {
+        "#[cfg(feature = \"tm4c123g\")] "
+    } else {
+        ""
+    }
+}
+
+/// How a function is selected, as a `board::pinmux::Select`.
+fn select(pctl: Option<u8>) -> String {
+    match pctl {
+        Some(value) => format!("Select::Digital({})", value),
+        None => String::from("Select::Analog"),
+    }
+}
+
+/// The commit we're building, with `-dirty` on the end if there are
+/// uncommitted changes.
+fn git_hash() -> String {
+    let run = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    match run(&["rev-parse", "HEAD"]) {
+        Some(hash) => match run(&["status", "--porcelain", "--untracked-files=no"]) {
+            Some(ref status) if !status.is_empty() => format!("{}-dirty", hash),
+            _ => hash,
+        },
+        None => String::from("unknown"),
+    }
+}
+
+/// Append a string, padded with NULs to `len` bytes. The layout is fixed, so
+/// a string which doesn't fit stops the build rather than being cut short.
+fn push_str(record: &mut Vec<u8>, field: &str, value: &str, len: usize) {
+    if value.len() > len {
+        panic!(
+            "The build-info {} is {} bytes, but there's only room for {}: {:?}",
+            field,
+            value.len(),
+            len,
+            value
+        );
+    }
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    record.extend_from_slice(&bytes);
+}
+
+/// Is the given cargo feature (in SHOUTY_CASE) enabled?
+fn feature(name: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", name)).is_some()
+}
+
+fn hex(value: u32) -> String {
+    format!("0x{:08x}", value)
 }
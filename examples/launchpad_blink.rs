@@ -53,16 +53,13 @@ use tm4c123x_hal::time::Bps;
 // ****************************************************************************
 #[no_mangle]
 pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
-    let mut pins_a = board.GPIO_PORTA.split(&board.power_control);
-    let mut uart = serial::Serial::uart0(
-        board.UART0,
+    let chip = board.chip_info();
+    let mut pins_a = board.gpio.porta;
+    let mut uart = board.serial.uart0.serial(
         pins_a.pa1.into_af_push_pull(&mut pins_a.control),
         pins_a.pa0.into_af_push_pull(&mut pins_a.control),
-        (),
-        (),
         Bps(115200),
         serial::NewlineMode::SwapLFtoCRLF,
-        stellaris_launchpad::board::clocks(),
         &board.power_control,
     );
     let mut delay = tm4c123x_hal::delay::Delay::new(
@@ -71,7 +68,10 @@ pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
     );
     let mut loops = 0;
 
-    let mut blue_led_pwm = tm4c123x_hal::pwm::Timer::timer1(&board.power_control, board.TIMER1)
+    let mut blue_led_pwm = board
+        .timers
+        .timer1
+        .pwm(&board.power_control)
         .into_even(board.led_blue.into_af_push_pull(&mut board.portf_control));
 
     blue_led_pwm.set_period(4096u32);
@@ -80,12 +80,47 @@ pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
 
     let levels = [1u32, 256, 512, 1024, 2048, 4096];
     uart.write_all("Welcome to Launchpad Blink\n");
+    let build = stellaris_launchpad::board::build_info();
+    writeln!(
+        uart,
+        "Version {} ({}, {}), built {}",
+        build.version(),
+        build.git_hash(),
+        build.profile(),
+        build.timestamp()
+    )
+    .unwrap();
+    writeln!(
+        uart,
+        "Image integrity: {:?}",
+        stellaris_launchpad::board::Board::image_integrity()
+    )
+    .unwrap();
+    writeln!(
+        uart,
+        "Chip: {:?} {:?}, {} KiB flash, {} KiB SRAM",
+        chip.part(),
+        chip.revision(),
+        chip.flash_size() / 1024,
+        chip.sram_size() / 1024
+    )
+    .unwrap();
+
+    // If the boot loader started us after an update, tell it we work
+    let mut flash = board.system.flash;
+    if let Err(e) = stellaris_launchpad::board::update::confirm(&mut flash) {
+        writeln!(uart, "Couldn't confirm the update: {:?}", e).unwrap();
+    }
+
     loop {
         for level in &levels {
             blue_led_pwm.set_duty((), *level);
             writeln!(uart, "Hello, world! Loops = {}, level = {}", loops, level).unwrap();
             while let Ok(ch) = uart.read() {
                 writeln!(uart, "byte read {}", ch).unwrap();
+                if ch == b'm' {
+                    stellaris_launchpad::common::memory::report(&mut uart).unwrap();
+                }
             }
             loops = loops + 1;
             delay.delay_ms(250u32);
@@ -54,16 +54,12 @@ use tm4c123x_hal::time::Bps;
 
 #[no_mangle]
 fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
-    let mut pins_a = board.GPIO_PORTA.split(&board.power_control);
-    let mut uart = serial::Serial::uart0(
-        board.UART0,
+    let mut pins_a = board.gpio.porta;
+    let mut uart = board.serial.uart0.serial(
         pins_a.pa1.into_af_push_pull(&mut pins_a.control),
         pins_a.pa0.into_af_push_pull(&mut pins_a.control),
-        (),
-        (),
         Bps(115200),
         serial::NewlineMode::SwapLFtoCRLF,
-        stellaris_launchpad::board::clocks(),
         &board.power_control,
     );
     let mut delay = tm4c123x_hal::delay::Delay::new(
@@ -72,13 +68,15 @@ fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
     );
 
     let mut loops = 0;
-    let mut tr = tm4c123x_hal::pwm::Timer::timer0(&board.power_control, board.TIMER0)
+    let mut tr = board
+        .timers
+        .timer0
+        .pwm(&board.power_control)
         .into_odd(board.led_red.into_af_push_pull(&mut board.portf_control));
-    let (mut tb, mut tg) = tm4c123x_hal::pwm::Timer::timer1(&board.power_control, board.TIMER1)
-        .into_both(
-            board.led_blue.into_af_push_pull(&mut board.portf_control),
-            board.led_green.into_af_push_pull(&mut board.portf_control),
-        );
+    let (mut tb, mut tg) = board.timers.timer1.pwm(&board.power_control).into_both(
+        board.led_blue.into_af_push_pull(&mut board.portf_control),
+        board.led_green.into_af_push_pull(&mut board.portf_control),
+    );
 
     tr.set_period(255u32);
     tr.set_duty((), 0);
@@ -0,0 +1,235 @@
+//! Times writing to an 8-bit parallel bus three ways, and prints the results
+//! every second:
+//!
+//! * `hal pins` - eight HAL pins (PE0 to PE5, PA6, PA7), one `OutputPin`
+//!   call per bit, strobing PA5
+//! * `masked pins` - the same, but with one-pin `PortMask`s on port B,
+//!   strobing PD2
+//! * `parallel bus` - a `ParallelBus` on port B, strobing PD2
+//!
+//! The HAL only drives pins through the APB aperture, so ports B and D are
+//! on APB too, and the three are compared like for like. Change `APERTURE`
+//! to `Aperture::Ahb` to see what AHB adds.
+//!
+//! Nothing needs to be connected, but a scope on the strobes is instructive.
+//! This example uses launchpad-rs.
+
+#![no_std]
+#![no_main]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate embedded_hal;
+extern crate stellaris_launchpad;
+extern crate tm4c123x_hal;
+
+use core::fmt::Write;
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+use stellaris_launchpad::board::groups::IntoAperture;
+use stellaris_launchpad::board::parallel_bus::{NoPin, ParallelBus};
+use stellaris_launchpad::board::port_mask::Aperture;
+use stellaris_launchpad::common::profiling::{self, Stopwatch};
+use tm4c123x_hal::gpio::GpioExt;
+use tm4c123x_hal::serial;
+use tm4c123x_hal::time::Bps;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// How many bytes each run writes
+const BYTES: usize = 256;
+
+/// How many runs each stopwatch times, between reports
+const RUNS: u32 = 100;
+
+/// How ports B and D are driven
+const APERTURE: Aperture = Aperture::Apb;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+#[no_mangle]
+pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
+    let mut pins_a = board.gpio.porta;
+    let mut uart = board.serial.uart0.serial(
+        pins_a.pa1.into_af_push_pull(&mut pins_a.control),
+        pins_a.pa0.into_af_push_pull(&mut pins_a.control),
+        Bps(115200),
+        serial::NewlineMode::SwapLFtoCRLF,
+        &board.power_control,
+    );
+    let mut delay = tm4c123x_hal::delay::Delay::new(
+        board.core_peripherals.SYST,
+        stellaris_launchpad::board::clocks(),
+    );
+    profiling::enable(
+        &mut board.core_peripherals.DCB,
+        &mut board.core_peripherals.DWT,
+    );
+
+    // Eight HAL pins, through APB
+    let pins_e = board.gpio.porte;
+    let mut e0 = pins_e.pe0.into_push_pull_output();
+    let mut e1 = pins_e.pe1.into_push_pull_output();
+    let mut e2 = pins_e.pe2.into_push_pull_output();
+    let mut e3 = pins_e.pe3.into_push_pull_output();
+    let mut e4 = pins_e.pe4.into_push_pull_output();
+    let mut e5 = pins_e.pe5.into_push_pull_output();
+    let mut a6 = pins_a.pa6.into_push_pull_output();
+    let mut a7 = pins_a.pa7.into_push_pull_output();
+    let mut hal_wr = pins_a.pa5.into_push_pull_output();
+
+    // Port B and PD2, through the same aperture
+    let mut portb = board.gpio.portb.into_masked(&board.power_control, APERTURE);
+    let mut portd = board.gpio.portd.into_masked(&board.power_control, APERTURE);
+    let mut wr = portd.outputs(1 << 2).unwrap();
+
+    let mut bytes = [0u8; BYTES];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = index as u8;
+    }
+
+    let mut hal_pins = Stopwatch::new("hal pins");
+    let mut masked_pins = Stopwatch::new("masked pins");
+    let mut parallel_bus = Stopwatch::new("parallel bus");
+    loop {
+        for _ in 0..RUNS {
+            hal_pins.time(|| {
+                for &byte in bytes.iter() {
+                    drive(&mut e0, byte & 0x01);
+                    drive(&mut e1, byte & 0x02);
+                    drive(&mut e2, byte & 0x04);
+                    drive(&mut e3, byte & 0x08);
+                    drive(&mut e4, byte & 0x10);
+                    drive(&mut e5, byte & 0x20);
+                    drive(&mut a6, byte & 0x40);
+                    drive(&mut a7, byte & 0x80);
+                    strobe(&mut hal_wr);
+                }
+            });
+        }
+
+        // Eight one-pin masks, then the whole port as a bus - each pin is
+        // only in one `PortMask` at a time, so they take turns
+        let mut b0 = portb.outputs(0x01).unwrap();
+        let mut b1 = portb.outputs(0x02).unwrap();
+        let mut b2 = portb.outputs(0x04).unwrap();
+        let mut b3 = portb.outputs(0x08).unwrap();
+        let mut b4 = portb.outputs(0x10).unwrap();
+        let mut b5 = portb.outputs(0x20).unwrap();
+        let mut b6 = portb.outputs(0x40).unwrap();
+        let mut b7 = portb.outputs(0x80).unwrap();
+        for _ in 0..RUNS {
+            masked_pins.time(|| {
+                for &byte in bytes.iter() {
+                    drive(&mut b0, byte & 0x01);
+                    drive(&mut b1, byte & 0x02);
+                    drive(&mut b2, byte & 0x04);
+                    drive(&mut b3, byte & 0x08);
+                    drive(&mut b4, byte & 0x10);
+                    drive(&mut b5, byte & 0x20);
+                    drive(&mut b6, byte & 0x40);
+                    drive(&mut b7, byte & 0x80);
+                    strobe(&mut wr);
+                }
+            });
+        }
+        portb.release(b0);
+        portb.release(b1);
+        portb.release(b2);
+        portb.release(b3);
+        portb.release(b4);
+        portb.release(b5);
+        portb.release(b6);
+        portb.release(b7);
+
+        let data = portb.outputs(0xFF).unwrap();
+        let mut bus = ParallelBus::new(data, wr, NoPin, NoPin).unwrap();
+        for _ in 0..RUNS {
+            parallel_bus.time(|| bus.write(&bytes));
+        }
+        let (data, bus_wr, _, _) = bus.release();
+        portb.release(data);
+        wr = bus_wr;
+
+        writeln!(uart, "Writing {} bytes:", BYTES).unwrap();
+        profiling::report(&mut uart, &[&hal_pins, &masked_pins, &parallel_bus]).unwrap();
+        for stopwatch in &[&hal_pins, &masked_pins, &parallel_bus] {
+            if let Some(mean) = stopwatch.mean() {
+                writeln!(
+                    uart,
+                    "{:<16} {:>8} cycles per byte",
+                    stopwatch.name(),
+                    mean / BYTES as u32
+                )
+                .unwrap();
+            }
+        }
+        hal_pins.reset();
+        masked_pins.reset();
+        parallel_bus.reset();
+        delay.delay_ms(1000u32);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Drive one data line, the way you would without a `PortMask`.
+fn drive<P: OutputPin>(pin: &mut P, bit: u8) {
+    let _ = if bit != 0 {
+        pin.set_high()
+    } else {
+        pin.set_low()
+    };
+}
+
+/// Pulse WR low, to latch the data lines.
+fn strobe<P: OutputPin>(wr: &mut P) {
+    let _ = wr.set_low();
+    let _ = wr.set_high();
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
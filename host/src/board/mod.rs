@@ -0,0 +1,16 @@
+//! The firmware's `board` modules which (partly) build on the host.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[path = "../../../src/board/flash.rs"]
+pub mod flash;
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
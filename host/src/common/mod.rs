@@ -0,0 +1,34 @@
+//! The firmware's `common` modules which build on the host.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[path = "../../../src/common/crc.rs"]
+pub mod crc;
+#[path = "../../../src/common/kvstore.rs"]
+pub mod kvstore;
+#[path = "../../../src/common/launchpad.rs"]
+pub mod launchpad;
+#[path = "../../../src/common/mpu.rs"]
+pub mod mpu;
+#[path = "../../../src/common/ram_flash.rs"]
+pub mod ram_flash;
+#[path = "../../../src/common/settings.rs"]
+pub mod settings;
+#[path = "../../../src/common/signed_image.rs"]
+pub mod signed_image;
+#[path = "../../../src/common/sim_board.rs"]
+pub mod sim_board;
+#[path = "../../../src/common/update.rs"]
+pub mod update;
+#[path = "../../../src/common/update_protocol.rs"]
+pub mod update_protocol;
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,44 @@
+//! The parts of stellaris-launchpad which don't touch the hardware, built
+//! for the host.
+//!
+//! The firmware crate only builds for `thumbv7em-none-eabihf`, so its tests
+//! can't run there. This crate includes the same source files, under the
+//! same paths (`stellaris_launchpad::common::...`), so they can be tested on
+//! a PC - the tests are in `tests/`, and the doc examples run too. Anything
+//! which needs the hardware is left out with `#[cfg(target_arch = "arm")]`.
+//!
+//! Run the tests with `make test`, or give cargo an explicit host target, as
+//! the repository is configured to build for the Launchpad.
+//!
+//! The host tools (`tools/`) use this crate too, for the boot loader's
+//! serial protocol, the signed image header and the CRC, so both ends share
+//! one definition.
+
+#![no_std]
+#![warn(dead_code)]
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Crates
+//
+// ****************************************************************************
+
+extern crate ed25519_compact;
+extern crate embedded_storage;
+extern crate sha2;
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+pub mod board;
+pub mod common;
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,178 @@
+//! Tests for the `NorFlash` contract, as `board::flash::Flash` (its checks
+//! and write buffer splitting) and `common::ram_flash::RamFlash` keep it.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate embedded_storage;
+extern crate stellaris_launchpad;
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+use stellaris_launchpad::board::flash::{self, Error, FLASH_SIZE, PAGE_SIZE, WRITE_BUFFER_WORDS};
+use stellaris_launchpad::common::ram_flash::RamFlash;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type Small = RamFlash<4096>;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn read(flash: &mut Small, offset: u32, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    flash.read(offset, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn ram_flash_matches_flash() {
+    assert_eq!(Small::WRITE_SIZE, 4);
+    assert_eq!(Small::ERASE_SIZE, PAGE_SIZE as usize);
+    assert_eq!(Small::READ_SIZE, 1);
+}
+
+#[test]
+fn flash_checks_alignment_and_bounds() {
+    let writes = [
+        (0x1000, 4, Ok(())),
+        (0x1000, 0, Ok(())),
+        (0x1002, 4, Err(Error::NotAligned)),
+        (0x1000, 6, Err(Error::NotAligned)),
+        (FLASH_SIZE - 4, 4, Ok(())),
+        (FLASH_SIZE - 4, 8, Err(Error::OutOfBounds)),
+        (FLASH_SIZE, 4, Err(Error::OutOfBounds)),
+        (0xFFFF_FFFC, 8, Err(Error::OutOfBounds)),
+    ];
+    for &(address, length, result) in writes.iter() {
+        assert_eq!(
+            flash::check_write(address, length),
+            result,
+            "write 0x{:x} {}",
+            address,
+            length
+        );
+    }
+    let erases = [
+        (0x1000, 1024, Ok(())),
+        (0x1000, 3072, Ok(())),
+        (0x1200, 1024, Err(Error::NotAligned)),
+        (0x1000, 512, Err(Error::NotAligned)),
+        (FLASH_SIZE - 1024, 1024, Ok(())),
+        (FLASH_SIZE, 1024, Err(Error::OutOfBounds)),
+    ];
+    for &(address, length, result) in erases.iter() {
+        assert_eq!(
+            flash::check_erase(address, length),
+            result,
+            "erase 0x{:x} {}",
+            address,
+            length
+        );
+    }
+    assert_eq!(Error::NotAligned.kind(), NorFlashErrorKind::NotAligned);
+    assert_eq!(Error::OutOfBounds.kind(), NorFlashErrorKind::OutOfBounds);
+}
+
+#[test]
+fn ram_flash_checks_alignment_and_bounds() {
+    let mut flash = Small::new();
+    assert_eq!(flash.write(2, &[0; 4]), Err(NorFlashErrorKind::NotAligned));
+    assert_eq!(flash.write(0, &[0; 3]), Err(NorFlashErrorKind::NotAligned));
+    assert_eq!(
+        flash.write(4096, &[0; 4]),
+        Err(NorFlashErrorKind::OutOfBounds)
+    );
+    assert_eq!(flash.erase(512, 1536), Err(NorFlashErrorKind::NotAligned));
+    assert_eq!(flash.erase(0, 1000), Err(NorFlashErrorKind::NotAligned));
+    assert_eq!(flash.erase(3072, 5120), Err(NorFlashErrorKind::OutOfBounds));
+    assert_eq!(
+        flash.read(4095, &mut [0; 2]),
+        Err(NorFlashErrorKind::OutOfBounds)
+    );
+    // Nothing was touched
+    assert_eq!(flash.writes(), 0);
+    assert_eq!(flash.erases(), 0);
+    assert!(flash.as_bytes().iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn writes_only_clear_bits_until_erased() {
+    let mut flash = Small::new();
+    flash.write(1024, &[0x0F, 0xF0, 0x55, 0xAA]).unwrap();
+    assert_eq!(read(&mut flash, 1024, 4), [0x0F, 0xF0, 0x55, 0xAA]);
+    // Writing again without erasing ANDs
+    flash.write(1024, &[0xFF, 0x3C, 0xF0, 0xFF]).unwrap();
+    assert_eq!(read(&mut flash, 1024, 4), [0x0F, 0x30, 0x50, 0xAA]);
+    // Erasing sets the whole page, and only that page
+    flash.write(2048, &[0; 4]).unwrap();
+    flash.erase(1024, 2048).unwrap();
+    assert_eq!(read(&mut flash, 1024, 4), [0xFF; 4]);
+    assert_eq!(read(&mut flash, 2048, 4), [0; 4]);
+    flash.write(1024, &[1, 2, 3, 4]).unwrap();
+    assert_eq!(read(&mut flash, 1024, 4), [1, 2, 3, 4]);
+}
+
+#[test]
+fn reads_from_the_start_of_flash() {
+    // The real flash starts at address 0, so offset 0 must work like any other
+    let mut flash = Small::new();
+    flash.write(0, &[0x00, 0x80, 0x00, 0x20]).unwrap();
+    assert_eq!(read(&mut flash, 0, 4), [0x00, 0x80, 0x00, 0x20]);
+    assert_eq!(read(&mut flash, 0, 6), [0x00, 0x80, 0x00, 0x20, 0xFF, 0xFF]);
+    assert!(read(&mut flash, 0, 0).is_empty());
+}
+
+#[test]
+fn writes_cross_write_buffer_blocks() {
+    let block = (WRITE_BUFFER_WORDS * 4) as u32;
+    let mut flash = Small::new();
+    let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    // Starts two words before a block boundary, and ends in the next block
+    // but one
+    flash.write(block - 8, &data).unwrap();
+    assert_eq!(read(&mut flash, block - 8, 200), data);
+    assert_eq!(read(&mut flash, block - 12, 4), [0xFF; 4]);
+    assert_eq!(read(&mut flash, block - 8 + 200, 4), [0xFF; 4]);
+    assert_eq!(
+        flash::buffered_writes(block - 8, 200).collect::<Vec<_>>(),
+        [(block - 8, 2), (block, 32), (2 * block, 16)]
+    );
+}
+
+#[test]
+fn buffered_writes_never_cross_a_block() {
+    let block = (WRITE_BUFFER_WORDS * 4) as u32;
+    assert_eq!(flash::buffered_writes(0x1000, 0).count(), 0);
+    assert_eq!(
+        flash::buffered_writes(0x1000, 4 * block as usize).count(),
+        4
+    );
+    for start in (0x1000..0x1000 + block).step_by(4) {
+        for length in (0..600).step_by(4) {
+            let mut next = start;
+            for (address, words) in flash::buffered_writes(start, length) {
+                assert_eq!(address, next);
+                assert!((1..=WRITE_BUFFER_WORDS).contains(&words));
+                assert!(address % block + words as u32 * 4 <= block);
+                next = address + words as u32 * 4;
+            }
+            assert_eq!(next, start + length as u32);
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
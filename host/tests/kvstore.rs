@@ -0,0 +1,266 @@
+//! Tests for `common::kvstore`, over `RamFlash`: a long run of random
+//! operations checked against a `HashMap`, and a power cut after every
+//! possible number of flash writes.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate embedded_storage;
+extern crate stellaris_launchpad;
+
+use std::collections::HashMap;
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashErrorKind};
+use stellaris_launchpad::common::kvstore::{Error, KvStore, MAX_KEY};
+use stellaris_launchpad::common::ram_flash::RamFlash;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Four pages
+type Flash = RamFlash<4096>;
+
+type Store = KvStore<Flash>;
+
+/// What the store should hold
+type Model = HashMap<u16, Vec<u8>>;
+
+/// A small, repeatable, random number generator (xorshift32).
+struct Random(u32);
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Set(u16, Vec<u8>),
+    Remove(u16),
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const PAGES: u32 = 4;
+
+/// Few enough keys that they're often overwritten
+const KEYS: u16 = 8;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Random {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn below(&mut self, limit: u32) -> u32 {
+        self.next() % limit
+    }
+
+    fn operation(&mut self) -> Operation {
+        let key = self.below(u32::from(KEYS)) as u16;
+        if self.below(4) == 0 {
+            Operation::Remove(key)
+        } else {
+            let len = self.below(120) as usize;
+            let value = (0..len).map(|_| self.next() as u8).collect();
+            Operation::Set(key, value)
+        }
+    }
+}
+
+impl Operation {
+    fn apply(&self, store: &mut Store) -> Result<(), Error<NorFlashErrorKind>> {
+        match *self {
+            Operation::Set(key, ref value) => store.set(key, value),
+            Operation::Remove(key) => store.remove(key),
+        }
+    }
+
+    fn apply_to_model(&self, model: &mut Model) {
+        match *self {
+            Operation::Set(key, ref value) => {
+                model.insert(key, value.clone());
+            }
+            Operation::Remove(key) => {
+                model.remove(&key);
+            }
+        }
+    }
+
+    fn key(&self) -> u16 {
+        match *self {
+            Operation::Set(key, _) | Operation::Remove(key) => key,
+        }
+    }
+}
+
+fn open(flash: Flash) -> Store {
+    KvStore::open(flash, 0, PAGES).unwrap()
+}
+
+fn reopen(store: Store) -> Store {
+    let mut flash = store.free();
+    flash.restore();
+    open(flash)
+}
+
+fn get(store: &mut Store, key: u16) -> Option<Vec<u8>> {
+    let mut buffer = [0u8; 1024];
+    store
+        .get(key, &mut buffer)
+        .unwrap()
+        .map(|len| buffer[..len].to_vec())
+}
+
+fn check(store: &mut Store, model: &Model) {
+    for key in 0..KEYS {
+        assert_eq!(get(store, key), model.get(&key).cloned(), "key {}", key);
+        assert_eq!(store.contains(key).unwrap(), model.contains_key(&key));
+    }
+}
+
+/// A copy of a flash, with a working power supply.
+fn copy(flash: &Flash) -> Flash {
+    let mut copy = Flash::new();
+    copy.write(0, flash.as_bytes()).unwrap();
+    copy
+}
+
+#[test]
+fn rejects_bad_configurations() {
+    for &(start, pages) in [(0, 1), (0, 5), (512, 2), (3072, 2)].iter() {
+        assert_eq!(
+            KvStore::open(Flash::new(), start, pages).err(),
+            Some(Error::BadConfig),
+            "{} {}",
+            start,
+            pages
+        );
+    }
+}
+
+#[test]
+fn sets_gets_and_removes() {
+    let mut store = open(Flash::new());
+    assert_eq!(get(&mut store, 1), None);
+    store.set(1, b"hello").unwrap();
+    store.set(2, b"").unwrap();
+    assert_eq!(get(&mut store, 1), Some(b"hello".to_vec()));
+    assert_eq!(get(&mut store, 2), Some(Vec::new()));
+    assert_eq!(store.get(1, &mut [0; 4]), Err(Error::BufferTooSmall));
+    store.remove(1).unwrap();
+    assert_eq!(get(&mut store, 1), None);
+    assert_eq!(store.set(MAX_KEY + 1, b"x"), Err(Error::BadKey));
+    let too_big = vec![0; store.max_value_len() + 1];
+    assert_eq!(store.set(3, &too_big), Err(Error::TooBig));
+    let biggest = vec![0x5A; store.max_value_len()];
+    store.set(3, &biggest).unwrap();
+    let mut store = reopen(store);
+    assert_eq!(get(&mut store, 2), Some(Vec::new()));
+    assert_eq!(get(&mut store, 3), Some(biggest));
+    store.format().unwrap();
+    assert_eq!(get(&mut store, 3), None);
+}
+
+#[test]
+fn setting_the_same_value_writes_nothing() {
+    let mut store = open(Flash::new());
+    store.set(1, b"same").unwrap();
+    let mut flash = store.free();
+    let writes = flash.writes();
+    flash.fail_after(0);
+    let mut store = KvStore::open(flash, 0, PAGES).unwrap();
+    store.set(1, b"same").unwrap();
+    assert_eq!(store.free().writes(), writes);
+}
+
+#[test]
+fn matches_a_hash_map() {
+    for seed in 1..=4u32 {
+        let mut random = Random(seed.wrapping_mul(0x9E37_79B9));
+        let mut store = open(Flash::new());
+        let mut model = Model::new();
+        for step in 0..3000 {
+            let operation = random.operation();
+            operation.apply(&mut store).unwrap();
+            operation.apply_to_model(&mut model);
+            check(&mut store, &model);
+            if step % 97 == 0 {
+                store = reopen(store);
+                check(&mut store, &model);
+            }
+        }
+        // Plenty of garbage collection happened
+        assert!(store.free().erases() > 50);
+    }
+}
+
+#[test]
+fn survives_a_power_cut_after_any_write() {
+    let mut random = Random(0xC0FF_EE01);
+    let mut store = open(Flash::new());
+    let mut model = Model::new();
+    let mut erases = 0;
+    for _ in 0..200 {
+        let operation = random.operation();
+        let before = copy(&store.free());
+
+        // Cut the power after 0, 1, 2... writes or erases, until the
+        // operation has time to finish
+        for cut in 0.. {
+            let mut flash = copy(&before);
+            flash.fail_after(cut);
+            let mut store = KvStore::open(flash, 0, PAGES).unwrap();
+            let finished = operation.apply(&mut store).is_ok();
+
+            let mut store = reopen(store);
+            let key = operation.key();
+            let mut expected = model.clone();
+            if finished {
+                operation.apply_to_model(&mut expected);
+            } else if get(&mut store, key) != model.get(&key).cloned() {
+                // It's allowed to have happened, but nothing else is
+                operation.apply_to_model(&mut expected);
+            }
+            check(&mut store, &expected);
+
+            // The store still works, and keeps working after a restart
+            store.set(KEYS, &[cut as u8; 12]).unwrap();
+            let mut store = reopen(store);
+            check(&mut store, &expected);
+            assert_eq!(get(&mut store, KEYS), Some(vec![cut as u8; 12]));
+
+            if finished {
+                break;
+            }
+        }
+
+        store = open(copy(&before));
+        operation.apply(&mut store).unwrap();
+        operation.apply_to_model(&mut model);
+        let flash = store.free();
+        erases += flash.erases();
+        store = open(flash);
+    }
+    // Including some which collected garbage (two erases each)
+    assert!(erases > 10);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,167 @@
+//! Tests for `common::mpu::encode`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate stellaris_launchpad;
+
+use stellaris_launchpad::common::mpu::{
+    self, Access, Background, Error, MemoryType, Region, FOUR_GIB,
+};
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn with_subregions(region: Region, subregions_disabled: u8) -> Region {
+    Region {
+        subregions_disabled,
+        ..region
+    }
+}
+
+#[test]
+fn encodes_regions() {
+    let cases = [
+        // The smallest region: SIZE 4, AP 0b011, C, B, S and XN
+        (
+            1,
+            Region::ram_no_execute(0x2000_0000, 32),
+            0x2000_0011,
+            0x1307_0009,
+        ),
+        // The whole address space: SIZE 31, AP 0b001, B, S and XN
+        (
+            0,
+            Region::peripherals(0, FOUR_GIB),
+            0x0000_0010,
+            0x1105_003F,
+        ),
+        // 256 KiB of flash: SIZE 17, AP 0b110, C, executable
+        (
+            2,
+            Region::flash_read_only(0, 256 * 1024),
+            0x0000_0012,
+            0x0602_0023,
+        ),
+        // Sub-regions 0 and 7 disabled
+        (
+            3,
+            with_subregions(Region::flash_read_only(0, 256 * 1024), 0x81),
+            0x0000_0013,
+            0x0602_8123,
+        ),
+        // The smallest region with sub-regions
+        (
+            7,
+            with_subregions(Region::no_access(0x2000_7F00, 256), 0x01),
+            0x2000_7F17,
+            0x1000_010F,
+        ),
+    ];
+    for &(number, ref region, rbar, rasr) in cases.iter() {
+        assert_eq!(
+            mpu::encode(number, region),
+            Ok((rbar, rasr)),
+            "{:?}",
+            region
+        );
+    }
+}
+
+#[test]
+fn encodes_every_access_and_memory_type() {
+    let base = Region::no_access(0, 32);
+    let accesses = [
+        (Access::NoAccess, 0b000),
+        (Access::PrivilegedReadWrite, 0b001),
+        (Access::PrivilegedReadWriteUserReadOnly, 0b010),
+        (Access::ReadWrite, 0b011),
+        (Access::PrivilegedReadOnly, 0b101),
+        (Access::ReadOnly, 0b110),
+    ];
+    for &(access, ap) in accesses.iter() {
+        let region = Region { access, ..base };
+        let (_, rasr) = mpu::encode(0, &region).unwrap();
+        assert_eq!((rasr >> 24) & 0b111, ap, "{:?}", access);
+    }
+    // TEX, C and B
+    let memory_types = [
+        (MemoryType::StronglyOrdered, (0b000, 0, 0)),
+        (MemoryType::Device, (0b000, 0, 1)),
+        (MemoryType::WriteThrough, (0b000, 1, 0)),
+        (MemoryType::WriteBack, (0b000, 1, 1)),
+        (MemoryType::NonCacheable, (0b001, 0, 0)),
+        (MemoryType::WriteBackAllocate, (0b001, 1, 1)),
+    ];
+    for &(memory_type, bits) in memory_types.iter() {
+        let region = Region {
+            memory_type,
+            ..base
+        };
+        let (_, rasr) = mpu::encode(0, &region).unwrap();
+        let fields = ((rasr >> 19) & 0b111, (rasr >> 17) & 1, (rasr >> 16) & 1);
+        assert_eq!(fields, bits, "{:?}", memory_type);
+    }
+}
+
+#[test]
+fn rejects_bad_regions() {
+    let cases = [
+        (
+            8,
+            Region::ram_no_execute(0x2000_0000, 32),
+            Error::BadRegionNumber,
+        ),
+        (
+            0,
+            Region::ram_no_execute(0x2000_0000, 16),
+            Error::SizeTooSmall,
+        ),
+        (
+            0,
+            Region::ram_no_execute(0x2000_0000, 96),
+            Error::SizeNotPowerOfTwo,
+        ),
+        (
+            0,
+            Region::ram_no_execute(0x2000_0020, 64),
+            Error::BaseNotAligned,
+        ),
+        (
+            0,
+            Region::flash_read_only(0x0000_1000, 0x2000),
+            Error::BaseNotAligned,
+        ),
+        (
+            0,
+            Region::peripherals(0x4000_0000, FOUR_GIB),
+            Error::BaseNotAligned,
+        ),
+        (
+            0,
+            with_subregions(Region::ram_no_execute(0x2000_0000, 128), 0x01),
+            Error::SubregionsTooSmall,
+        ),
+    ];
+    for &(number, ref region, error) in cases.iter() {
+        assert_eq!(mpu::encode(number, region), Err(error), "{:?}", region);
+    }
+}
+
+#[test]
+fn encodes_ctrl() {
+    assert_eq!(mpu::encode_ctrl(Background::PrivilegedDefault), 0b101);
+    assert_eq!(mpu::encode_ctrl(Background::Fault), 0b001);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,376 @@
+//! Tests for `common::settings`: round trips, migration from an older
+//! version, damaged records, and keeping two copies through a power cut.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate stellaris_launchpad;
+
+use stellaris_launchpad::common::crc;
+use stellaris_launchpad::common::settings::{
+    self, Next, Outcome, Reader, Settings, TooBig, Writer, OVERHEAD,
+};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The first version of some settings
+#[derive(Debug, Default, PartialEq, Clone)]
+struct Old {
+    baud: u32,
+    brightness: u8,
+}
+
+/// The same settings, with a field added in version 2
+#[derive(Debug, Default, PartialEq, Clone)]
+struct New {
+    baud: u32,
+    brightness: u8,
+    inverted: bool,
+}
+
+/// One of everything
+#[derive(Debug, Default, PartialEq, Clone)]
+struct Everything {
+    a: bool,
+    b: u8,
+    c: u16,
+    d: u32,
+    e: i8,
+    f: i16,
+    g: i32,
+    h: f32,
+    name: [u8; 5],
+}
+
+/// Two copies, as `board::eeprom` keeps them
+type Copies = [[u8; LEN]; 2];
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const LEN: usize = 48;
+
+const OLD: Old = Old {
+    baud: 115_200,
+    brightness: 7,
+};
+
+const NEW: New = New {
+    baud: 9600,
+    brightness: 200,
+    inverted: true,
+};
+
+/// `OLD`, as `New` migrates it
+const OLD_AS_NEW: New = New {
+    baud: 115_200,
+    brightness: 7,
+    inverted: false,
+};
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Settings for Old {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, w: &mut Writer) {
+        w.u32(self.baud);
+        w.u8(self.brightness);
+    }
+
+    fn decode(_version: u16, r: &mut Reader) -> Option<Old> {
+        Some(Old {
+            baud: r.u32()?,
+            brightness: r.u8()?,
+        })
+    }
+}
+
+impl Settings for New {
+    const VERSION: u16 = 2;
+
+    fn encode(&self, w: &mut Writer) {
+        w.u32(self.baud);
+        w.u8(self.brightness);
+        w.bool(self.inverted);
+    }
+
+    fn decode(version: u16, r: &mut Reader) -> Option<New> {
+        let mut new = New {
+            baud: r.u32()?,
+            brightness: r.u8()?,
+            ..New::default()
+        };
+        if version >= 2 {
+            new.inverted = r.bool()?;
+        }
+        Some(new)
+    }
+}
+
+impl Settings for Everything {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, w: &mut Writer) {
+        w.bool(self.a);
+        w.u8(self.b);
+        w.u16(self.c);
+        w.u32(self.d);
+        w.i8(self.e);
+        w.i16(self.f);
+        w.i32(self.g);
+        w.f32(self.h);
+        w.bytes(&self.name);
+    }
+
+    fn decode(_version: u16, r: &mut Reader) -> Option<Everything> {
+        let mut everything = Everything {
+            a: r.bool()?,
+            b: r.u8()?,
+            c: r.u16()?,
+            d: r.u32()?,
+            e: r.i8()?,
+            f: r.i16()?,
+            g: r.i32()?,
+            h: r.f32()?,
+            ..Everything::default()
+        };
+        everything.name.copy_from_slice(r.bytes(5)?);
+        Some(everything)
+    }
+}
+
+fn record<S>(value: &S) -> Vec<u8>
+where
+    S: Settings,
+{
+    let mut buffer = [0xFFu8; LEN];
+    let len = settings::save(value, 0, &mut buffer).unwrap();
+    buffer[..len].to_vec()
+}
+
+/// Save `value` over the copy `load_newest` picks, as `board::eeprom` does,
+/// but lose power after writing `cut` bytes.
+fn save_copy(copies: &mut Copies, value: &New, cut: usize) {
+    let (_, _, next) = settings::load_newest::<New>([&copies[0], &copies[1]]);
+    let mut buffer = [0xFFu8; LEN];
+    let len = settings::save(value, next.sequence, &mut buffer).unwrap();
+    let len = len.min(cut);
+    copies[next.copy][..len].copy_from_slice(&buffer[..len]);
+}
+
+fn load_copies(copies: &Copies) -> (New, Outcome, Next) {
+    settings::load_newest([&copies[0], &copies[1]])
+}
+
+#[test]
+fn round_trips() {
+    let everything = Everything {
+        a: true,
+        b: 0xAB,
+        c: 0xBEEF,
+        d: 0xDEAD_BEEF,
+        e: -5,
+        f: -30_000,
+        g: -2_000_000_000,
+        h: 1.5,
+        name: *b"hello",
+    };
+    let record = record(&everything);
+    assert_eq!(record.len(), OVERHEAD + 24);
+    assert_eq!(settings::load(&record), (everything, Outcome::Loaded));
+    assert_eq!(settings::load(&self::record(&NEW)), (NEW, Outcome::Loaded));
+}
+
+#[test]
+fn migrates_an_older_version() {
+    let (new, outcome) = settings::load::<New>(&record(&OLD));
+    assert_eq!(outcome, Outcome::Migrated { from: 1 });
+    assert_eq!(new, OLD_AS_NEW);
+}
+
+#[test]
+fn refuses_a_newer_version() {
+    assert_eq!(
+        settings::load::<Old>(&record(&NEW)),
+        (Old::default(), Outcome::TooNew { version: 2 })
+    );
+}
+
+#[test]
+fn spots_blank_and_damaged_records() {
+    assert_eq!(
+        settings::load::<New>(&[0xFF; LEN]),
+        (New::default(), Outcome::Blank)
+    );
+    assert_eq!(settings::load::<New>(&[]).1, Outcome::Blank);
+
+    let record = record(&NEW);
+    // Any flipped bit is noticed. Damage to the magic number makes it look
+    // blank.
+    for byte in 0..record.len() {
+        for bit in 0..8 {
+            let mut damaged = record.clone();
+            damaged[byte] ^= 1 << bit;
+            let expected = if byte < 2 {
+                Outcome::Blank
+            } else {
+                Outcome::Corrupt
+            };
+            assert_eq!(
+                settings::load::<New>(&damaged),
+                (New::default(), expected),
+                "byte {} bit {}",
+                byte,
+                bit
+            );
+        }
+    }
+    // So is a record cut short
+    for len in OVERHEAD..record.len() {
+        assert_eq!(settings::load::<New>(&record[..len]).1, Outcome::Corrupt);
+    }
+}
+
+#[test]
+fn corrupt_when_decode_says_no() {
+    // A good CRC, but `inverted` is 2
+    let mut record = record(&NEW);
+    let len = record.len();
+    record[len - 5] = 2;
+    let crc = crc::crc32(&record[..len - 4]);
+    record[len - 4..].copy_from_slice(&crc.to_le_bytes());
+    assert_eq!(
+        settings::load::<New>(&record),
+        (New::default(), Outcome::Corrupt)
+    );
+}
+
+#[test]
+fn refuses_settings_which_do_not_fit() {
+    let mut buffer = [0u8; OVERHEAD + 5];
+    assert_eq!(settings::save(&NEW, 0, &mut buffer), Err(TooBig));
+    assert_eq!(settings::save(&NEW, 0, &mut buffer[..4]), Err(TooBig));
+    let mut buffer = [0u8; OVERHEAD + 6];
+    assert_eq!(settings::save(&NEW, 0, &mut buffer), Ok(OVERHEAD + 6));
+}
+
+#[test]
+fn alternates_between_two_copies() {
+    let mut copies = [[0xFFu8; LEN]; 2];
+    assert_eq!(
+        load_copies(&copies),
+        (
+            New::default(),
+            Outcome::Blank,
+            Next {
+                copy: 0,
+                sequence: 0
+            }
+        )
+    );
+    for i in 0..10u32 {
+        let value = New { baud: i, ..NEW };
+        save_copy(&mut copies, &value, LEN);
+        let (loaded, outcome, next) = load_copies(&copies);
+        assert_eq!((loaded, outcome), (value, Outcome::Loaded));
+        assert_eq!(next.copy, (i as usize + 1) % 2);
+        assert_eq!(next.sequence, i as u16 + 1);
+    }
+}
+
+#[test]
+fn sequence_numbers_wrap() {
+    let mut copies = [[0xFFu8; LEN]; 2];
+    let older = New { baud: 1, ..NEW };
+    let newer = New { baud: 2, ..NEW };
+    settings::save(&older, 0xFFFF, &mut copies[1]).unwrap();
+    settings::save(&newer, 0, &mut copies[0]).unwrap();
+    assert_eq!(
+        load_copies(&copies),
+        (
+            newer,
+            Outcome::Loaded,
+            Next {
+                copy: 1,
+                sequence: 1
+            }
+        )
+    );
+}
+
+#[test]
+fn falls_back_to_the_other_copy() {
+    // The newest copy is damaged, or `decode` rejects it
+    let mut copies = [[0xFFu8; LEN]; 2];
+    save_copy(&mut copies, &OLD_AS_NEW, LEN);
+    save_copy(&mut copies, &NEW, LEN);
+    copies[1][8] ^= 1;
+    assert_eq!(
+        load_copies(&copies),
+        (
+            OLD_AS_NEW,
+            Outcome::Loaded,
+            Next {
+                copy: 1,
+                sequence: 1
+            }
+        )
+    );
+
+    // Both are damaged
+    copies[0][8] ^= 1;
+    assert_eq!(
+        load_copies(&copies),
+        (
+            New::default(),
+            Outcome::Corrupt,
+            Next {
+                copy: 0,
+                sequence: 0
+            }
+        )
+    );
+}
+
+#[test]
+fn survives_a_power_cut_while_saving() {
+    let values: Vec<New> = (0..6).map(|i| New { baud: i, ..NEW }).collect();
+    let mut copies = [[0xFFu8; LEN]; 2];
+    save_copy(&mut copies, &values[0], LEN);
+    for pair in values.windows(2) {
+        // Cut the power after every possible number of bytes; you get the
+        // old settings or the new ones
+        for cut in 0..LEN {
+            let mut attempt = copies;
+            save_copy(&mut attempt, &pair[1], cut);
+            let (loaded, outcome, _) = load_copies(&attempt);
+            assert_eq!(outcome, Outcome::Loaded, "cut {}", cut);
+            assert!(loaded == pair[0] || loaded == pair[1], "cut {}", cut);
+            // And saving again works
+            save_copy(&mut attempt, &pair[1], LEN);
+            assert_eq!(load_copies(&attempt).0, pair[1]);
+        }
+        save_copy(&mut copies, &pair[1], LEN);
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
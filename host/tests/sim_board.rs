@@ -0,0 +1,146 @@
+//! Runs application code written against `common::launchpad::LaunchpadBoard`
+//! on `common::sim_board::SimBoard`, as you would test your own.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate stellaris_launchpad;
+
+use std::fmt::Write;
+
+use stellaris_launchpad::common::launchpad::{
+    Button, Header, HeaderPin, LaunchpadBoard, Led, Port,
+};
+use stellaris_launchpad::common::sim_board::{SimBoard, INPUT_LEN, OUTPUT_LEN};
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Some application code: light the green LED while SW1 is held, and echo
+/// the console back in upper case, a line at a time.
+fn poll<B: LaunchpadBoard>(board: &mut B, line: &mut String) {
+    let pressed = board.is_pressed(Button::One);
+    board.set_led(Led::Green, pressed);
+    while let Some(byte) = board.console_read() {
+        if byte == b'\r' {
+            writeln!(board.console(), "> {}", line.to_uppercase()).unwrap();
+            line.clear();
+        } else {
+            line.push(byte as char);
+        }
+    }
+    board.delay_ms(10);
+}
+
+/// More application code: blink the red LED `count` times.
+fn blink<B: LaunchpadBoard>(board: &mut B, count: u32) -> u32 {
+    let mut flashes = 0;
+    for _ in 0..count {
+        board.set_led(Led::Red, true);
+        if board.is_pressed(Button::Two) {
+            // Cut short
+            board.set_led(Led::Red, false);
+            break;
+        }
+        flashes += 1;
+        board.delay_ms(250);
+        board.set_led(Led::Red, false);
+        board.delay_ms(250);
+    }
+    flashes
+}
+
+#[test]
+fn lights_the_led_while_the_button_is_held() {
+    let mut board = SimBoard::new();
+    let mut line = String::new();
+    poll(&mut board, &mut line);
+    assert!(!board.led(Led::Green));
+    board.press(Button::One, true);
+    poll(&mut board, &mut line);
+    assert!(board.led(Led::Green));
+    assert!(!board.led(Led::Red) && !board.led(Led::Blue));
+    board.press(Button::One, false);
+    poll(&mut board, &mut line);
+    assert!(!board.led(Led::Green));
+    assert_eq!(board.elapsed_ms(), 30);
+}
+
+#[test]
+fn echoes_the_console() {
+    let mut board = SimBoard::new();
+    let mut line = String::new();
+    assert_eq!(board.type_input(b"hello\rwor"), 9);
+    poll(&mut board, &mut line);
+    assert_eq!(board.output(), b"> HELLO\n");
+    assert_eq!(line, "wor");
+
+    board.clear_output();
+    board.type_input(b"ld\r");
+    poll(&mut board, &mut line);
+    assert_eq!(board.output(), b"> WORLD\n");
+    assert_eq!(board.console_read(), None);
+}
+
+#[test]
+fn keeps_what_fits() {
+    let mut board = SimBoard::new();
+    let input = [b'x'; INPUT_LEN + 10];
+    assert_eq!(board.type_input(&input), INPUT_LEN);
+    assert_eq!(board.type_input(b"y"), 0);
+    for _ in 0..INPUT_LEN {
+        assert_eq!(board.console_read(), Some(b'x'));
+    }
+    // The queue wraps around
+    assert_eq!(board.type_input(b"yz"), 2);
+    assert_eq!(board.console_read(), Some(b'y'));
+    assert_eq!(board.console_read(), Some(b'z'));
+
+    board.console_write(&[b'a'; OUTPUT_LEN - 1]);
+    board.console_write(b"bcd");
+    assert_eq!(board.output().len(), OUTPUT_LEN);
+    assert_eq!(board.output()[OUTPUT_LEN - 1], b'b');
+    assert_eq!(board.dropped(), 2);
+}
+
+#[test]
+fn blinks_until_stopped() {
+    let mut board = SimBoard::new();
+    assert_eq!(blink(&mut board, 3), 3);
+    assert_eq!(board.elapsed_ms(), 1500);
+    assert!(!board.led(Led::Red));
+
+    board.press(Button::Two, true);
+    assert_eq!(blink(&mut board, 3), 0);
+    assert!(!board.led(Led::Red));
+    assert_eq!(board.elapsed_ms(), 1500);
+}
+
+#[test]
+fn has_the_stellaris_launchpad_headers() {
+    let board = SimBoard::new();
+    assert_eq!(board.header_pin(Header::J1, 1), Some(HeaderPin::Power3v3));
+    assert_eq!(
+        board.header_pin(Header::J1, 3),
+        Some(HeaderPin::Gpio(Port::B, 0))
+    );
+    assert_eq!(
+        board.header_pin(Header::J4, 10),
+        Some(HeaderPin::Gpio(Port::F, 4))
+    );
+    assert_eq!(board.header_pin(Header::J2, 0), None);
+    assert_eq!(board.header_pin(Header::J2, 11), None);
+    assert_eq!(board.name(), "sim");
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
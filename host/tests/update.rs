@@ -0,0 +1,371 @@
+//! Tests for `common::update` and `common::update_protocol`, over
+//! `RamFlash`: trial boots, rolling back and confirming, damaged images, and
+//! an update sent through the serial protocol.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate embedded_storage;
+extern crate stellaris_launchpad;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash};
+use stellaris_launchpad::common::crc;
+use stellaris_launchpad::common::ram_flash::RamFlash;
+use stellaris_launchpad::common::update::{
+    Error, Image, Layout, Region, Slot, State, Trial, Updater,
+};
+use stellaris_launchpad::common::update_protocol::{
+    self as protocol, Event, Server, ACK, BEGIN, BOOT, DATA, FINISH, HELLO, INFO, MAX_CHUNK,
+    MAX_FRAME, NAK, NAK_BAD_CRC, NAK_BAD_FRAME, NAK_NOT_STARTED, NAK_OUT_OF_ORDER, NAK_UNKNOWN,
+};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// 16 pages
+type Flash = RamFlash<16384>;
+
+type FlashError = <Flash as ErrorType>::Error;
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Two pages of metadata, then two slots of six pages each
+const LAYOUT: Layout = Layout {
+    slots: [
+        Region {
+            start: 0x0800,
+            size: 0x1800,
+        },
+        Region {
+            start: 0x2000,
+            size: 0x1800,
+        },
+    ],
+    metadata: Region {
+        start: 0x0000,
+        size: 0x0800,
+    },
+    ram: Region {
+        start: 0x2000_0000,
+        size: 0x8000,
+    },
+};
+
+const MAX_BOOTS: u8 = 3;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// An image of `len` bytes linked for `slot`: a vector table, then bytes
+/// which depend on `seed`.
+fn image(slot: Slot, len: usize, seed: u8) -> Vec<u8> {
+    let mut image: Vec<u8> = (0..len)
+        .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+        .collect();
+    image[0..4].copy_from_slice(&0x2000_8000u32.to_le_bytes());
+    let reset = LAYOUT.slot(slot).start + 0x41;
+    image[4..8].copy_from_slice(&reset.to_le_bytes());
+    image
+}
+
+fn open(flash: Flash) -> Updater<Flash> {
+    Updater::open(flash, LAYOUT).unwrap()
+}
+
+fn reopen(updater: Updater<Flash>) -> Updater<Flash> {
+    open(updater.free())
+}
+
+/// Receive an image, 100 bytes at a time.
+fn install(updater: &mut Updater<Flash>, image: &[u8]) -> Result<Slot, Error<FlashError>> {
+    updater.begin(image.len() as u32, crc::crc32(image))?;
+    for (i, chunk) in image.chunks(100).enumerate() {
+        updater.write(i as u32 * 100, chunk)?;
+    }
+    updater.finish()
+}
+
+/// An updater with a confirmed image in slot A.
+fn confirmed() -> Updater<Flash> {
+    let mut updater = open(Flash::new());
+    assert_eq!(install(&mut updater, &image(Slot::A, 1000, 1)), Ok(Slot::A));
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::A)));
+    updater.confirm(Slot::A).unwrap();
+    updater
+}
+
+/// Send a command to a server, one byte at a time, and decode its reply.
+fn command(server: &mut Server<Flash>, kind: u8, payload: &[u8]) -> (u8, Vec<u8>, bool) {
+    let mut frame = [0u8; MAX_FRAME];
+    let len = protocol::encode(kind, payload, &mut frame);
+    send(server, &frame[..len])
+}
+
+fn send(server: &mut Server<Flash>, bytes: &[u8]) -> (u8, Vec<u8>, bool) {
+    let mut reply = [0u8; MAX_FRAME];
+    for (i, byte) in bytes.iter().enumerate() {
+        let (len, boot) = match server.receive(*byte, &mut reply) {
+            Event::Nothing => continue,
+            Event::Reply(len) => (len, false),
+            Event::Boot(len) => (len, true),
+        };
+        assert_eq!(i, bytes.len() - 1, "replied early");
+        let mut decoder = protocol::Decoder::new();
+        for byte in &reply[..len - 1] {
+            assert_eq!(decoder.push(*byte), None);
+        }
+        let frame = decoder.push(reply[len - 1]).unwrap().unwrap();
+        return (frame.kind, frame.payload.to_vec(), boot);
+    }
+    panic!("no reply");
+}
+
+fn data(offset: u32, chunk: &[u8]) -> Vec<u8> {
+    let mut payload = offset.to_le_bytes().to_vec();
+    payload.extend_from_slice(chunk);
+    payload
+}
+
+#[test]
+fn rejects_bad_layouts() {
+    let mut layout = LAYOUT;
+    layout.slots[1].size = 0x2400;
+    assert_eq!(
+        Updater::open(Flash::new(), layout).err(),
+        Some(Error::BadConfig)
+    );
+    let mut layout = LAYOUT;
+    layout.metadata.start = 0x10;
+    assert_eq!(
+        Updater::open(Flash::new(), layout).err(),
+        Some(Error::BadConfig)
+    );
+}
+
+#[test]
+fn blank_flash_has_nothing_to_boot() {
+    let mut updater = open(Flash::new());
+    assert_eq!(updater.state(), State::default());
+    assert_eq!(updater.target(), Slot::A);
+    assert_eq!(updater.select(MAX_BOOTS), Ok(None));
+}
+
+#[test]
+fn a_trial_is_booted_max_boots_times_then_rolled_back() {
+    let mut updater = confirmed();
+    assert_eq!(updater.target(), Slot::B);
+    assert_eq!(install(&mut updater, &image(Slot::B, 3000, 2)), Ok(Slot::B));
+    for boots in 1..=MAX_BOOTS {
+        // Each boot is counted, even across a restart
+        updater = reopen(updater);
+        assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::B)));
+        assert_eq!(
+            updater.state().trial,
+            Some(Trial {
+                slot: Slot::B,
+                boots,
+            })
+        );
+    }
+    updater = reopen(updater);
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::A)));
+    let state = updater.state();
+    assert_eq!(state.trial, None);
+    assert_eq!(state.confirmed, Some(Slot::A));
+    // Confirming the abandoned image does nothing
+    updater.confirm(Slot::B).unwrap();
+    assert_eq!(reopen(updater).state(), state);
+}
+
+#[test]
+fn a_confirmed_trial_stays() {
+    let mut updater = confirmed();
+    let image = image(Slot::B, 2000, 3);
+    install(&mut updater, &image).unwrap();
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::B)));
+    updater.confirm(Slot::B).unwrap();
+    let mut updater = reopen(updater);
+    assert_eq!(updater.state().confirmed, Some(Slot::B));
+    assert_eq!(updater.state().trial, None);
+    assert_eq!(
+        updater.state().images[1],
+        Some(Image {
+            len: 2000,
+            crc: crc::crc32(&image),
+        })
+    );
+    for _ in 0..10 {
+        assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::B)));
+    }
+    // The next update goes over the old image
+    assert_eq!(updater.target(), Slot::A);
+}
+
+#[test]
+fn a_damaged_trial_is_rolled_back() {
+    let mut updater = confirmed();
+    install(&mut updater, &image(Slot::B, 2000, 4)).unwrap();
+    let mut flash = updater.free();
+    // Clear some bits in the middle of the image
+    flash.write(LAYOUT.slots[1].start + 500, &[0; 4]).unwrap();
+    let mut updater = open(flash);
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::A)));
+    assert_eq!(updater.state().trial, None);
+}
+
+#[test]
+fn a_damaged_confirmed_image_is_not_booted() {
+    let mut flash = confirmed().free();
+    flash.write(LAYOUT.slots[0].start + 12, &[0; 4]).unwrap();
+    assert_eq!(open(flash).select(MAX_BOOTS), Ok(None));
+}
+
+#[test]
+fn rejects_bad_images() {
+    let mut updater = confirmed();
+    let good = image(Slot::B, 1000, 5);
+
+    // The wrong CRC
+    updater.begin(1000, crc::crc32(&good) ^ 1).unwrap();
+    updater.write(0, &good).unwrap();
+    assert_eq!(updater.finish(), Err(Error::BadCrc));
+
+    // Linked for the other slot
+    assert_eq!(
+        install(&mut updater, &image(Slot::A, 1000, 5)),
+        Err(Error::BadImage)
+    );
+
+    // A stack pointer outside SRAM
+    let mut bad = good.clone();
+    bad[3] = 0x30;
+    assert_eq!(install(&mut updater, &bad), Err(Error::BadImage));
+
+    // Too big, or empty
+    assert_eq!(updater.begin(0x1801, 0), Err(Error::TooBig));
+    assert_eq!(updater.begin(0, 0), Err(Error::TooBig));
+
+    // None of which changed what gets booted
+    let mut updater = reopen(updater);
+    assert_eq!(updater.state().trial, None);
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::A)));
+}
+
+#[test]
+fn writes_must_be_in_order() {
+    let mut updater = confirmed();
+    assert_eq!(updater.write(0, &[0; 4]), Err(Error::NotStarted));
+    assert_eq!(updater.finish(), Err(Error::NotStarted));
+
+    let image = image(Slot::B, 1002, 6);
+    updater.begin(1002, crc::crc32(&image)).unwrap();
+    assert_eq!(updater.write(0, &image[..400]), Ok(400));
+    // The same piece again is fine
+    assert_eq!(updater.write(0, &image[..400]), Ok(400));
+    // A gap, going back further, or running past the end, isn't
+    assert_eq!(updater.write(404, &image[404..500]), Err(Error::OutOfOrder));
+    assert_eq!(updater.write(0, &image[..200]), Err(Error::OutOfOrder));
+    assert_eq!(updater.write(400, &[0; 700]), Err(Error::OutOfOrder));
+    // Only the last piece can be a part word
+    assert_eq!(updater.write(400, &image[400..501]), Err(Error::OutOfOrder));
+    assert_eq!(updater.finish(), Err(Error::OutOfOrder));
+    assert_eq!(updater.write(400, &image[400..800]), Ok(800));
+    assert_eq!(updater.write(400, &image[400..800]), Ok(800));
+    assert_eq!(updater.write(800, &image[800..]), Ok(1002));
+    assert_eq!(updater.finish(), Ok(Slot::B));
+}
+
+#[test]
+fn updates_through_the_protocol() {
+    let mut server = Server::new(confirmed());
+
+    let (kind, info, boot) = command(&mut server, HELLO, &[]);
+    assert_eq!((kind, boot), (INFO, false));
+    assert_eq!(&info[..4], &[protocol::VERSION, 1, 0, 0]);
+    assert_eq!(&info[4..8], &0x2000u32.to_le_bytes());
+    assert_eq!(&info[8..12], &0x1800u32.to_le_bytes());
+    assert_eq!(&info[12..14], &(MAX_CHUNK as u16).to_le_bytes());
+
+    // Data before BEGIN
+    assert_eq!(
+        command(&mut server, DATA, &data(0, &[0; 4])),
+        (NAK, vec![NAK_NOT_STARTED], false)
+    );
+
+    let image = image(Slot::B, 1000, 7);
+    let mut begin = (image.len() as u32).to_le_bytes().to_vec();
+    begin.extend_from_slice(&crc::crc32(&image).to_le_bytes());
+    assert_eq!(command(&mut server, BEGIN, &begin), (ACK, vec![1], false));
+
+    let chunks: Vec<&[u8]> = image.chunks(MAX_CHUNK).collect();
+    let mut offset = 0u32;
+    for (i, chunk) in chunks.iter().enumerate() {
+        let total = offset + chunk.len() as u32;
+        let ack = (ACK, total.to_le_bytes().to_vec(), false);
+        assert_eq!(command(&mut server, DATA, &data(offset, chunk)), ack);
+        // The host didn't hear the ACK, and sends it again
+        assert_eq!(command(&mut server, DATA, &data(offset, chunk)), ack);
+        if let Some(next) = chunks.get(i + 2) {
+            // A chunk from the future
+            let ahead = total + chunks[i + 1].len() as u32;
+            assert_eq!(
+                command(&mut server, DATA, &data(ahead, next)),
+                (NAK, vec![NAK_OUT_OF_ORDER], false)
+            );
+        }
+        offset = total;
+    }
+
+    // A damaged frame is thrown away, and the host is asked to try again
+    let mut frame = [0u8; MAX_FRAME];
+    let len = protocol::encode(FINISH, &[], &mut frame);
+    frame[1] ^= 0x40;
+    assert_eq!(
+        send(&mut server, &frame[..len]),
+        (NAK, vec![NAK_BAD_FRAME], false)
+    );
+    assert_eq!(
+        command(&mut server, 0x7F, &[]),
+        (NAK, vec![NAK_UNKNOWN], false)
+    );
+
+    assert_eq!(command(&mut server, FINISH, &[]), (ACK, vec![1], false));
+    assert_eq!(command(&mut server, BOOT, &[]), (ACK, vec![], true));
+
+    let mut updater = server.free();
+    assert_eq!(updater.select(MAX_BOOTS), Ok(Some(Slot::B)));
+}
+
+#[test]
+fn the_protocol_reports_a_bad_crc() {
+    let mut server = Server::new(confirmed());
+    let image = image(Slot::B, 200, 8);
+    let mut begin = (image.len() as u32).to_le_bytes().to_vec();
+    begin.extend_from_slice(&(crc::crc32(&image) + 1).to_le_bytes());
+    command(&mut server, BEGIN, &begin);
+    command(&mut server, DATA, &data(0, &image));
+    assert_eq!(
+        command(&mut server, FINISH, &[]),
+        (NAK, vec![NAK_BAD_CRC], false)
+    );
+    assert_eq!(server.free().state().trial, None);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
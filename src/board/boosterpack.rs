@@ -0,0 +1,465 @@
+//! The BoosterPack headers, J1 to J4.
+//!
+//! `position` says what is at each of the 40 positions - the pin (from
+//! `common::launchpad::HEADER_PINS`), its alternate functions (from
+//! `board::pinmux::PINS`), and anything else on the board it's wired to.
+//! `Headers` hands out the pins on ports A to E by position, as the HAL's
+//! pins, so you don't have to look them up:
+//!
+//! ```rust,ignore
+//! let mut headers = Headers::new(board.gpio);
+//! let rx = headers.j1_3().unwrap(); // PB0, which can be U1Rx
+//! let tx = headers.j1_4().unwrap(); // PB1, which can be U1Tx
+//! ```
+//!
+//! Each pin can only be taken once. The positions on port F are the
+//! on-board LEDs and buttons, which `Board` already has, so `Headers` doesn't
+//! hand those out. The pins on ports A to E which aren't on a header (PA0,
+//! PA1, PC0 to PC3, PD4 and PD5) are public fields, so nothing is lost. The
+//! HAL's pins only work through the APB aperture; if you want a port on AHB
+//! (or masked), move it to that aperture (see `board::groups::IntoAperture`)
+//! instead of using `Headers`. Watch out for:
+//!
+//! * PD0 (J3.3) and PB6 (J2.7), and PD1 (J3.4) and PB7 (J2.6), are joined by
+//!   0 ohm resistors (R9 and R10), for MSP430 BoosterPack compatibility.
+//!   Only drive one of each pair, or remove the resistor.
+//! * PF1 to PF3 (J3.10, J4.1 and J4.2) drive the RGB LED, and PF0 (J2.4) and
+//!   PF4 (J4.10) are SW2 and SW1.
+//! * PD7 (J4.9) and PF0 are locked at reset, as they can be NMI.
+//! * The PWM (`MnPWMn`, `MnFAULTn`) and QEI (`PhAn`, `PhBn`, `IDXn`)
+//!   functions are only on the TM4C123 - see the `tm4c123g` feature.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use tm4c123x_hal::gpio::gpioa::{self, PA0, PA1, PA2, PA3, PA4, PA5, PA6, PA7};
+use tm4c123x_hal::gpio::gpiob::{self, PB0, PB1, PB2, PB3, PB4, PB5, PB6, PB7};
+use tm4c123x_hal::gpio::gpioc::{self, PC0, PC1, PC2, PC3, PC4, PC5, PC6, PC7};
+use tm4c123x_hal::gpio::gpiod::{self, PD0, PD1, PD2, PD3, PD4, PD5, PD6, PD7};
+use tm4c123x_hal::gpio::gpioe::{self, PE0, PE1, PE2, PE3, PE4, PE5};
+use tm4c123x_hal::gpio::Tristate;
+
+use board::groups::Gpio;
+use board::pinmux::{Select, PINS};
+use common::launchpad::{Header, HeaderPin, Port, HEADER_PINS};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// What is at one position on a header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    /// The pin, or supply
+    pub pin: HeaderPin,
+    /// The pin's alternate functions, including analog ones (from
+    /// `board::pinmux::PINS`)
+    pub functions: &'static [(Select, &'static str)],
+    /// Anything else on the board the pin is wired to
+    pub shared: Option<&'static str>,
+}
+
+/// The header pins on ports A to E, ready to be taken by position, and the
+/// rest of those ports' pins.
+pub struct Headers {
+    /// GPIO control for port A
+    pub porta_control: gpioa::GpioControl,
+    /// GPIO control for port B
+    pub portb_control: gpiob::GpioControl,
+    /// GPIO control for port C
+    pub portc_control: gpioc::GpioControl,
+    /// GPIO control for port D
+    pub portd_control: gpiod::GpioControl,
+    /// GPIO control for port E
+    pub porte_control: gpioe::GpioControl,
+    /// PA0 - U0Rx, the console through the on-board debugger
+    pub pa0: PA0<Tristate>,
+    /// PA1 - U0Tx, the console through the on-board debugger
+    pub pa1: PA1<Tristate>,
+    /// PC0 - JTAG TCK / SWCLK; changing it cuts off the debugger
+    pub pc0: PC0<Tristate>,
+    /// PC1 - JTAG TMS / SWDIO; changing it cuts off the debugger
+    pub pc1: PC1<Tristate>,
+    /// PC2 - JTAG TDI; changing it cuts off the debugger
+    pub pc2: PC2<Tristate>,
+    /// PC3 - JTAG TDO / SWO; changing it cuts off the debugger
+    pub pc3: PC3<Tristate>,
+    /// PD4 - USB0DM, on the USB device connector
+    pub pd4: PD4<Tristate>,
+    /// PD5 - USB0DP, on the USB device connector
+    pub pd5: PD5<Tristate>,
+    pa2: Option<PA2<Tristate>>,
+    pa3: Option<PA3<Tristate>>,
+    pa4: Option<PA4<Tristate>>,
+    pa5: Option<PA5<Tristate>>,
+    pa6: Option<PA6<Tristate>>,
+    pa7: Option<PA7<Tristate>>,
+    pb0: Option<PB0<Tristate>>,
+    pb1: Option<PB1<Tristate>>,
+    pb2: Option<PB2<Tristate>>,
+    pb3: Option<PB3<Tristate>>,
+    pb4: Option<PB4<Tristate>>,
+    pb5: Option<PB5<Tristate>>,
+    pb6: Option<PB6<Tristate>>,
+    pb7: Option<PB7<Tristate>>,
+    pc4: Option<PC4<Tristate>>,
+    pc5: Option<PC5<Tristate>>,
+    pc6: Option<PC6<Tristate>>,
+    pc7: Option<PC7<Tristate>>,
+    pd0: Option<PD0<Tristate>>,
+    pd1: Option<PD1<Tristate>>,
+    pd2: Option<PD2<Tristate>>,
+    pd3: Option<PD3<Tristate>>,
+    pd6: Option<PD6<Tristate>>,
+    pd7: Option<PD7<Tristate>>,
+    pe0: Option<PE0<Tristate>>,
+    pe1: Option<PE1<Tristate>>,
+    pe2: Option<PE2<Tristate>>,
+    pe3: Option<PE3<Tristate>>,
+    pe4: Option<PE4<Tristate>>,
+    pe5: Option<PE5<Tristate>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Anything else on the board each position is wired to. Indexed like
+/// `HEADER_PINS`.
+const SHARED: [[Option<&str>; 10]; 4] = [
+    // J1
+    [None, None, None, None, None, None, None, None, None, None],
+    // J2
+    [
+        None,
+        None,
+        None,
+        Some("SW2 - board.button_two"),
+        None,
+        Some("tied to PD1 (J3.4) through R10"),
+        Some("tied to PD0 (J3.3) through R9"),
+        None,
+        None,
+        None,
+    ],
+    // J3
+    [
+        None,
+        None,
+        Some("tied to PB6 (J2.7) through R9"),
+        Some("tied to PB7 (J2.6) through R10"),
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("the red LED - board.led_red"),
+    ],
+    // J4
+    [
+        Some("the blue LED - board.led_blue"),
+        Some("the green LED - board.led_green"),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("locked at reset (it can be NMI) - unlock it before use"),
+        Some("SW1 - board.button_one"),
+    ],
+];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// What is at `position` (1 to 10) on a header.
+pub fn position(header: Header, position: u8) -> Option<Position> {
+    match position {
+        1..=10 => {
+            let (header, index) = (header as usize, usize::from(position) - 1);
+            Some(Position {
+                pin: HEADER_PINS[header][index],
+                functions: functions(HEADER_PINS[header][index]),
+                shared: SHARED[header][index],
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Where a GPIO pin is on the headers, if it is.
+pub fn find(port: Port, pin: u8) -> Option<(Header, u8)> {
+    let headers = [Header::J1, Header::J2, Header::J3, Header::J4];
+    for (header, positions) in headers.iter().zip(HEADER_PINS.iter()) {
+        for (index, position) in positions.iter().enumerate() {
+            if *position == HeaderPin::Gpio(port, pin) {
+                return Some((*header, index as u8 + 1));
+            }
+        }
+    }
+    None
+}
+
+impl Headers {
+    /// Sort the pins of ports A to E into header pins, and the rest.
+    pub fn new(gpio: Gpio) -> Headers {
+        let Gpio {
+            porta,
+            portb,
+            portc,
+            portd,
+            porte,
+        } = gpio;
+        Headers {
+            porta_control: porta.control,
+            portb_control: portb.control,
+            portc_control: portc.control,
+            portd_control: portd.control,
+            porte_control: porte.control,
+            pa0: porta.pa0,
+            pa1: porta.pa1,
+            pc0: portc.pc0,
+            pc1: portc.pc1,
+            pc2: portc.pc2,
+            pc3: portc.pc3,
+            pd4: portd.pd4,
+            pd5: portd.pd5,
+            pa2: Some(porta.pa2),
+            pa3: Some(porta.pa3),
+            pa4: Some(porta.pa4),
+            pa5: Some(porta.pa5),
+            pa6: Some(porta.pa6),
+            pa7: Some(porta.pa7),
+            pb0: Some(portb.pb0),
+            pb1: Some(portb.pb1),
+            pb2: Some(portb.pb2),
+            pb3: Some(portb.pb3),
+            pb4: Some(portb.pb4),
+            pb5: Some(portb.pb5),
+            pb6: Some(portb.pb6),
+            pb7: Some(portb.pb7),
+            pc4: Some(portc.pc4),
+            pc5: Some(portc.pc5),
+            pc6: Some(portc.pc6),
+            pc7: Some(portc.pc7),
+            pd0: Some(portd.pd0),
+            pd1: Some(portd.pd1),
+            pd2: Some(portd.pd2),
+            pd3: Some(portd.pd3),
+            pd6: Some(portd.pd6),
+            pd7: Some(portd.pd7),
+            pe0: Some(porte.pe0),
+            pe1: Some(porte.pe1),
+            pe2: Some(porte.pe2),
+            pe3: Some(porte.pe3),
+            pe4: Some(porte.pe4),
+            pe5: Some(porte.pe5),
+        }
+    }
+
+    /// J1.2 - PB5.
+    pub fn j1_2(&mut self) -> Option<PB5<Tristate>> {
+        self.pb5.take()
+    }
+
+    /// J1.3 - PB0.
+    pub fn j1_3(&mut self) -> Option<PB0<Tristate>> {
+        self.pb0.take()
+    }
+
+    /// J1.4 - PB1.
+    pub fn j1_4(&mut self) -> Option<PB1<Tristate>> {
+        self.pb1.take()
+    }
+
+    /// J1.5 - PE4.
+    pub fn j1_5(&mut self) -> Option<PE4<Tristate>> {
+        self.pe4.take()
+    }
+
+    /// J1.6 - PE5.
+    pub fn j1_6(&mut self) -> Option<PE5<Tristate>> {
+        self.pe5.take()
+    }
+
+    /// J1.7 - PB4.
+    pub fn j1_7(&mut self) -> Option<PB4<Tristate>> {
+        self.pb4.take()
+    }
+
+    /// J1.8 - PA5.
+    pub fn j1_8(&mut self) -> Option<PA5<Tristate>> {
+        self.pa5.take()
+    }
+
+    /// J1.9 - PA6.
+    pub fn j1_9(&mut self) -> Option<PA6<Tristate>> {
+        self.pa6.take()
+    }
+
+    /// J1.10 - PA7.
+    pub fn j1_10(&mut self) -> Option<PA7<Tristate>> {
+        self.pa7.take()
+    }
+
+    /// J2.2 - PB2.
+    pub fn j2_2(&mut self) -> Option<PB2<Tristate>> {
+        self.pb2.take()
+    }
+
+    /// J2.3 - PE0.
+    pub fn j2_3(&mut self) -> Option<PE0<Tristate>> {
+        self.pe0.take()
+    }
+
+    /// J2.6 - PB7.
+    /// Tied to PD1 (J3.4) through R10.
+    pub fn j2_6(&mut self) -> Option<PB7<Tristate>> {
+        self.pb7.take()
+    }
+
+    /// J2.7 - PB6.
+    /// Tied to PD0 (J3.3) through R9.
+    pub fn j2_7(&mut self) -> Option<PB6<Tristate>> {
+        self.pb6.take()
+    }
+
+    /// J2.8 - PA4.
+    pub fn j2_8(&mut self) -> Option<PA4<Tristate>> {
+        self.pa4.take()
+    }
+
+    /// J2.9 - PA3.
+    pub fn j2_9(&mut self) -> Option<PA3<Tristate>> {
+        self.pa3.take()
+    }
+
+    /// J2.10 - PA2.
+    pub fn j2_10(&mut self) -> Option<PA2<Tristate>> {
+        self.pa2.take()
+    }
+
+    /// J3.3 - PD0.
+    /// Tied to PB6 (J2.7) through R9.
+    pub fn j3_3(&mut self) -> Option<PD0<Tristate>> {
+        self.pd0.take()
+    }
+
+    /// J3.4 - PD1.
+    /// Tied to PB7 (J2.6) through R10.
+    pub fn j3_4(&mut self) -> Option<PD1<Tristate>> {
+        self.pd1.take()
+    }
+
+    /// J3.5 - PD2.
+    pub fn j3_5(&mut self) -> Option<PD2<Tristate>> {
+        self.pd2.take()
+    }
+
+    /// J3.6 - PD3.
+    pub fn j3_6(&mut self) -> Option<PD3<Tristate>> {
+        self.pd3.take()
+    }
+
+    /// J3.7 - PE1.
+    pub fn j3_7(&mut self) -> Option<PE1<Tristate>> {
+        self.pe1.take()
+    }
+
+    /// J3.8 - PE2.
+    pub fn j3_8(&mut self) -> Option<PE2<Tristate>> {
+        self.pe2.take()
+    }
+
+    /// J3.9 - PE3.
+    pub fn j3_9(&mut self) -> Option<PE3<Tristate>> {
+        self.pe3.take()
+    }
+
+    /// J4.3 - PB3.
+    pub fn j4_3(&mut self) -> Option<PB3<Tristate>> {
+        self.pb3.take()
+    }
+
+    /// J4.4 - PC4.
+    pub fn j4_4(&mut self) -> Option<PC4<Tristate>> {
+        self.pc4.take()
+    }
+
+    /// J4.5 - PC5.
+    pub fn j4_5(&mut self) -> Option<PC5<Tristate>> {
+        self.pc5.take()
+    }
+
+    /// J4.6 - PC6.
+    pub fn j4_6(&mut self) -> Option<PC6<Tristate>> {
+        self.pc6.take()
+    }
+
+    /// J4.7 - PC7.
+    pub fn j4_7(&mut self) -> Option<PC7<Tristate>> {
+        self.pc7.take()
+    }
+
+    /// J4.8 - PD6.
+    pub fn j4_8(&mut self) -> Option<PD6<Tristate>> {
+        self.pd6.take()
+    }
+
+    /// J4.9 - PD7.
+    /// Locked at reset (it can be NMI) - unlock it before use.
+    pub fn j4_9(&mut self) -> Option<PD7<Tristate>> {
+        self.pd7.take()
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// A pin's alternate functions.
+fn functions(pin: HeaderPin) -> &'static [(Select, &'static str)] {
+    match pin {
+        HeaderPin::Gpio(port, number) => PINS
+            .iter()
+            .find(|info| info.port == port && info.number == number)
+            .map(|info| info.functions)
+            .unwrap_or(&[]),
+        _ => &[],
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,309 @@
+//! Which chip is fitted, and what it has.
+//!
+//! The EK-LM4F120XL has an LM4F120H5QR and the EK-TM4C123GXL a TM4C123GH6PM.
+//! They run the same code, but the LM4F120 has no PWM or QEI modules and
+//! only one CAN controller. The `tm4c123x` crate describes the TM4C123, so
+//! it hands out peripherals the LM4F120 doesn't have. `Board` only has them
+//! with the `tm4c123g` feature, and even then checks the peripheral-present
+//! (PPxxx) registers before it hands them out, in case the image is run on
+//! the wrong board. Everything else is on both parts (see `board::groups`).
+//!
+//! ```rust,ignore
+//! let chip = board.chip_info();
+//! writeln!(uart, "{:?} {:?}, {} KiB flash", chip.part(), chip.revision(), chip.flash_size() / 1024);
+//! let pwm0 = board.take_pwm0()?;
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::ptr;
+
+use board::rom::{self, Revision};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// What DID0, DID1 and the peripheral properties registers say about the
+/// chip.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ChipInfo {
+    did0: u32,
+    did1: u32,
+    flash_size: u32,
+    sram_size: u32,
+    present: [u32; PERIPHERALS],
+}
+
+/// The parts the Launchpads were made with.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Part {
+    /// The EK-LM4F120XL's part
+    Lm4f120h5qr,
+    /// The EK-TM4C123GXL's part
+    Tm4c123gh6pm,
+    /// Some other part - the DID1 PARTNO field
+    Other(u8),
+}
+
+/// The temperature range the part is rated for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TemperatureRange {
+    /// 0 to 70 C
+    Commercial,
+    /// -40 to 85 C
+    Industrial,
+    /// -40 to 105 C
+    Extended,
+    /// A value DID1 shouldn't have
+    Unknown(u8),
+}
+
+/// The package the part comes in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Package {
+    /// Small outline
+    Soic,
+    /// Quad flat pack
+    Lqfp,
+    /// Ball grid array
+    Bga,
+    /// A value DID1 shouldn't have
+    Unknown(u8),
+}
+
+/// A kind of peripheral, with a peripheral-present register (PPxxx). Each
+/// instance has a bit in the register.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Peripheral {
+    /// Watchdog timers
+    Watchdog,
+    /// 16/32-bit timers
+    Timer,
+    /// GPIO ports (A is 0)
+    Gpio,
+    /// The uDMA controller
+    Dma,
+    /// The hibernation module
+    Hibernate,
+    /// UARTs
+    Uart,
+    /// SSI modules
+    Ssi,
+    /// I2C modules
+    I2c,
+    /// The USB controller
+    Usb,
+    /// CAN controllers
+    Can,
+    /// ADC modules
+    Adc,
+    /// The analog comparator module
+    Comparator,
+    /// PWM modules
+    Pwm,
+    /// Quadrature encoder interfaces
+    Qei,
+    /// The EEPROM
+    Eeprom,
+    /// 32/64-bit wide timers
+    WideTimer,
+}
+
+/// Things that can go wrong handing out a peripheral.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The chip doesn't have the peripheral
+    NotPresent {
+        /// The kind of peripheral
+        peripheral: Peripheral,
+        /// Which one
+        instance: u8,
+    },
+    /// The peripheral has already been taken
+    AlreadyTaken,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const DID0: *const u32 = 0x400F_E000 as *const u32;
+const DID1: *const u32 = 0x400F_E004 as *const u32;
+/// Flash Peripheral Properties - the size is in 2 KiB units, less one
+const FLASHPP: *const u32 = 0x400F_DFC0 as *const u32;
+/// SRAM Size - in 256 byte units, less one
+const SRAMSIZE: *const u32 = 0x400F_DFC4 as *const u32;
+
+/// The peripheral-present registers, in the order of `Peripheral`
+const PP_REGISTERS: [usize; PERIPHERALS] = [
+    0x400F_E300, // PPWD
+    0x400F_E304, // PPTIMER
+    0x400F_E308, // PPGPIO
+    0x400F_E30C, // PPDMA
+    0x400F_E314, // PPHIB
+    0x400F_E318, // PPUART
+    0x400F_E31C, // PPSSI
+    0x400F_E320, // PPI2C
+    0x400F_E328, // PPUSB
+    0x400F_E334, // PPCAN
+    0x400F_E338, // PPADC
+    0x400F_E33C, // PPACMP
+    0x400F_E340, // PPPWM
+    0x400F_E344, // PPQEI
+    0x400F_E358, // PPEEPROM
+    0x400F_E35C, // PPWTIMER
+];
+
+const PERIPHERALS: usize = 16;
+
+const PARTNO_LM4F120H5QR: u8 = 0x04;
+const PARTNO_TM4C123GH6PM: u8 = 0xA1;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl ChipInfo {
+    /// Read the identification registers.
+    pub fn read() -> ChipInfo {
+        let mut present = [0; PERIPHERALS];
+        for (bits, &address) in present.iter_mut().zip(PP_REGISTERS.iter()) {
+            *bits = unsafe { ptr::read_volatile(address as *const u32) };
+        }
+        unsafe {
+            ChipInfo {
+                did0: ptr::read_volatile(DID0),
+                did1: ptr::read_volatile(DID1),
+                flash_size: ((ptr::read_volatile(FLASHPP) & 0xFFFF) + 1) * 2048,
+                sram_size: ((ptr::read_volatile(SRAMSIZE) & 0xFFFF) + 1) * 256,
+                present,
+            }
+        }
+    }
+
+    /// Which part this is.
+    pub fn part(&self) -> Part {
+        match (self.did1 >> 16) as u8 {
+            PARTNO_LM4F120H5QR => Part::Lm4f120h5qr,
+            PARTNO_TM4C123GH6PM => Part::Tm4c123gh6pm,
+            other => Part::Other(other),
+        }
+    }
+
+    /// The silicon revision, if it's one we know (see `board::rom`).
+    pub fn revision(&self) -> Option<Revision> {
+        rom::revision(self.did0)
+    }
+
+    /// The silicon revision as (major, minor) - (1, 1) is B1.
+    pub fn revision_number(&self) -> (u8, u8) {
+        ((self.did0 >> 8) as u8, self.did0 as u8)
+    }
+
+    /// The size of the flash, in bytes.
+    pub fn flash_size(&self) -> u32 {
+        self.flash_size
+    }
+
+    /// The size of the SRAM, in bytes.
+    pub fn sram_size(&self) -> u32 {
+        self.sram_size
+    }
+
+    /// The temperature range the part is rated for.
+    pub fn temperature_range(&self) -> TemperatureRange {
+        match ((self.did1 >> 5) & 0x7) as u8 {
+            0 => TemperatureRange::Commercial,
+            1 => TemperatureRange::Industrial,
+            2 => TemperatureRange::Extended,
+            other => TemperatureRange::Unknown(other),
+        }
+    }
+
+    /// The package the part comes in.
+    pub fn package(&self) -> Package {
+        match ((self.did1 >> 3) & 0x3) as u8 {
+            0 => Package::Soic,
+            1 => Package::Lqfp,
+            2 => Package::Bga,
+            other => Package::Unknown(other),
+        }
+    }
+
+    /// How many pins the package has.
+    pub fn pin_count(&self) -> Option<u16> {
+        match (self.did1 >> 13) & 0x7 {
+            0 => Some(28),
+            1 => Some(48),
+            2 => Some(100),
+            3 => Some(64),
+            4 => Some(144),
+            5 => Some(157),
+            6 => Some(168),
+            _ => None,
+        }
+    }
+
+    /// The raw DID0 and DID1 registers.
+    pub fn device_id(&self) -> (u32, u32) {
+        (self.did0, self.did1)
+    }
+
+    /// Does the chip have this instance of the peripheral?
+    pub fn has(&self, peripheral: Peripheral, instance: u8) -> bool {
+        instance < 32 && self.present[peripheral as usize] & (1 << instance) != 0
+    }
+
+    /// Check the chip has this instance of the peripheral.
+    pub fn check(&self, peripheral: Peripheral, instance: u8) -> Result<(), Error> {
+        if self.has(peripheral, instance) {
+            Ok(())
+        } else {
+            Err(Error::NotPresent {
+                peripheral,
+                instance,
+            })
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
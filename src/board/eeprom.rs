@@ -0,0 +1,380 @@
+//! A driver for the 2 KiB EEPROM.
+//!
+//! The EEPROM is 512 32-bit words, arranged as 32 blocks of 16 words. It can
+//! be addressed a word at a time (`read_word`/`write_word`, word 0 to 511) or
+//! a block at a time (`read_block`/`write_block`). Each block can be
+//! protected, and optionally locked with a password of up to three words.
+//!
+//! `Eeprom::new` follows TI's start-up sequence: wait for the EEPROM to
+//! finish anything it was doing, check whether a write was interrupted by a
+//! power failure, and if so reset the EEPROM so it can recover.
+//!
+//! Typed, versioned settings can be kept in the EEPROM with
+//! `load_settings`/`save_settings` - see `common::settings`. They take
+//! `SETTINGS_WORDS` words, for two copies which are written alternately, so
+//! losing power while saving leaves the previous settings intact.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use cortex_m;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::EEPROM;
+
+use common::settings::{self, Next, Outcome, Settings};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Owns the EEPROM.
+pub struct Eeprom {
+    eeprom: EEPROM,
+}
+
+/// Things that can go wrong with the EEPROM.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The EEPROM didn't recover from an interrupted write. Power-cycle the
+    /// chip and try again.
+    RecoveryFailed,
+    /// The word or block number is past the end of the EEPROM
+    OutOfBounds,
+    /// The block's protection (or lock) doesn't allow this
+    NoPermission,
+    /// A password must be between one and three words long
+    BadPassword,
+    /// The password didn't unlock the block
+    StillLocked,
+    /// The settings don't fit in the space given
+    TooBig,
+}
+
+/// What a block allows. When a block has a password and is locked, access is
+/// further restricted, as described on each variant.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Protection {
+    /// Read and write. Read only when locked.
+    ReadWrite,
+    /// Read and write when unlocked. No access when locked. Only useful
+    /// with a password.
+    NoAccessWhenLocked,
+    /// Read only when unlocked. No access when locked.
+    ReadOnly,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The number of 32-bit words in the EEPROM.
+pub const NUM_WORDS: u32 = 512;
+
+/// The number of words in a block.
+pub const BLOCK_WORDS: u32 = 16;
+
+/// The number of blocks.
+pub const NUM_BLOCKS: u32 = NUM_WORDS / BLOCK_WORDS;
+
+/// The largest settings record (in bytes, including its header and CRC)
+/// that `load_settings` will read.
+pub const MAX_SETTINGS_LEN: usize = 256;
+
+/// The number of words used by `save_settings`: room for two records.
+pub const SETTINGS_WORDS: u32 = 2 * COPY_WORDS;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// The words given to each copy of the settings
+const COPY_WORDS: u32 = (MAX_SETTINGS_LEN / 4) as u32;
+
+const EEDONE_WORKING: u32 = 1 << 0;
+const EEDONE_NOPERM: u32 = 1 << 4;
+/// Erase needs to be retried
+const EESUPP_ERETRY: u32 = 1 << 2;
+/// Programming needs to be retried
+const EESUPP_PRETRY: u32 = 1 << 3;
+/// Only privileged code may access the block
+const EEPROT_ACC: u32 = 1 << 3;
+/// Write to EEUNLOCK to re-lock a block
+const LOCK: u32 = 0xFFFF_FFFF;
+/// EEUNLOCK reads back as 1 when the block is unlocked
+const UNLOCKED: u32 = 1;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Eeprom {
+    /// Power up the EEPROM, recovering from any write that was interrupted
+    /// when power was last lost.
+    pub fn new(eeprom: EEPROM, power_control: &PowerControl) -> Result<Eeprom, Error> {
+        sysctl::control_power(power_control, Domain::Eeprom, RunMode::Run, PowerState::On);
+        // The datasheet asks for six cycles before the EEPROM is touched
+        cortex_m::asm::delay(6);
+        let mut eeprom = Eeprom { eeprom };
+        eeprom.wait();
+        if eeprom.needs_retry() {
+            return Err(Error::RecoveryFailed);
+        }
+        // A reset makes the EEPROM check for (and finish) any interrupted
+        // write.
+        sysctl::reset(power_control, Domain::Eeprom);
+        eeprom.wait();
+        if eeprom.needs_retry() {
+            return Err(Error::RecoveryFailed);
+        }
+        Ok(eeprom)
+    }
+
+    /// Read one word (0 to 511).
+    pub fn read_word(&mut self, word: u32) -> Result<u32, Error> {
+        self.select(word)?;
+        Ok(self.eeprom.eerdwr.read().bits())
+    }
+
+    /// Write one word (0 to 511). Takes about 1 ms, or much longer if the
+    /// EEPROM has to reclaim space.
+    pub fn write_word(&mut self, word: u32, value: u32) -> Result<(), Error> {
+        self.select(word)?;
+        self.eeprom.eerdwr.write(|w| unsafe { w.bits(value) });
+        self.finish()
+    }
+
+    /// Read consecutive words, starting at `word`.
+    pub fn read(&mut self, word: u32, buffer: &mut [u32]) -> Result<(), Error> {
+        check_range(word, buffer.len())?;
+        for (i, value) in buffer.iter_mut().enumerate() {
+            *value = self.read_word(word + i as u32)?;
+        }
+        Ok(())
+    }
+
+    /// Write consecutive words, starting at `word`. Words which already hold
+    /// the right value aren't written, to save wear.
+    pub fn write(&mut self, word: u32, values: &[u32]) -> Result<(), Error> {
+        check_range(word, values.len())?;
+        for (i, value) in values.iter().enumerate() {
+            let address = word + i as u32;
+            if self.read_word(address)? != *value {
+                self.write_word(address, *value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a whole block.
+    pub fn read_block(&mut self, block: u32, buffer: &mut [u32; 16]) -> Result<(), Error> {
+        check_block(block)?;
+        self.read(block * BLOCK_WORDS, buffer)
+    }
+
+    /// Write a whole block.
+    pub fn write_block(&mut self, block: u32, values: &[u32; 16]) -> Result<(), Error> {
+        check_block(block)?;
+        self.write(block * BLOCK_WORDS, values)
+    }
+
+    /// Set what a block allows. With `privileged_only`, unprivileged code
+    /// can't access it at all.
+    pub fn set_protection(
+        &mut self,
+        block: u32,
+        protection: Protection,
+        privileged_only: bool,
+    ) -> Result<(), Error> {
+        self.select_block(block)?;
+        let mut value = match protection {
+            Protection::ReadWrite => 0,
+            Protection::NoAccessWhenLocked => 1,
+            Protection::ReadOnly => 2,
+        };
+        if privileged_only {
+            value |= EEPROT_ACC;
+        }
+        self.eeprom.eeprot.write(|w| unsafe { w.bits(value) });
+        self.finish()
+    }
+
+    /// Give a block a password of one to three words. A password can't be
+    /// removed (except by mass erasing the EEPROM), and the block (or the
+    /// whole EEPROM, for block 0) is locked from the next reset.
+    pub fn set_password(&mut self, block: u32, password: &[u32]) -> Result<(), Error> {
+        if password.is_empty() || password.len() > 3 {
+            return Err(Error::BadPassword);
+        }
+        self.select_block(block)?;
+        for (i, word) in password.iter().enumerate() {
+            match i {
+                0 => self.eeprom.eepass0.write(|w| unsafe { w.bits(*word) }),
+                1 => self.eeprom.eepass1.write(|w| unsafe { w.bits(*word) }),
+                _ => self.eeprom.eepass2.write(|w| unsafe { w.bits(*word) }),
+            }
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Unlock a password-protected block. The password must be the same
+    /// words given to `set_password`.
+    pub fn unlock(&mut self, block: u32, password: &[u32]) -> Result<(), Error> {
+        if password.is_empty() || password.len() > 3 {
+            return Err(Error::BadPassword);
+        }
+        self.select_block(block)?;
+        // The words go in last first
+        for word in password.iter().rev() {
+            self.eeprom.eeunlock.write(|w| unsafe { w.bits(*word) });
+        }
+        if self.eeprom.eeunlock.read().bits() & UNLOCKED != 0 {
+            Ok(())
+        } else {
+            Err(Error::StillLocked)
+        }
+    }
+
+    /// Lock a password-protected block again.
+    pub fn lock(&mut self, block: u32) -> Result<(), Error> {
+        self.select_block(block)?;
+        self.eeprom.eeunlock.write(|w| unsafe { w.bits(LOCK) });
+        Ok(())
+    }
+
+    /// Load settings saved by `save_settings` at `word`. If there aren't
+    /// any valid settings there, you get the defaults - `Outcome` says why.
+    pub fn load_settings<S>(&mut self, word: u32) -> Result<(S, Outcome), Error>
+    where
+        S: Settings,
+    {
+        let (settings, outcome, _) = self.read_settings(word)?;
+        Ok((settings, outcome))
+    }
+
+    /// Save settings in the `SETTINGS_WORDS` words at `word`, over the older
+    /// of the two copies. Only the words which have changed are written.
+    pub fn save_settings<S>(&mut self, word: u32, value: &S) -> Result<(), Error>
+    where
+        S: Settings,
+    {
+        let (_, _, next) = self.read_settings::<S>(word)?;
+        let mut bytes = [0xFFu8; MAX_SETTINGS_LEN];
+        let len = settings::save(value, next.sequence, &mut bytes).map_err(|_| Error::TooBig)?;
+        let mut words = [0u32; COPY_WORDS as usize];
+        for (value, chunk) in words.iter_mut().zip(bytes.chunks(4)) {
+            *value = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        let num_words = (len + 3) / 4;
+        self.write(word + next.copy as u32 * COPY_WORDS, &words[..num_words])
+    }
+
+    /// Give back the EEPROM.
+    pub fn free(self) -> EEPROM {
+        self.eeprom
+    }
+
+    /// Read both copies of the settings at `word`, and pick one.
+    fn read_settings<S>(&mut self, word: u32) -> Result<(S, Outcome, Next), Error>
+    where
+        S: Settings,
+    {
+        check_range(word, SETTINGS_WORDS as usize)?;
+        let mut bytes = [[0u8; MAX_SETTINGS_LEN]; 2];
+        for (copy, buffer) in bytes.iter_mut().enumerate() {
+            let mut words = [0u32; COPY_WORDS as usize];
+            self.read(word + copy as u32 * COPY_WORDS, &mut words)?;
+            for (chunk, value) in buffer.chunks_mut(4).zip(words.iter()) {
+                chunk.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        Ok(settings::load_newest([&bytes[0], &bytes[1]]))
+    }
+
+    /// Point EEBLOCK and EEOFFSET at a word.
+    fn select(&mut self, word: u32) -> Result<(), Error> {
+        check_range(word, 1)?;
+        self.eeprom
+            .eeblock
+            .write(|w| unsafe { w.bits(word / BLOCK_WORDS) });
+        self.eeprom
+            .eeoffset
+            .write(|w| unsafe { w.bits(word % BLOCK_WORDS) });
+        Ok(())
+    }
+
+    fn select_block(&mut self, block: u32) -> Result<(), Error> {
+        check_block(block)?;
+        self.eeprom.eeblock.write(|w| unsafe { w.bits(block) });
+        Ok(())
+    }
+
+    /// Wait for a write to finish, and check it was allowed.
+    fn finish(&mut self) -> Result<(), Error> {
+        let done = self.wait();
+        if done & EEDONE_NOPERM != 0 {
+            Err(Error::NoPermission)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn wait(&mut self) -> u32 {
+        loop {
+            let done = self.eeprom.eedone.read().bits();
+            if done & EEDONE_WORKING == 0 {
+                return done;
+            }
+        }
+    }
+
+    fn needs_retry(&self) -> bool {
+        self.eeprom.eesupp.read().bits() & (EESUPP_ERETRY | EESUPP_PRETRY) != 0
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn check_block(block: u32) -> Result<(), Error> {
+    if block < NUM_BLOCKS {
+        Ok(())
+    } else {
+        Err(Error::OutOfBounds)
+    }
+}
+
+fn check_range(word: u32, len: usize) -> Result<(), Error> {
+    match word.checked_add(len as u32) {
+        Some(end) if end <= NUM_WORDS => Ok(()),
+        _ => Err(Error::OutOfBounds),
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
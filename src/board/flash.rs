@@ -0,0 +1,551 @@
+//! A driver for the 256 KiB of internal flash.
+//!
+//! Flash is erased in 1 KiB pages (to all ones) and programmed a 32-bit word
+//! at a time, or up to 32 words at a time through the write buffer. Reads
+//! are just memory reads. `Flash` also implements the `embedded-storage`
+//! `NorFlash` traits, so generic code (e.g. `common::kvstore`) can use it,
+//! and `common::ram_flash::RamFlash` behaves the same way in SRAM or on a PC.
+//!
+//! There are two guards against erasing the program you're running:
+//!
+//! * `erase`/`write` refuse to touch the flash occupied by this image
+//!   (`Error::InUse`).
+//! * `mass_erase` needs a `MassEraseConfirmation`, which is `unsafe` to
+//!   make, and resets the chip when it's done.
+//!
+//! The flash can't be read whilst it's being programmed or erased, so the
+//! code which starts an operation and waits for it to finish is linked into
+//! `.data.ramfunc`, which the start-up code copies into SRAM along with the
+//! rest of `.data`.
+//!
+//! Unlike the older LM3S parts, the LM4F120 / TM4C123 has no USECRL register;
+//! the flash controller times its own program and erase pulses. We use
+//! `board::clocks()` to turn the datasheet's worst case times into time-outs.
+//!
+//! The checks `Flash` makes, and how it splits a write up for the write
+//! buffer, are free functions (`check_write`, `check_erase`,
+//! `buffered_writes`) which also build on the host, where
+//! `host/tests/flash.rs` tests them alongside `RamFlash`.
+
+#![cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[cfg(target_arch = "arm")]
+use core::arch::asm;
+#[cfg(target_arch = "arm")]
+use core::convert::Infallible;
+#[cfg(target_arch = "arm")]
+use core::ptr;
+
+#[cfg(target_arch = "arm")]
+use cortex_m;
+#[cfg(target_arch = "arm")]
+use embedded_storage::nor_flash::{self, ErrorType, NorFlash, ReadNorFlash};
+use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
+#[cfg(target_arch = "arm")]
+use tm4c123x_hal::tm4c123x::FLASH_CTRL;
+
+#[cfg(target_arch = "arm")]
+use board;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Owns the flash controller.
+#[cfg(target_arch = "arm")]
+pub struct Flash {
+    flash: FLASH_CTRL,
+}
+
+/// Things that can go wrong when programming or erasing flash.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The address range isn't inside the flash
+    OutOfBounds,
+    /// Writes must be word aligned, erases must be page aligned
+    NotAligned,
+    /// The address range overlaps the running program
+    InUse,
+    /// The range is write- or erase-protected (see FMPPEn)
+    Protected,
+    /// The flash controller reported an error
+    Failed,
+    /// The operation took far longer than the datasheet allows
+    Timeout,
+}
+
+/// Proof that you really do want to erase the entire flash, including the
+/// running program.
+pub struct MassEraseConfirmation(());
+
+/// The buffered writes a run of words is split into (see `buffered_writes`).
+#[derive(Debug, Clone)]
+pub struct BufferedWrites {
+    address: u32,
+    words: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The address of the start of flash.
+pub const FLASH_BASE: u32 = 0x0000_0000;
+
+/// The size of the flash, in bytes.
+pub const FLASH_SIZE: u32 = 256 * 1024;
+
+/// The size of an erasable page, in bytes.
+pub const PAGE_SIZE: u32 = 1024;
+
+/// The size of the write buffer, in 32-bit words. A buffered write must not
+/// cross a `WRITE_BUFFER_WORDS * 4` byte boundary.
+pub const WRITE_BUFFER_WORDS: usize = 32;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// FMC/FMC2 key if BOOTCFG.KEY is set
+const KEY_A442: u32 = 0xA442;
+/// FMC/FMC2 key if BOOTCFG.KEY is clear
+const KEY_71D5: u32 = 0x71D5;
+const BOOTCFG_KEY: u32 = 1 << 4;
+
+const FMC_WRITE: u32 = 1 << 0;
+const FMC_ERASE: u32 = 1 << 1;
+const FMC_MERASE: u32 = 1 << 2;
+const FMC_COMT: u32 = 1 << 3;
+const FMC2_WRBUF: u32 = 1 << 0;
+
+/// Access (protection) violation
+const FCRIS_ARIS: u32 = 1 << 0;
+/// Programming (or erase) failed
+const FCRIS_PRIS: u32 = 1 << 1;
+const FCRIS_VOLTRIS: u32 = 1 << 9;
+const FCRIS_INVDRIS: u32 = 1 << 10;
+const FCRIS_ERRIS: u32 = 1 << 11;
+const FCRIS_PROGRIS: u32 = 1 << 13;
+const FCRIS_ERRORS: u32 =
+    FCRIS_ARIS | FCRIS_PRIS | FCRIS_VOLTRIS | FCRIS_INVDRIS | FCRIS_ERRIS | FCRIS_PROGRIS;
+
+/// FMC, relative to the FLASH_CTRL base address
+const FMC_OFFSET: usize = 0x008;
+/// FMC2, relative to the FLASH_CTRL base address
+const FMC2_OFFSET: usize = 0x020;
+/// FWB0, relative to the FLASH_CTRL base address. FWB1..31 follow it.
+const FWB_OFFSET: usize = 0x100;
+
+/// Worst case times, from the datasheet, with plenty of margin
+const WORD_TIMEOUT_US: u32 = 1_000;
+const BUFFER_TIMEOUT_US: u32 = 10_000;
+const PAGE_TIMEOUT_US: u32 = 50_000;
+const MASS_ERASE_TIMEOUT_US: u32 = 500_000;
+/// Roughly how many CPU cycles one pass of the polling loop takes
+const CYCLES_PER_POLL: u32 = 8;
+
+/// The Application Interrupt and Reset Control Register, and the value which
+/// requests a system reset.
+const AIRCR: *mut u32 = 0xE000_ED0C as *mut u32;
+const AIRCR_SYSRESETREQ: u32 = 0x05FA_0004;
+
+#[cfg(target_arch = "arm")]
+extern "C" {
+    /// The start of this image in flash - from memory.x
+    static _flash_image_start: u32;
+    /// Load address of `.data`, which comes last in flash - from cortex-m-rt's link.x
+    static __sidata: u32;
+    /// Start of `.data` (in SRAM) - from cortex-m-rt's link.x
+    static __sdata: u32;
+    /// End of `.data` (in SRAM) - from cortex-m-rt's link.x
+    static __edata: u32;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl MassEraseConfirmation {
+    /// Confirm that the whole flash may be erased.
+    ///
+    /// # Safety
+    ///
+    /// The program calling it will be erased too - nothing runs afterwards
+    /// until the chip is re-programmed.
+    pub unsafe fn new() -> MassEraseConfirmation {
+        MassEraseConfirmation(())
+    }
+}
+
+/// Check that `length` bytes at `address` are inside the flash, and that both
+/// are word aligned, as `Flash::write` does. (It also refuses to write over
+/// the running program.)
+pub fn check_write(address: u32, length: usize) -> Result<(), Error> {
+    check_bounds(address, length)?;
+    if address % 4 != 0 || length % 4 != 0 {
+        return Err(Error::NotAligned);
+    }
+    Ok(())
+}
+
+/// Check that `length` bytes at `address` are inside the flash, and are whole
+/// pages, as `NorFlash::erase` does.
+pub fn check_erase(address: u32, length: usize) -> Result<(), Error> {
+    check_bounds(address, length)?;
+    if address % PAGE_SIZE != 0 || length % PAGE_SIZE as usize != 0 {
+        return Err(Error::NotAligned);
+    }
+    Ok(())
+}
+
+/// Split a write of `length` bytes at `address` (both word aligned) the way
+/// `Flash::write` does: into runs of at most `WRITE_BUFFER_WORDS` words,
+/// none of which cross a `WRITE_BUFFER_WORDS * 4` byte block. Yields the
+/// address and number of words of each run.
+pub fn buffered_writes(address: u32, length: usize) -> BufferedWrites {
+    BufferedWrites {
+        address,
+        words: length / 4,
+    }
+}
+
+impl Iterator for BufferedWrites {
+    type Item = (u32, usize);
+
+    fn next(&mut self) -> Option<(u32, usize)> {
+        if self.words == 0 {
+            return None;
+        }
+        let block_size = (WRITE_BUFFER_WORDS * 4) as u32;
+        let space = ((block_size - self.address % block_size) / 4) as usize;
+        let words = space.min(self.words);
+        let run = (self.address, words);
+        self.address += (words * 4) as u32;
+        self.words -= words;
+        Some(run)
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl Flash {
+    /// Take ownership of the flash controller.
+    pub fn new(flash: FLASH_CTRL) -> Flash {
+        Flash { flash }
+    }
+
+    /// Erase the 1 KiB page starting at `address`.
+    pub fn erase_page(&mut self, address: u32) -> Result<(), Error> {
+        check_erase(address, PAGE_SIZE as usize)?;
+        check_in_use(address, PAGE_SIZE)?;
+        self.flash.fma.write(|w| unsafe { w.bits(address) });
+        self.run(FMC_OFFSET, FMC_ERASE, PAGE_TIMEOUT_US)
+    }
+
+    /// Program one 32-bit word. Programming can only clear bits, so the word
+    /// should have been erased first.
+    pub fn write_word(&mut self, address: u32, word: u32) -> Result<(), Error> {
+        check_write(address, 4)?;
+        check_in_use(address, 4)?;
+        self.flash.fmd.write(|w| unsafe { w.bits(word) });
+        self.flash.fma.write(|w| unsafe { w.bits(address) });
+        self.run(FMC_OFFSET, FMC_WRITE, WORD_TIMEOUT_US)
+    }
+
+    /// Program up to 32 words through the write buffer in one operation.
+    /// They must all fall within the same 128 byte aligned block.
+    pub fn write_buffered(&mut self, address: u32, words: &[u32]) -> Result<(), Error> {
+        let block_size = (WRITE_BUFFER_WORDS * 4) as u32;
+        check_write(address, words.len() * 4)?;
+        if (address % block_size) as usize + words.len() * 4 > block_size as usize {
+            return Err(Error::NotAligned);
+        }
+        check_in_use(address, (words.len() * 4) as u32)?;
+        if words.is_empty() {
+            return Ok(());
+        }
+        let block = address & !(block_size - 1);
+        let first = ((address - block) / 4) as usize;
+        self.flash.fma.write(|w| unsafe { w.bits(block) });
+        let fwb = self.register(FWB_OFFSET);
+        for (i, word) in words.iter().enumerate() {
+            unsafe { ptr::write_volatile(fwb.add(first + i), *word) };
+        }
+        self.run(FMC2_OFFSET, FMC2_WRBUF, BUFFER_TIMEOUT_US)
+    }
+
+    /// Program a word-aligned run of bytes, using the write buffer where
+    /// possible. The length must be a multiple of four bytes.
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
+        check_write(address, data.len())?;
+        check_in_use(address, data.len() as u32)?;
+        let mut data = data;
+        for (address, count) in buffered_writes(address, data.len()) {
+            let (chunk, rest) = data.split_at(count * 4);
+            let mut words = [0u32; WRITE_BUFFER_WORDS];
+            for (word, bytes) in words.iter_mut().zip(chunk.chunks(4)) {
+                *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            self.write_buffered(address, &words[..count])?;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Copy bytes out of flash.
+    pub fn read(&self, address: u32, buffer: &mut [u8]) -> Result<(), Error> {
+        if address
+            .checked_add(buffer.len() as u32)
+            .map_or(true, |end| end > FLASH_BASE + FLASH_SIZE)
+        {
+            return Err(Error::OutOfBounds);
+        }
+        // Flash starts at address 0, which Rust won't let us treat as an
+        // ordinary pointer, so read it a byte at a time
+        for (byte, address) in buffer.iter_mut().zip(address as usize..) {
+            *byte = unsafe { ptr::read_volatile(address as *const u8) };
+        }
+        Ok(())
+    }
+
+    /// Erase the entire flash - including this program - then reset the
+    /// chip. Fails (without erasing anything) if any of the flash is
+    /// erase-protected.
+    pub fn mass_erase(&mut self, _confirm: MassEraseConfirmation) -> Result<Infallible, Error> {
+        let protected = self.flash.fmppe0.read().bits() != 0xFFFF_FFFF
+            || self.flash.fmppe1.read().bits() != 0xFFFF_FFFF
+            || self.flash.fmppe2.read().bits() != 0xFFFF_FFFF
+            || self.flash.fmppe3.read().bits() != 0xFFFF_FFFF;
+        if protected {
+            return Err(Error::Protected);
+        }
+        cortex_m::interrupt::disable();
+        let command = (self.key() << 16) | FMC_MERASE;
+        let loops = timeout_loops(MASS_ERASE_TIMEOUT_US);
+        unsafe { mass_erase_and_reset(self.register(FMC_OFFSET), command, loops) }
+    }
+
+    /// Make a protection or boot configuration register permanent (see
+    /// `board::protection`). `address` says which register, and `value` is
+    /// what to commit.
+    pub(crate) fn commit_register(&mut self, address: u32, value: u32) -> Result<(), Error> {
+        self.flash.fmd.write(|w| unsafe { w.bits(value) });
+        self.flash.fma.write(|w| unsafe { w.bits(address) });
+        self.run(FMC_OFFSET, FMC_COMT, WORD_TIMEOUT_US)
+    }
+
+    /// Give back the flash controller.
+    pub fn free(self) -> FLASH_CTRL {
+        self.flash
+    }
+
+    /// Start an operation by writing `command` (plus the key) to the FMC or
+    /// FMC2 register, wait for it to finish, and check for errors.
+    fn run(&mut self, offset: usize, command: u32, timeout_us: u32) -> Result<(), Error> {
+        self.flash.fcmisc.write(|w| unsafe { w.bits(FCRIS_ERRORS) });
+        let command = (self.key() << 16) | command;
+        let loops = timeout_loops(timeout_us);
+        let finished = cortex_m::interrupt::free(|_| unsafe {
+            execute_and_wait(self.register(offset), command, loops)
+        });
+        let status = self.flash.fcris.read().bits();
+        self.flash.fcmisc.write(|w| unsafe { w.bits(FCRIS_ERRORS) });
+        if !finished {
+            Err(Error::Timeout)
+        } else if status & FCRIS_ARIS != 0 {
+            Err(Error::Protected)
+        } else if status & FCRIS_ERRORS != 0 {
+            Err(Error::Failed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The write key depends on how BOOTCFG has been programmed.
+    fn key(&self) -> u32 {
+        if self.flash.bootcfg.read().bits() & BOOTCFG_KEY != 0 {
+            KEY_A442
+        } else {
+            KEY_71D5
+        }
+    }
+
+    fn register(&self, offset: usize) -> *mut u32 {
+        (FLASH_CTRL::ptr() as usize + offset) as *mut u32
+    }
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl ErrorType for Flash {
+    type Error = Error;
+}
+
+#[cfg(target_arch = "arm")]
+impl ReadNorFlash for Flash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        Flash::read(self, FLASH_BASE + offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        FLASH_SIZE as usize
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl NorFlash for Flash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = PAGE_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        nor_flash::check_erase(self, from, to).map_err(from_kind)?;
+        for page in (from..to).step_by(PAGE_SIZE as usize) {
+            self.erase_page(FLASH_BASE + page)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        nor_flash::check_write(self, offset, bytes.len()).map_err(from_kind)?;
+        Flash::write(self, FLASH_BASE + offset, bytes)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Check a range is inside the flash.
+fn check_bounds(address: u32, length: usize) -> Result<(), Error> {
+    let end = u64::from(address) + length as u64;
+    // FLASH_BASE is zero, so the start can't be below it
+    if end > u64::from(FLASH_BASE + FLASH_SIZE) {
+        return Err(Error::OutOfBounds);
+    }
+    Ok(())
+}
+
+/// Check an in-bounds range doesn't overlap this program.
+#[cfg(target_arch = "arm")]
+fn check_in_use(address: u32, length: u32) -> Result<(), Error> {
+    let (image_start, image_end) = image_range();
+    if address < image_end && address + length > image_start {
+        return Err(Error::InUse);
+    }
+    Ok(())
+}
+
+/// The flash occupied by this image: from the vector table to the end of
+/// the `.data` initialisers.
+#[cfg(target_arch = "arm")]
+pub(crate) fn image_range() -> (u32, u32) {
+    unsafe {
+        let start = &_flash_image_start as *const u32 as u32;
+        let data_size = (&__edata as *const u32 as u32) - (&__sdata as *const u32 as u32);
+        (start, (&__sidata as *const u32 as u32) + data_size)
+    }
+}
+
+#[cfg(target_arch = "arm")]
+fn timeout_loops(us: u32) -> u32 {
+    (board::clocks().sysclk.0 / 1_000_000) * us / CYCLES_PER_POLL
+}
+
+#[cfg(target_arch = "arm")]
+fn from_kind(kind: NorFlashErrorKind) -> Error {
+    match kind {
+        NorFlashErrorKind::NotAligned => Error::NotAligned,
+        NorFlashErrorKind::OutOfBounds => Error::OutOfBounds,
+        _ => Error::Failed,
+    }
+}
+
+/// Write `command` to a FMC/FMC2 register and wait for the command bits to
+/// clear. Runs from SRAM. Returns false on time-out.
+#[cfg(target_arch = "arm")]
+#[link_section = ".data.ramfunc"]
+#[inline(never)]
+unsafe fn execute_and_wait(register: *mut u32, command: u32, loops: u32) -> bool {
+    store(register, command);
+    let mut remaining = loops;
+    while load(register) & (command & 0xFFFF) != 0 {
+        if remaining == 0 {
+            return false;
+        }
+        remaining -= 1;
+    }
+    true
+}
+
+/// Erase everything, then reset. Runs from SRAM, with interrupts disabled,
+/// because there's no flash left to return to - so it only uses `load` and
+/// `store`, never a call.
+#[cfg(target_arch = "arm")]
+#[link_section = ".data.ramfunc"]
+#[inline(never)]
+unsafe fn mass_erase_and_reset(fmc: *mut u32, command: u32, loops: u32) -> ! {
+    store(fmc, command);
+    let mut remaining = loops;
+    while load(fmc) & FMC_MERASE != 0 && remaining != 0 {
+        remaining -= 1;
+    }
+    store(AIRCR, AIRCR_SYSRESETREQ);
+    loop {}
+}
+
+/// A volatile load. `ptr::read_volatile` can be an out-of-line call (in
+/// flash) in a debug build; this is always a single `ldr`.
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn load(address: *const u32) -> u32 {
+    let value;
+    asm!("ldr {0}, [{1}]", out(reg) value, in(reg) address, options(nostack, preserves_flags));
+    value
+}
+
+/// A volatile store, which is always a single `str` (see `load`).
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn store(address: *mut u32, value: u32) {
+    asm!("str {0}, [{1}]", in(reg) value, in(reg) address, options(nostack, preserves_flags));
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,395 @@
+//! The peripherals `Board` hands out, grouped by what they do.
+//!
+//! Each group is a plain struct of public fields, so you can move out just
+//! the peripherals you need and leave the rest. GPIO ports A to E come
+//! already split into the HAL's pins. The UARTs and timers are a `Uart` or
+//! a `Timer`, which become the HAL's driver once you give them what only you
+//! know - pins and a baud rate, or a timeout. Flash is this crate's driver
+//! (see `board::flash`). The HAL has no driver for the rest yet, so they
+//! are the peripherals themselves.
+//!
+//! Both Launchpads' parts have everything here except CAN1: the LM4F120H5QR
+//! has one CAN controller and the TM4C123GH6PM two. So `comms.can1` only
+//! exists with the `tm4c123g` feature, and is `None` unless the chip's
+//! peripheral-present register says it's there (see `board::chip`). The
+//! differences elsewhere are inside a peripheral (the LM4F120's USB
+//! controller is device-only, the TM4C123's can also be a host), so they
+//! don't change what you're handed.
+//!
+//! GPIO ports A to E can each be used through the legacy APB aperture or the
+//! faster AHB one, but not both. The HAL's pins use APB, so that's how
+//! `Board` splits them. `IntoAperture` moves a port to AHB, or to
+//! `board::port_mask` on either aperture, but only in exchange for all of the
+//! port's pins and its control - so once any pin is in use (e.g. PA0/PA1 by
+//! the console), the port stays on APB. The one exception is a fault: the
+//! fault handlers take UART0 on PA0/PA1 through APB to report it, and then
+//! stop. Port F holds the LEDs and buttons, so it stays on APB.
+//!
+//! ```rust,ignore
+//! let mut pins_b = board.gpio.portb;
+//! let uart = board.serial.uart1.serial(
+//!     pins_b.pb1.into_af_push_pull(&mut pins_b.control),
+//!     pins_b.pb0.into_af_push_pull(&mut pins_b.control),
+//!     Bps(115_200),
+//!     serial::NewlineMode::Binary,
+//!     &board.power_control,
+//! );
+//! let timer = board.timers.timer0.timer(100.hz(), &board.power_control);
+//! let porte = board.gpio.porte.into_ahb(&board.power_control);
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::ptr;
+
+use cortex_m;
+use tm4c123x_hal::gpio::{gpioa, gpiob, gpioc, gpiod, gpioe};
+use tm4c123x_hal::serial::{self, NewlineMode, RxPin, TxPin};
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl};
+use tm4c123x_hal::time::{Bps, Hertz};
+use tm4c123x_hal::{pwm, timer, tm4c123x};
+
+use board::clocks;
+use board::flash::Flash;
+use board::port_mask::{Aperture, MaskedPort};
+use common::launchpad::Port;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// GPIO ports A to E, split through the APB aperture.
+pub struct Gpio {
+    /// GPIO port A (PA0/PA1 are the console UART)
+    pub porta: gpioa::Parts,
+    /// GPIO port B
+    pub portb: gpiob::Parts,
+    /// GPIO port C (PC0 to PC3 are JTAG)
+    pub portc: gpioc::Parts,
+    /// GPIO port D
+    pub portd: gpiod::Parts,
+    /// GPIO port E
+    pub porte: gpioe::Parts,
+}
+
+/// Moves a port `Board` split through APB to another aperture, in exchange
+/// for all of its pins.
+pub trait IntoAperture {
+    /// The port's registers through AHB
+    type Ahb;
+
+    /// Use the port through the AHB aperture, which is faster. The HAL only
+    /// drives ports through APB, so this gives you the port's registers.
+    fn into_ahb(self, power_control: &PowerControl) -> Self::Ahb;
+
+    /// Use the port through `aperture`, reading and writing several pins at
+    /// once with `board::port_mask::PortMask`.
+    fn into_masked(self, power_control: &PowerControl, aperture: Aperture) -> MaskedPort;
+}
+
+/// A UART which hasn't been given pins yet. Call `serial`.
+pub struct Uart<UART> {
+    uart: UART,
+}
+
+/// The UARTs.
+pub struct Serial {
+    /// UART0 (the console, through the on-board debugger)
+    pub uart0: Uart<tm4c123x::UART0>,
+    /// UART1
+    pub uart1: Uart<tm4c123x::UART1>,
+    /// UART2
+    pub uart2: Uart<tm4c123x::UART2>,
+    /// UART3
+    pub uart3: Uart<tm4c123x::UART3>,
+    /// UART4
+    pub uart4: Uart<tm4c123x::UART4>,
+    /// UART5
+    pub uart5: Uart<tm4c123x::UART5>,
+    /// UART6
+    pub uart6: Uart<tm4c123x::UART6>,
+    /// UART7
+    pub uart7: Uart<tm4c123x::UART7>,
+}
+
+/// A timer which hasn't been set up yet. Call `timer` (or, for the 16/32-bit
+/// timers, `pwm`).
+pub struct Timer<TIMER> {
+    timer: TIMER,
+}
+
+/// The timers and watchdogs.
+pub struct Timers {
+    /// 16/32-bit timer 0
+    pub timer0: Timer<tm4c123x::TIMER0>,
+    /// 16/32-bit timer 1
+    pub timer1: Timer<tm4c123x::TIMER1>,
+    /// 16/32-bit timer 2
+    pub timer2: Timer<tm4c123x::TIMER2>,
+    /// 16/32-bit timer 3
+    pub timer3: Timer<tm4c123x::TIMER3>,
+    /// 16/32-bit timer 4
+    pub timer4: Timer<tm4c123x::TIMER4>,
+    /// 16/32-bit timer 5
+    pub timer5: Timer<tm4c123x::TIMER5>,
+    /// 32/64-bit wide timer 0
+    pub wtimer0: Timer<tm4c123x::WTIMER0>,
+    /// 32/64-bit wide timer 1
+    pub wtimer1: Timer<tm4c123x::WTIMER1>,
+    /// 32/64-bit wide timer 2
+    pub wtimer2: Timer<tm4c123x::WTIMER2>,
+    /// 32/64-bit wide timer 3
+    pub wtimer3: Timer<tm4c123x::WTIMER3>,
+    /// 32/64-bit wide timer 4
+    pub wtimer4: Timer<tm4c123x::WTIMER4>,
+    /// 32/64-bit wide timer 5 (for `board::sampler`)
+    pub wtimer5: Timer<tm4c123x::WTIMER5>,
+    /// Watchdog timer 0
+    pub watchdog0: tm4c123x::WATCHDOG0,
+    /// Watchdog timer 1
+    pub watchdog1: tm4c123x::WATCHDOG1,
+}
+
+/// The ADCs and the analog comparators.
+pub struct Analog {
+    /// ADC module 0
+    pub adc0: tm4c123x::ADC0,
+    /// ADC module 1
+    pub adc1: tm4c123x::ADC1,
+    /// The analog comparators
+    pub comp: tm4c123x::COMP,
+}
+
+/// The synchronous serial (for `spi::Spi`), I2C (for `i2c::I2c`), CAN and
+/// USB controllers.
+pub struct Comms {
+    /// SSI module 0
+    pub ssi0: tm4c123x::SSI0,
+    /// SSI module 1
+    pub ssi1: tm4c123x::SSI1,
+    /// SSI module 2
+    pub ssi2: tm4c123x::SSI2,
+    /// SSI module 3
+    pub ssi3: tm4c123x::SSI3,
+    /// I2C module 0
+    pub i2c0: tm4c123x::I2C0,
+    /// I2C module 1
+    pub i2c1: tm4c123x::I2C1,
+    /// I2C module 2
+    pub i2c2: tm4c123x::I2C2,
+    /// I2C module 3
+    pub i2c3: tm4c123x::I2C3,
+    /// CAN controller 0
+    pub can0: tm4c123x::CAN0,
+    /// CAN controller 1, if the chip has one (the LM4F120 doesn't)
+    #[cfg(feature = "tm4c123g")]
+    pub can1: Option<tm4c123x::CAN1>,
+    /// The USB controller (device-only on the LM4F120)
+    pub usb0: tm4c123x::USB0,
+}
+
+/// Memory, power and DMA.
+pub struct System {
+    /// The internal flash (see `board::flash`)
+    pub flash: Flash,
+    /// The EEPROM - pass it to `board::eeprom::Eeprom::new`
+    pub eeprom: tm4c123x::EEPROM,
+    /// The hibernation module
+    pub hib: tm4c123x::HIB,
+    /// The floating-point exception interrupt controls
+    pub sysexc: tm4c123x::SYSEXC,
+    /// The uDMA controller
+    pub udma: tm4c123x::UDMA,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// GPIOHBCTL - a set bit moves a port from the APB to the AHB aperture
+const GPIOHBCTL: *mut u32 = 0x400F_E06C as *mut u32;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// `IntoAperture` for each port.
+macro_rules! into_aperture {
+    ($module:ident, $ahb:ident, $domain:ident, $port:ident, $bit:expr) => {
+        impl IntoAperture for $module::Parts {
+            type Ahb = tm4c123x::$ahb;
+
+            fn into_ahb(self, power_control: &PowerControl) -> tm4c123x::$ahb {
+                sysctl::reset(power_control, Domain::$domain);
+                select_ahb($bit);
+                // We have every pin of the port, so nothing else is using it
+                unsafe { tm4c123x::Peripherals::steal().$ahb }
+            }
+
+            fn into_masked(self, power_control: &PowerControl, aperture: Aperture) -> MaskedPort {
+                sysctl::reset(power_control, Domain::$domain);
+                if aperture == Aperture::Ahb {
+                    select_ahb($bit);
+                }
+                unsafe { MaskedPort::new(Port::$port, aperture) }
+            }
+        }
+    };
+}
+
+into_aperture!(gpioa, GPIO_PORTA_AHB, GpioA, A, 0);
+into_aperture!(gpiob, GPIO_PORTB_AHB, GpioB, B, 1);
+into_aperture!(gpioc, GPIO_PORTC_AHB, GpioC, C, 2);
+into_aperture!(gpiod, GPIO_PORTD_AHB, GpioD, D, 3);
+into_aperture!(gpioe, GPIO_PORTE_AHB, GpioE, E, 4);
+
+impl<UART> Uart<UART> {
+    pub(crate) fn new(uart: UART) -> Uart<UART> {
+        Uart { uart }
+    }
+}
+
+/// `serial` for each UART.
+macro_rules! uart {
+    ($uart:ident, $constructor:ident) => {
+        impl Uart<tm4c123x::$uart> {
+            /// Drive the UART with the HAL, sending on `tx` and receiving on
+            /// `rx`, without flow control.
+            pub fn serial<TX, RX>(
+                self,
+                tx: TX,
+                rx: RX,
+                baud_rate: Bps,
+                newline_mode: NewlineMode,
+                power_control: &PowerControl,
+            ) -> serial::Serial<tm4c123x::$uart, TX, RX, (), ()>
+            where
+                TX: TxPin<tm4c123x::$uart>,
+                RX: RxPin<tm4c123x::$uart>,
+            {
+                serial::Serial::$constructor(
+                    self.uart,
+                    tx,
+                    rx,
+                    (),
+                    (),
+                    baud_rate,
+                    newline_mode,
+                    clocks(),
+                    power_control,
+                )
+            }
+        }
+    };
+}
+
+uart!(UART0, uart0);
+uart!(UART1, uart1);
+uart!(UART2, uart2);
+uart!(UART3, uart3);
+uart!(UART4, uart4);
+uart!(UART5, uart5);
+uart!(UART6, uart6);
+uart!(UART7, uart7);
+
+impl<TIMER> Timer<TIMER> {
+    pub(crate) fn new(timer: TIMER) -> Timer<TIMER> {
+        Timer { timer }
+    }
+
+    /// The timer's registers, for `board::sampler`.
+    #[cfg(feature = "sampling-profiler")]
+    pub(crate) fn into_inner(self) -> TIMER {
+        self.timer
+    }
+}
+
+/// `timer` for each timer, and `pwm` for the 16/32-bit ones.
+macro_rules! timer {
+    ($timer:ident, $constructor:ident) => {
+        impl Timer<tm4c123x::$timer> {
+            /// Drive the timer with the HAL, as a periodic `CountDown`
+            /// which times out `timeout` times a second.
+            pub fn timer<T>(
+                self,
+                timeout: T,
+                power_control: &PowerControl,
+            ) -> timer::Timer<tm4c123x::$timer>
+            where
+                T: Into<Hertz>,
+            {
+                timer::Timer::$constructor(self.timer, timeout, power_control, clocks())
+            }
+        }
+    };
+    ($timer:ident, $constructor:ident, pwm) => {
+        timer!($timer, $constructor);
+
+        impl Timer<tm4c123x::$timer> {
+            /// Drive the timer with the HAL as a PWM generator - give it its
+            /// pins with `into_even`, `into_odd` or `into_both`.
+            pub fn pwm(self, power_control: &PowerControl) -> pwm::Timer<tm4c123x::$timer> {
+                pwm::Timer::$constructor(power_control, self.timer)
+            }
+        }
+    };
+}
+
+timer!(TIMER0, timer0, pwm);
+timer!(TIMER1, timer1, pwm);
+timer!(TIMER2, timer2, pwm);
+timer!(TIMER3, timer3, pwm);
+timer!(TIMER4, timer4, pwm);
+timer!(TIMER5, timer5, pwm);
+timer!(WTIMER0, wtimer0);
+timer!(WTIMER1, wtimer1);
+timer!(WTIMER2, wtimer2);
+timer!(WTIMER3, wtimer3);
+timer!(WTIMER4, wtimer4);
+timer!(WTIMER5, wtimer5);
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Move port `bit` (A is 0) to the AHB aperture.
+fn select_ahb(bit: u32) {
+    cortex_m::interrupt::free(|_| unsafe {
+        let value = ptr::read_volatile(GPIOHBCTL);
+        ptr::write_volatile(GPIOHBCTL, value | (1 << bit));
+    });
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
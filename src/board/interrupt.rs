@@ -0,0 +1,482 @@
+//! Typed access to the Nested Vectored Interrupt Controller (NVIC).
+//!
+//! The TM4C123 / LM4F120 implements 3 bits of priority, so there are eight
+//! priority levels - 0 is the most urgent and 7 is the least urgent. All the
+//! priorities in this module use that 0..=7 numbering rather than the raw
+//! 8-bit value the NVIC stores.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use cortex_m;
+use cortex_m::interrupt::Nr;
+use cortex_m::peripheral::{NVIC, SCB};
+use cortex_m::register::{basepri, basepri_max};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Every interrupt vector on the TM4C123 / LM4F120, numbered as per Table
+/// 2-9 in the datasheet. Reserved vectors are omitted.
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum Interrupt {
+    /// GPIO Port A
+    GPIOA = 0,
+    /// GPIO Port B
+    GPIOB = 1,
+    /// GPIO Port C
+    GPIOC = 2,
+    /// GPIO Port D
+    GPIOD = 3,
+    /// GPIO Port E
+    GPIOE = 4,
+    /// UART0
+    UART0 = 5,
+    /// UART1
+    UART1 = 6,
+    /// SSI0
+    SSI0 = 7,
+    /// I2C0
+    I2C0 = 8,
+    /// PWM0 Fault
+    PWM0_FAULT = 9,
+    /// PWM0 Generator 0
+    PWM0_0 = 10,
+    /// PWM0 Generator 1
+    PWM0_1 = 11,
+    /// PWM0 Generator 2
+    PWM0_2 = 12,
+    /// QEI0
+    QEI0 = 13,
+    /// ADC0 Sequence 0
+    ADC0SS0 = 14,
+    /// ADC0 Sequence 1
+    ADC0SS1 = 15,
+    /// ADC0 Sequence 2
+    ADC0SS2 = 16,
+    /// ADC0 Sequence 3
+    ADC0SS3 = 17,
+    /// Watchdog Timers 0 and 1
+    WATCHDOG = 18,
+    /// 16/32-Bit Timer 0A
+    TIMER0A = 19,
+    /// 16/32-Bit Timer 0B
+    TIMER0B = 20,
+    /// 16/32-Bit Timer 1A
+    TIMER1A = 21,
+    /// 16/32-Bit Timer 1B
+    TIMER1B = 22,
+    /// 16/32-Bit Timer 2A
+    TIMER2A = 23,
+    /// 16/32-Bit Timer 2B
+    TIMER2B = 24,
+    /// Analog Comparator 0
+    COMP0 = 25,
+    /// Analog Comparator 1
+    COMP1 = 26,
+    /// System Control
+    SYSCTL = 28,
+    /// Flash Memory Control and EEPROM Control
+    FLASH = 29,
+    /// GPIO Port F
+    GPIOF = 30,
+    /// UART2
+    UART2 = 33,
+    /// SSI1
+    SSI1 = 34,
+    /// 16/32-Bit Timer 3A
+    TIMER3A = 35,
+    /// 16/32-Bit Timer 3B
+    TIMER3B = 36,
+    /// I2C1
+    I2C1 = 37,
+    /// QEI1
+    QEI1 = 38,
+    /// CAN0
+    CAN0 = 39,
+    /// CAN1
+    CAN1 = 40,
+    /// Hibernation Module
+    HIBERNATE = 43,
+    /// USB
+    USB0 = 44,
+    /// PWM0 Generator 3
+    PWM0_3 = 45,
+    /// uDMA Software
+    UDMA = 46,
+    /// uDMA Error
+    UDMAERR = 47,
+    /// ADC1 Sequence 0
+    ADC1SS0 = 48,
+    /// ADC1 Sequence 1
+    ADC1SS1 = 49,
+    /// ADC1 Sequence 2
+    ADC1SS2 = 50,
+    /// ADC1 Sequence 3
+    ADC1SS3 = 51,
+    /// SSI2
+    SSI2 = 57,
+    /// SSI3
+    SSI3 = 58,
+    /// UART3
+    UART3 = 59,
+    /// UART4
+    UART4 = 60,
+    /// UART5
+    UART5 = 61,
+    /// UART6
+    UART6 = 62,
+    /// UART7
+    UART7 = 63,
+    /// I2C2
+    I2C2 = 68,
+    /// I2C3
+    I2C3 = 69,
+    /// 16/32-Bit Timer 4A
+    TIMER4A = 70,
+    /// 16/32-Bit Timer 4B
+    TIMER4B = 71,
+    /// 16/32-Bit Timer 5A
+    TIMER5A = 92,
+    /// 16/32-Bit Timer 5B
+    TIMER5B = 93,
+    /// 32/64-Bit Timer 0A
+    WTIMER0A = 94,
+    /// 32/64-Bit Timer 0B
+    WTIMER0B = 95,
+    /// 32/64-Bit Timer 1A
+    WTIMER1A = 96,
+    /// 32/64-Bit Timer 1B
+    WTIMER1B = 97,
+    /// 32/64-Bit Timer 2A
+    WTIMER2A = 98,
+    /// 32/64-Bit Timer 2B
+    WTIMER2B = 99,
+    /// 32/64-Bit Timer 3A
+    WTIMER3A = 100,
+    /// 32/64-Bit Timer 3B
+    WTIMER3B = 101,
+    /// 32/64-Bit Timer 4A
+    WTIMER4A = 102,
+    /// 32/64-Bit Timer 4B
+    WTIMER4B = 103,
+    /// 32/64-Bit Timer 5A
+    WTIMER5A = 104,
+    /// 32/64-Bit Timer 5B
+    WTIMER5B = 105,
+    /// System Exception (imprecise)
+    SYSEXC = 106,
+    /// PWM1 Generator 0
+    PWM1_0 = 134,
+    /// PWM1 Generator 1
+    PWM1_1 = 135,
+    /// PWM1 Generator 2
+    PWM1_2 = 136,
+    /// PWM1 Generator 3
+    PWM1_3 = 137,
+    /// PWM1 Fault
+    PWM1_FAULT = 138,
+}
+
+/// How the three priority bits are split between pre-emption priority (which
+/// decides whether one interrupt can interrupt another) and sub-priority
+/// (which only decides the order in which pending interrupts are taken).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PriorityGrouping {
+    /// Eight pre-emption levels, no sub-priority. This is the reset default.
+    Preempt8Sub1,
+    /// Four pre-emption levels, two sub-priority levels.
+    Preempt4Sub2,
+    /// Two pre-emption levels, four sub-priority levels.
+    Preempt2Sub4,
+    /// One pre-emption level, eight sub-priority levels. No interrupt can
+    /// interrupt another.
+    Preempt1Sub8,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The number of priority bits implemented by this chip.
+pub const PRIORITY_BITS: u8 = 3;
+
+/// The number of distinct priority levels (0 is the most urgent).
+pub const NUM_PRIORITIES: u8 = 1 << PRIORITY_BITS;
+
+/// The number of interrupt vectors (including reserved ones) after the 16
+/// system exceptions.
+pub const NUM_INTERRUPTS: usize = 139;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const AIRCR_VECTKEY: u32 = 0x05FA << 16;
+const AIRCR_VECTKEY_MASK: u32 = 0xFFFF << 16;
+const AIRCR_PRIGROUP_SHIFT: u32 = 8;
+const AIRCR_PRIGROUP_MASK: u32 = 0x7 << AIRCR_PRIGROUP_SHIFT;
+
+/// Every implemented interrupt, in vector order.
+static ALL: [Interrupt; 78] = [
+    Interrupt::GPIOA,
+    Interrupt::GPIOB,
+    Interrupt::GPIOC,
+    Interrupt::GPIOD,
+    Interrupt::GPIOE,
+    Interrupt::UART0,
+    Interrupt::UART1,
+    Interrupt::SSI0,
+    Interrupt::I2C0,
+    Interrupt::PWM0_FAULT,
+    Interrupt::PWM0_0,
+    Interrupt::PWM0_1,
+    Interrupt::PWM0_2,
+    Interrupt::QEI0,
+    Interrupt::ADC0SS0,
+    Interrupt::ADC0SS1,
+    Interrupt::ADC0SS2,
+    Interrupt::ADC0SS3,
+    Interrupt::WATCHDOG,
+    Interrupt::TIMER0A,
+    Interrupt::TIMER0B,
+    Interrupt::TIMER1A,
+    Interrupt::TIMER1B,
+    Interrupt::TIMER2A,
+    Interrupt::TIMER2B,
+    Interrupt::COMP0,
+    Interrupt::COMP1,
+    Interrupt::SYSCTL,
+    Interrupt::FLASH,
+    Interrupt::GPIOF,
+    Interrupt::UART2,
+    Interrupt::SSI1,
+    Interrupt::TIMER3A,
+    Interrupt::TIMER3B,
+    Interrupt::I2C1,
+    Interrupt::QEI1,
+    Interrupt::CAN0,
+    Interrupt::CAN1,
+    Interrupt::HIBERNATE,
+    Interrupt::USB0,
+    Interrupt::PWM0_3,
+    Interrupt::UDMA,
+    Interrupt::UDMAERR,
+    Interrupt::ADC1SS0,
+    Interrupt::ADC1SS1,
+    Interrupt::ADC1SS2,
+    Interrupt::ADC1SS3,
+    Interrupt::SSI2,
+    Interrupt::SSI3,
+    Interrupt::UART3,
+    Interrupt::UART4,
+    Interrupt::UART5,
+    Interrupt::UART6,
+    Interrupt::UART7,
+    Interrupt::I2C2,
+    Interrupt::I2C3,
+    Interrupt::TIMER4A,
+    Interrupt::TIMER4B,
+    Interrupt::TIMER5A,
+    Interrupt::TIMER5B,
+    Interrupt::WTIMER0A,
+    Interrupt::WTIMER0B,
+    Interrupt::WTIMER1A,
+    Interrupt::WTIMER1B,
+    Interrupt::WTIMER2A,
+    Interrupt::WTIMER2B,
+    Interrupt::WTIMER3A,
+    Interrupt::WTIMER3B,
+    Interrupt::WTIMER4A,
+    Interrupt::WTIMER4B,
+    Interrupt::WTIMER5A,
+    Interrupt::WTIMER5B,
+    Interrupt::SYSEXC,
+    Interrupt::PWM1_0,
+    Interrupt::PWM1_1,
+    Interrupt::PWM1_2,
+    Interrupt::PWM1_3,
+    Interrupt::PWM1_FAULT,
+];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+unsafe impl Nr for Interrupt {
+    fn nr(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl Interrupt {
+    /// Convert an IRQ number (as passed to `DefaultHandler`) into an
+    /// `Interrupt`. Returns `None` for negative numbers (system exceptions)
+    /// and for reserved vectors.
+    pub fn from_irqn(irqn: i16) -> Option<Interrupt> {
+        if irqn < 0 {
+            return None;
+        }
+        ALL.iter().cloned().find(|i| i16::from(*i as u8) == irqn)
+    }
+
+    /// Iterate through every implemented interrupt, in vector order.
+    pub fn all() -> impl Iterator<Item = Interrupt> {
+        ALL.iter().cloned()
+    }
+}
+
+/// Enable an interrupt in the NVIC.
+///
+/// This is `unsafe` because it can break a critical section which relies on
+/// this interrupt being masked.
+pub unsafe fn enable(interrupt: Interrupt) {
+    NVIC::unmask(interrupt);
+}
+
+/// Disable an interrupt in the NVIC.
+pub fn disable(interrupt: Interrupt) {
+    NVIC::mask(interrupt);
+}
+
+/// Is this interrupt enabled in the NVIC?
+pub fn is_enabled(interrupt: Interrupt) -> bool {
+    NVIC::is_enabled(interrupt)
+}
+
+/// Mark an interrupt as pending, so it fires as soon as its priority allows.
+pub fn pend(interrupt: Interrupt) {
+    NVIC::pend(interrupt);
+}
+
+/// Clear the pending flag on an interrupt.
+pub fn unpend(interrupt: Interrupt) {
+    NVIC::unpend(interrupt);
+}
+
+/// Is this interrupt pending?
+pub fn is_pending(interrupt: Interrupt) -> bool {
+    NVIC::is_pending(interrupt)
+}
+
+/// Is this interrupt currently being serviced (or pre-empted whilst being
+/// serviced)?
+pub fn is_active(interrupt: Interrupt) -> bool {
+    NVIC::is_active(interrupt)
+}
+
+/// Set the priority of an interrupt, where 0 is the most urgent and 7 is the
+/// least urgent.
+///
+/// This is `unsafe` because it can break a priority-based critical section
+/// (see `with_interrupts_masked_below`). Panics if `priority` is out of
+/// range.
+pub unsafe fn set_priority(nvic: &mut NVIC, interrupt: Interrupt, priority: u8) {
+    nvic.set_priority(interrupt, to_hw_priority(priority));
+}
+
+/// Get the priority of an interrupt, where 0 is the most urgent and 7 is the
+/// least urgent.
+pub fn priority(interrupt: Interrupt) -> u8 {
+    from_hw_priority(NVIC::get_priority(interrupt))
+}
+
+/// Set how the priority bits are split between pre-emption priority and
+/// sub-priority.
+pub fn set_priority_grouping(scb: &mut SCB, grouping: PriorityGrouping) {
+    // With 3 implemented bits, any PRIGROUP of 4 or less gives three bits of
+    // pre-emption priority.
+    let prigroup = match grouping {
+        PriorityGrouping::Preempt8Sub1 => 4,
+        PriorityGrouping::Preempt4Sub2 => 5,
+        PriorityGrouping::Preempt2Sub4 => 6,
+        PriorityGrouping::Preempt1Sub8 => 7,
+    };
+    unsafe {
+        scb.aircr.modify(|r| {
+            (r & !(AIRCR_VECTKEY_MASK | AIRCR_PRIGROUP_MASK))
+                | AIRCR_VECTKEY
+                | (prigroup << AIRCR_PRIGROUP_SHIFT)
+        });
+    }
+}
+
+/// Get the current priority grouping.
+pub fn priority_grouping() -> PriorityGrouping {
+    let aircr = unsafe { (*SCB::ptr()).aircr.read() };
+    match (aircr & AIRCR_PRIGROUP_MASK) >> AIRCR_PRIGROUP_SHIFT {
+        0..=4 => PriorityGrouping::Preempt8Sub1,
+        5 => PriorityGrouping::Preempt4Sub2,
+        6 => PriorityGrouping::Preempt2Sub4,
+        _ => PriorityGrouping::Preempt1Sub8,
+    }
+}
+
+/// Run `f` with every interrupt at `priority` or less urgent (i.e. a
+/// numerically equal or higher priority value) masked, using BASEPRI. More
+/// urgent interrupts can still fire. A `priority` of 0 masks everything, like
+/// `cortex_m::interrupt::free`.
+///
+/// Nested calls only ever raise the masking level - an inner call with a less
+/// urgent `priority` than the outer call has no extra effect. Panics if
+/// `priority` is out of range.
+pub fn with_interrupts_masked_below<F, R>(priority: u8, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let hw_priority = to_hw_priority(priority);
+    if hw_priority == 0 {
+        // BASEPRI of zero means 'mask nothing', so fall back to PRIMASK
+        return cortex_m::interrupt::free(|_| f());
+    }
+    let old = basepri::read();
+    basepri_max::write(hw_priority);
+    let result = f();
+    unsafe { basepri::write(old) };
+    result
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Convert a 0..=7 priority into the 8-bit value the NVIC uses.
+fn to_hw_priority(priority: u8) -> u8 {
+    assert!(priority < NUM_PRIORITIES, "Priority out of range");
+    priority << (8 - PRIORITY_BITS)
+}
+
+/// Convert an 8-bit NVIC priority value into 0..=7.
+fn from_hw_priority(hw_priority: u8) -> u8 {
+    hw_priority >> (8 - PRIORITY_BITS)
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
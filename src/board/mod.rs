@@ -6,32 +6,44 @@
 //
 // ****************************************************************************
 
+pub mod boosterpack;
+pub mod chip;
+pub mod eeprom;
+pub mod flash;
+pub mod groups;
+pub mod interrupt;
+pub mod parallel_bus;
+pub mod pinmux;
+pub mod port_mask;
+pub mod protection;
+pub mod rom;
+#[cfg(feature = "sampling-profiler")]
+pub mod sampler;
+pub mod shared;
+pub mod update;
+#[cfg(feature = "ram-vectors")]
+pub mod vectors;
+
+pub use self::interrupt::Interrupt;
+pub use self::shared::IsrShared;
+pub use common::launchpad::{Button, Led};
+
+use self::chip::ChipInfo;
+#[cfg(feature = "tm4c123g")]
+use self::chip::Peripheral;
+use self::flash::Flash;
+use self::groups::{Analog, Comms, Gpio, Serial, System, Timer, Timers, Uart};
+use common::build_info::{self, BuildInfo};
+use common::image_checksum;
+use common::launchpad::LaunchpadBoard;
+
 // ****************************************************************************
 //
 // Public Types
 //
 // ****************************************************************************
 
-#[derive(PartialEq, Clone, Copy)]
-/// The Launchpad has a tri-colour LED, which we consider
-/// to be three separate LEDs.
-pub enum Led {
-    /// The Red LED
-    Red,
-    /// The Blue LED
-    Blue,
-    /// The Green LED
-    Green,
-}
-
-#[derive(PartialEq, Clone, Copy)]
-/// The Launchpad has two buttons
-pub enum Button {
-    /// SW1
-    One,
-    /// SW2
-    Two,
-}
+// None
 
 // ****************************************************************************
 //
@@ -47,16 +59,20 @@ pub enum Button {
 //
 // ****************************************************************************
 
-use embedded_hal::digital::v2::OutputPin;
-use tm4c123x_hal::gpio::{gpiof::*, GpioExt, Input, Output, PullUp, PushPull};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::serial::{Read, Write};
+use tm4c123x_hal::gpio::gpioa::{PA0, PA1};
+use tm4c123x_hal::gpio::{
+    gpiof::*, AlternateFunction, GpioExt, Input, Output, PullUp, PushPull, AF1,
+};
+use tm4c123x_hal::serial;
 use tm4c123x_hal::sysctl::{
-    Clocks, CrystalFrequency, Oscillator, PllOutputFrequency, SysctlExt, SystemClock,
+    Clocks, CrystalFrequency, Oscillator, PllOutputFrequency, PowerControl, SysctlExt, SystemClock,
 };
-use tm4c123x_hal::time::Hertz;
+use tm4c123x_hal::time::{Bps, Hertz};
 
-/// Represents the EK-LM4F120XL LaunchPad board, with the locations of the LEDs and buttons
-/// predefined.
-#[allow(non_snake_case)]
+/// Represents the EK-LM4F120XL LaunchPad board (or, with the `tm4c123g` feature, the
+/// EK-TM4C123GXL), with the locations of the LEDs and buttons predefined.
 pub struct Board {
     /// The core peripherals on the LM4F120 / TM4C1233
     pub core_peripherals: tm4c123x_hal::CorePeripherals,
@@ -72,126 +88,80 @@ pub struct Board {
     pub button_one: PF4<Input<PullUp>>,
     /// The pin used for Button Two
     pub button_two: PF0<Input<PullUp>>,
-    /// GPIO control for GPIO port F
+    /// GPIO control for GPIO port F. The HAL only drives ports through APB, so
+    /// port F is on APB, though the chip has an AHB aperture for it too.
     pub portf_control: tm4c123x_hal::gpio::gpiof::GpioControl,
 
-    // moved from the tm4c123x crate, with the exception of GPIO_PORTF -- those pins are moved to
-    // the LEDs and buttons above.  sysctl is omitted, and only the power_control portion is
-    // included (above) to allow the user to enable peripherals.
-    #[doc = "WATCHDOG0"]
-    pub WATCHDOG0: tm4c123x_hal::tm4c123x::WATCHDOG0,
-    #[doc = "WATCHDOG1"]
-    pub WATCHDOG1: tm4c123x_hal::tm4c123x::WATCHDOG1,
-    #[doc = "GPIO_PORTA"]
-    pub GPIO_PORTA: tm4c123x_hal::tm4c123x::GPIO_PORTA,
-    #[doc = "GPIO_PORTB"]
-    pub GPIO_PORTB: tm4c123x_hal::tm4c123x::GPIO_PORTB,
-    #[doc = "GPIO_PORTC"]
-    pub GPIO_PORTC: tm4c123x_hal::tm4c123x::GPIO_PORTC,
-    #[doc = "GPIO_PORTD"]
-    pub GPIO_PORTD: tm4c123x_hal::tm4c123x::GPIO_PORTD,
-    #[doc = "SSI0"]
-    pub SSI0: tm4c123x_hal::tm4c123x::SSI0,
-    #[doc = "SSI1"]
-    pub SSI1: tm4c123x_hal::tm4c123x::SSI1,
-    #[doc = "SSI2"]
-    pub SSI2: tm4c123x_hal::tm4c123x::SSI2,
-    #[doc = "SSI3"]
-    pub SSI3: tm4c123x_hal::tm4c123x::SSI3,
-    #[doc = "UART0"]
-    pub UART0: tm4c123x_hal::tm4c123x::UART0,
-    #[doc = "UART1"]
-    pub UART1: tm4c123x_hal::tm4c123x::UART1,
-    #[doc = "UART2"]
-    pub UART2: tm4c123x_hal::tm4c123x::UART2,
-    #[doc = "UART3"]
-    pub UART3: tm4c123x_hal::tm4c123x::UART3,
-    #[doc = "UART4"]
-    pub UART4: tm4c123x_hal::tm4c123x::UART4,
-    #[doc = "UART5"]
-    pub UART5: tm4c123x_hal::tm4c123x::UART5,
-    #[doc = "UART6"]
-    pub UART6: tm4c123x_hal::tm4c123x::UART6,
-    #[doc = "UART7"]
-    pub UART7: tm4c123x_hal::tm4c123x::UART7,
-    #[doc = "I2C0"]
-    pub I2C0: tm4c123x_hal::tm4c123x::I2C0,
-    #[doc = "I2C1"]
-    pub I2C1: tm4c123x_hal::tm4c123x::I2C1,
-    #[doc = "I2C2"]
-    pub I2C2: tm4c123x_hal::tm4c123x::I2C2,
-    #[doc = "I2C3"]
-    pub I2C3: tm4c123x_hal::tm4c123x::I2C3,
-    #[doc = "GPIO_PORTE"]
-    pub GPIO_PORTE: tm4c123x_hal::tm4c123x::GPIO_PORTE,
-    #[doc = "PWM0"]
-    pub PWM0: tm4c123x_hal::tm4c123x::PWM0,
-    #[doc = "PWM1"]
-    pub PWM1: tm4c123x_hal::tm4c123x::PWM1,
-    #[doc = "QEI0"]
-    pub QEI0: tm4c123x_hal::tm4c123x::QEI0,
-    #[doc = "QEI1"]
-    pub QEI1: tm4c123x_hal::tm4c123x::QEI1,
-    #[doc = "TIMER0"]
-    pub TIMER0: tm4c123x_hal::tm4c123x::TIMER0,
-    #[doc = "TIMER1"]
-    pub TIMER1: tm4c123x_hal::tm4c123x::TIMER1,
-    #[doc = "TIMER2"]
-    pub TIMER2: tm4c123x_hal::tm4c123x::TIMER2,
-    #[doc = "TIMER3"]
-    pub TIMER3: tm4c123x_hal::tm4c123x::TIMER3,
-    #[doc = "TIMER4"]
-    pub TIMER4: tm4c123x_hal::tm4c123x::TIMER4,
-    #[doc = "TIMER5"]
-    pub TIMER5: tm4c123x_hal::tm4c123x::TIMER5,
-    #[doc = "WTIMER0"]
-    pub WTIMER0: tm4c123x_hal::tm4c123x::WTIMER0,
-    #[doc = "WTIMER1"]
-    pub WTIMER1: tm4c123x_hal::tm4c123x::WTIMER1,
-    #[doc = "ADC0"]
-    pub ADC0: tm4c123x_hal::tm4c123x::ADC0,
-    #[doc = "ADC1"]
-    pub ADC1: tm4c123x_hal::tm4c123x::ADC1,
-    #[doc = "COMP"]
-    pub COMP: tm4c123x_hal::tm4c123x::COMP,
-    #[doc = "CAN0"]
-    pub CAN0: tm4c123x_hal::tm4c123x::CAN0,
-    #[doc = "CAN1"]
-    pub CAN1: tm4c123x_hal::tm4c123x::CAN1,
-    #[doc = "WTIMER2"]
-    pub WTIMER2: tm4c123x_hal::tm4c123x::WTIMER2,
-    #[doc = "WTIMER3"]
-    pub WTIMER3: tm4c123x_hal::tm4c123x::WTIMER3,
-    #[doc = "WTIMER4"]
-    pub WTIMER4: tm4c123x_hal::tm4c123x::WTIMER4,
-    #[doc = "WTIMER5"]
-    pub WTIMER5: tm4c123x_hal::tm4c123x::WTIMER5,
-    #[doc = "USB0"]
-    pub USB0: tm4c123x_hal::tm4c123x::USB0,
-    #[doc = "GPIO_PORTA_AHB"]
-    pub GPIO_PORTA_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTA_AHB,
-    #[doc = "GPIO_PORTB_AHB"]
-    pub GPIO_PORTB_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTB_AHB,
-    #[doc = "GPIO_PORTC_AHB"]
-    pub GPIO_PORTC_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTC_AHB,
-    #[doc = "GPIO_PORTD_AHB"]
-    pub GPIO_PORTD_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTD_AHB,
-    #[doc = "GPIO_PORTE_AHB"]
-    pub GPIO_PORTE_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTE_AHB,
-    #[doc = "GPIO_PORTF_AHB"]
-    pub GPIO_PORTF_AHB: tm4c123x_hal::tm4c123x::GPIO_PORTF_AHB,
-    #[doc = "EEPROM"]
-    pub EEPROM: tm4c123x_hal::tm4c123x::EEPROM,
-    #[doc = "SYSEXC"]
-    pub SYSEXC: tm4c123x_hal::tm4c123x::SYSEXC,
-    #[doc = "HIB"]
-    pub HIB: tm4c123x_hal::tm4c123x::HIB,
-    #[doc = "FLASH_CTRL"]
-    pub FLASH_CTRL: tm4c123x_hal::tm4c123x::FLASH_CTRL,
-    #[doc = "UDMA"]
-    pub UDMA: tm4c123x_hal::tm4c123x::UDMA,
+    /// GPIO ports A to E
+    pub gpio: Gpio,
+    /// The UARTs
+    pub serial: Serial,
+    /// The timers and watchdogs
+    pub timers: Timers,
+    /// The ADCs and comparators
+    pub analog: Analog,
+    /// SSI, I2C, CAN and USB
+    pub comms: Comms,
+    /// Flash, EEPROM, hibernation and DMA
+    pub system: System,
+
+    chip: ChipInfo,
+    // The LM4F120 doesn't have these, so they only exist with the `tm4c123g`
+    // feature, and are only handed out (by the `take_*` methods) once we've
+    // checked the chip does.
+    #[cfg(feature = "tm4c123g")]
+    pwm0: Option<tm4c123x_hal::tm4c123x::PWM0>,
+    #[cfg(feature = "tm4c123g")]
+    pwm1: Option<tm4c123x_hal::tm4c123x::PWM1>,
+    #[cfg(feature = "tm4c123g")]
+    qei0: Option<tm4c123x_hal::tm4c123x::QEI0>,
+    #[cfg(feature = "tm4c123g")]
+    qei1: Option<tm4c123x_hal::tm4c123x::QEI1>,
 }
 
+/// The LEDs, the buttons and a console, as a `LaunchpadBoard`, for code
+/// written against that trait. It owns the pins and the UART, so make one
+/// by moving them out of `Board`:
+///
+/// ```rust,ignore
+/// let mut pins_a = board.gpio.porta;
+/// let console = board.serial.uart0.serial(
+///     pins_a.pa1.into_af_push_pull(&mut pins_a.control),
+///     pins_a.pa0.into_af_push_pull(&mut pins_a.control),
+///     Bps(115_200),
+///     serial::NewlineMode::Binary,
+///     &board.power_control,
+/// );
+/// let mut launchpad = Launchpad::new(
+///     board.led_red,
+///     board.led_blue,
+///     board.led_green,
+///     board.button_one,
+///     board.button_two,
+///     console,
+/// );
+/// ```
+pub struct Launchpad {
+    led_red: PF1<Output<PushPull>>,
+    led_blue: PF2<Output<PushPull>>,
+    led_green: PF3<Output<PushPull>>,
+    button_one: PF4<Input<PullUp>>,
+    button_two: PF0<Input<PullUp>>,
+    console: ConsoleUart,
+}
+
+/// UART0 on PA0/PA1 (through the on-board debugger), as the HAL drives it
+pub type ConsoleUart = serial::Serial<
+    tm4c123x_hal::tm4c123x::UART0,
+    PA1<AlternateFunction<AF1, PushPull>>,
+    PA0<AlternateFunction<AF1, PushPull>>,
+    (),
+    (),
+>;
+
+/// The emergency console's baud rate
+const CONSOLE_BPS: u32 = 115_200;
+
 // a moderately sane default, but will be replaced by Board::new()
 static mut CLOCKS: Clocks = Clocks {
     osc: Hertz(16_000_000),
@@ -233,6 +203,14 @@ impl Board {
         let led_green = pins.pf3.into_push_pull_output();
         let button_one = pins.pf4.into_pull_up_input();
         let button_two = pins.pf0.unlock(&mut pins.control).into_pull_up_input();
+        let chip = ChipInfo::read();
+        let gpio = Gpio {
+            porta: peripherals.GPIO_PORTA.split(&sysctl.power_control),
+            portb: peripherals.GPIO_PORTB.split(&sysctl.power_control),
+            portc: peripherals.GPIO_PORTC.split(&sysctl.power_control),
+            portd: peripherals.GPIO_PORTD.split(&sysctl.power_control),
+            porte: peripherals.GPIO_PORTE.split(&sysctl.power_control),
+        };
 
         Board {
             core_peripherals,
@@ -243,62 +221,203 @@ impl Board {
             button_one,
             button_two,
             portf_control: pins.control,
-            WATCHDOG0: peripherals.WATCHDOG0,
-            WATCHDOG1: peripherals.WATCHDOG1,
-            GPIO_PORTA: peripherals.GPIO_PORTA,
-            GPIO_PORTB: peripherals.GPIO_PORTB,
-            GPIO_PORTC: peripherals.GPIO_PORTC,
-            GPIO_PORTD: peripherals.GPIO_PORTD,
-            SSI0: peripherals.SSI0,
-            SSI1: peripherals.SSI1,
-            SSI2: peripherals.SSI2,
-            SSI3: peripherals.SSI3,
-            UART0: peripherals.UART0,
-            UART1: peripherals.UART1,
-            UART2: peripherals.UART2,
-            UART3: peripherals.UART3,
-            UART4: peripherals.UART4,
-            UART5: peripherals.UART5,
-            UART6: peripherals.UART6,
-            UART7: peripherals.UART7,
-            I2C0: peripherals.I2C0,
-            I2C1: peripherals.I2C1,
-            I2C2: peripherals.I2C2,
-            I2C3: peripherals.I2C3,
-            GPIO_PORTE: peripherals.GPIO_PORTE,
-            PWM0: peripherals.PWM0,
-            PWM1: peripherals.PWM1,
-            QEI0: peripherals.QEI0,
-            QEI1: peripherals.QEI1,
-            TIMER0: peripherals.TIMER0,
-            TIMER1: peripherals.TIMER1,
-            TIMER2: peripherals.TIMER2,
-            TIMER3: peripherals.TIMER3,
-            TIMER4: peripherals.TIMER4,
-            TIMER5: peripherals.TIMER5,
-            WTIMER0: peripherals.WTIMER0,
-            WTIMER1: peripherals.WTIMER1,
-            ADC0: peripherals.ADC0,
-            ADC1: peripherals.ADC1,
-            COMP: peripherals.COMP,
-            CAN0: peripherals.CAN0,
-            CAN1: peripherals.CAN1,
-            WTIMER2: peripherals.WTIMER2,
-            WTIMER3: peripherals.WTIMER3,
-            WTIMER4: peripherals.WTIMER4,
-            WTIMER5: peripherals.WTIMER5,
-            USB0: peripherals.USB0,
-            GPIO_PORTA_AHB: peripherals.GPIO_PORTA_AHB,
-            GPIO_PORTB_AHB: peripherals.GPIO_PORTB_AHB,
-            GPIO_PORTC_AHB: peripherals.GPIO_PORTC_AHB,
-            GPIO_PORTD_AHB: peripherals.GPIO_PORTD_AHB,
-            GPIO_PORTE_AHB: peripherals.GPIO_PORTE_AHB,
-            GPIO_PORTF_AHB: peripherals.GPIO_PORTF_AHB,
-            EEPROM: peripherals.EEPROM,
-            SYSEXC: peripherals.SYSEXC,
-            HIB: peripherals.HIB,
-            FLASH_CTRL: peripherals.FLASH_CTRL,
-            UDMA: peripherals.UDMA,
+            gpio,
+            serial: Serial {
+                uart0: Uart::new(peripherals.UART0),
+                uart1: Uart::new(peripherals.UART1),
+                uart2: Uart::new(peripherals.UART2),
+                uart3: Uart::new(peripherals.UART3),
+                uart4: Uart::new(peripherals.UART4),
+                uart5: Uart::new(peripherals.UART5),
+                uart6: Uart::new(peripherals.UART6),
+                uart7: Uart::new(peripherals.UART7),
+            },
+            timers: Timers {
+                timer0: Timer::new(peripherals.TIMER0),
+                timer1: Timer::new(peripherals.TIMER1),
+                timer2: Timer::new(peripherals.TIMER2),
+                timer3: Timer::new(peripherals.TIMER3),
+                timer4: Timer::new(peripherals.TIMER4),
+                timer5: Timer::new(peripherals.TIMER5),
+                wtimer0: Timer::new(peripherals.WTIMER0),
+                wtimer1: Timer::new(peripherals.WTIMER1),
+                wtimer2: Timer::new(peripherals.WTIMER2),
+                wtimer3: Timer::new(peripherals.WTIMER3),
+                wtimer4: Timer::new(peripherals.WTIMER4),
+                wtimer5: Timer::new(peripherals.WTIMER5),
+                watchdog0: peripherals.WATCHDOG0,
+                watchdog1: peripherals.WATCHDOG1,
+            },
+            analog: Analog {
+                adc0: peripherals.ADC0,
+                adc1: peripherals.ADC1,
+                comp: peripherals.COMP,
+            },
+            comms: Comms {
+                ssi0: peripherals.SSI0,
+                ssi1: peripherals.SSI1,
+                ssi2: peripherals.SSI2,
+                ssi3: peripherals.SSI3,
+                i2c0: peripherals.I2C0,
+                i2c1: peripherals.I2C1,
+                i2c2: peripherals.I2C2,
+                i2c3: peripherals.I2C3,
+                can0: peripherals.CAN0,
+                #[cfg(feature = "tm4c123g")]
+                can1: if chip.has(Peripheral::Can, 1) {
+                    Some(peripherals.CAN1)
+                } else {
+                    None
+                },
+                usb0: peripherals.USB0,
+            },
+            system: System {
+                flash: Flash::new(peripherals.FLASH_CTRL),
+                eeprom: peripherals.EEPROM,
+                hib: peripherals.HIB,
+                sysexc: peripherals.SYSEXC,
+                udma: peripherals.UDMA,
+            },
+            chip,
+            #[cfg(feature = "tm4c123g")]
+            pwm0: Some(peripherals.PWM0),
+            #[cfg(feature = "tm4c123g")]
+            pwm1: Some(peripherals.PWM1),
+            #[cfg(feature = "tm4c123g")]
+            qei0: Some(peripherals.QEI0),
+            #[cfg(feature = "tm4c123g")]
+            qei1: Some(peripherals.QEI1),
+        }
+    }
+
+    /// Which chip is fitted, and what it has (see `board::chip`).
+    pub fn chip_info(&self) -> ChipInfo {
+        self.chip
+    }
+
+    /// Take PWM module 0, if the chip has one (the LM4F120 doesn't).
+    #[cfg(feature = "tm4c123g")]
+    pub fn take_pwm0(&mut self) -> Result<tm4c123x_hal::tm4c123x::PWM0, chip::Error> {
+        take(&self.chip, &mut self.pwm0, Peripheral::Pwm, 0)
+    }
+
+    /// Take PWM module 1, if the chip has one (the LM4F120 doesn't).
+    #[cfg(feature = "tm4c123g")]
+    pub fn take_pwm1(&mut self) -> Result<tm4c123x_hal::tm4c123x::PWM1, chip::Error> {
+        take(&self.chip, &mut self.pwm1, Peripheral::Pwm, 1)
+    }
+
+    /// Take QEI module 0, if the chip has one (the LM4F120 doesn't).
+    #[cfg(feature = "tm4c123g")]
+    pub fn take_qei0(&mut self) -> Result<tm4c123x_hal::tm4c123x::QEI0, chip::Error> {
+        take(&self.chip, &mut self.qei0, Peripheral::Qei, 0)
+    }
+
+    /// Take QEI module 1, if the chip has one (the LM4F120 doesn't).
+    #[cfg(feature = "tm4c123g")]
+    pub fn take_qei1(&mut self) -> Result<tm4c123x_hal::tm4c123x::QEI1, chip::Error> {
+        take(&self.chip, &mut self.qei1, Peripheral::Qei, 1)
+    }
+
+    /// What the start-up check of the flash image found (see
+    /// `common::image_checksum`). This doesn't borrow the board, so it can
+    /// be called after the peripherals have been handed out.
+    pub fn image_integrity() -> image_checksum::Integrity {
+        image_checksum::integrity()
+    }
+}
+
+impl Launchpad {
+    /// Take the LEDs, the buttons and a console UART (see `Launchpad`).
+    pub fn new(
+        led_red: PF1<Output<PushPull>>,
+        led_blue: PF2<Output<PushPull>>,
+        led_green: PF3<Output<PushPull>>,
+        button_one: PF4<Input<PullUp>>,
+        button_two: PF0<Input<PullUp>>,
+        console: ConsoleUart,
+    ) -> Launchpad {
+        Launchpad {
+            led_red,
+            led_blue,
+            led_green,
+            button_one,
+            button_two,
+            console,
+        }
+    }
+
+    /// Give back the LEDs, the buttons and the console UART.
+    #[allow(clippy::type_complexity)]
+    pub fn release(
+        self,
+    ) -> (
+        PF1<Output<PushPull>>,
+        PF2<Output<PushPull>>,
+        PF3<Output<PushPull>>,
+        PF4<Input<PullUp>>,
+        PF0<Input<PullUp>>,
+        ConsoleUart,
+    ) {
+        (
+            self.led_red,
+            self.led_blue,
+            self.led_green,
+            self.button_one,
+            self.button_two,
+            self.console,
+        )
+    }
+}
+
+impl LaunchpadBoard for Launchpad {
+    fn name(&self) -> &'static str {
+        build_info().board()
+    }
+
+    fn set_led(&mut self, led: Led, on: bool) {
+        let _ = match (led, on) {
+            (Led::Red, true) => self.led_red.set_high(),
+            (Led::Red, false) => self.led_red.set_low(),
+            (Led::Blue, true) => self.led_blue.set_high(),
+            (Led::Blue, false) => self.led_blue.set_low(),
+            (Led::Green, true) => self.led_green.set_high(),
+            (Led::Green, false) => self.led_green.set_low(),
+        };
+    }
+
+    fn is_pressed(&self, button: Button) -> bool {
+        // The buttons pull the pin to ground
+        match button {
+            Button::One => self.button_one.is_low(),
+            Button::Two => self.button_two.is_low(),
+        }
+        .unwrap_or(false)
+    }
+
+    fn console_write(&mut self, bytes: &[u8]) {
+        let uart = &mut self.console;
+        for &byte in bytes {
+            // Like `serial::NewlineMode::SwapLFtoCRLF`
+            if byte == b'\n' {
+                while uart.write(b'\r').is_err() {}
+            }
+            while uart.write(byte).is_err() {}
+        }
+    }
+
+    fn console_read(&mut self) -> Option<u8> {
+        self.console.read().ok()
+    }
+
+    fn sysclk_hz(&self) -> u32 {
+        clocks().sysclk.0
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        let cycles_per_ms = clocks().sysclk.0 / 1000;
+        for _ in 0..ms {
+            cortex_m::asm::delay(cycles_per_ms);
         }
     }
 }
@@ -326,12 +445,76 @@ pub fn panic() -> ! {
     }
 }
 
+/// How this firmware was built (see `common::build_info`).
+pub fn build_info() -> &'static BuildInfo {
+    &build_info::BUILD_INFO
+}
+
+/// Blink the red LED `flashes` times, pause, and repeat forever - for
+/// problems found before anything has been set up to report them.
+pub fn blink_code(flashes: u32) -> ! {
+    use embedded_hal::blocking::delay::DelayMs;
+    let core_peripherals = unsafe { tm4c123x_hal::CorePeripherals::steal() };
+    let p = unsafe { tm4c123x_hal::Peripherals::steal() };
+    let pins = p.GPIO_PORTF.split(&p.SYSCTL.constrain().power_control);
+
+    let mut delay = tm4c123x_hal::delay::Delay::new(core_peripherals.SYST, unsafe { &CLOCKS });
+    let mut led_red = pins.pf1.into_push_pull_output();
+    loop {
+        for _ in 0..flashes {
+            let _ = led_red.set_high();
+            delay.delay_ms(200u32);
+            let _ = led_red.set_low();
+            delay.delay_ms(300u32);
+        }
+        delay.delay_ms(1500u32);
+    }
+}
+
+/// Grab UART0 on PA0/PA1 at 115200 bps, regardless of who else might own
+/// it, so that a fault handler can report what went wrong before we call
+/// `panic()`.
+pub(crate) unsafe fn emergency_console() -> impl core::fmt::Write {
+    let peripherals = tm4c123x_hal::Peripherals::steal();
+    let sysctl = peripherals.SYSCTL.constrain();
+    uart0(&sysctl.power_control, serial::NewlineMode::SwapLFtoCRLF)
+}
+
 // ****************************************************************************
 //
 // Private Functions
 //
 // ****************************************************************************
 
+/// Set up UART0 on PA0/PA1 at `CONSOLE_BPS`, stealing the UART and port A.
+unsafe fn uart0(power_control: &PowerControl, newline_mode: serial::NewlineMode) -> ConsoleUart {
+    let peripherals = tm4c123x_hal::Peripherals::steal();
+    let mut pins = peripherals.GPIO_PORTA.split(power_control);
+    serial::Serial::uart0(
+        peripherals.UART0,
+        pins.pa1.into_af_push_pull(&mut pins.control),
+        pins.pa0.into_af_push_pull(&mut pins.control),
+        (),
+        (),
+        Bps(CONSOLE_BPS),
+        newline_mode,
+        &CLOCKS,
+        power_control,
+    )
+}
+
+/// Hand out a peripheral the chip might not have.
+#[cfg(feature = "tm4c123g")]
+fn take<T>(
+    chip: &ChipInfo,
+    slot: &mut Option<T>,
+    peripheral: Peripheral,
+    instance: u8,
+) -> Result<T, chip::Error> {
+    chip.check(peripheral, instance)?;
+    slot.take().ok_or(chip::Error::AlreadyTaken)
+}
+
 // ****************************************************************************
 //
 // End Of File
@@ -0,0 +1,204 @@
+//! An 8-bit parallel bus, like the 8080-style interface many LCD
+//! controllers have.
+//!
+//! The eight data lines are a whole port, written a byte at a time through a
+//! `board::port_mask::PortMask`. The strobes - write (WR), read (RD) and
+//! data/command (DC) - can be any `OutputPin`s which can't fail (their
+//! `Error` is `Infallible`): another `PortMask` is fastest, but a HAL pin
+//! will do. Pass `NoPin` for a strobe you haven't
+//! wired up. Chip select is up to you; tie it low if the bus has nothing
+//! else on it.
+//!
+//! ```rust,ignore
+//! let mut portb = board.gpio.portb.into_masked(&board.power_control, Aperture::Ahb);
+//! let mut porte = board.gpio.porte.into_masked(&board.power_control, Aperture::Ahb);
+//! let wr = porte.outputs(1 << 1).unwrap();
+//! let rd = porte.outputs(1 << 2).unwrap();
+//! let dc = porte.outputs(1 << 3).unwrap();
+//! let mut bus = ParallelBus::new(portb.outputs(0xFF).unwrap(), wr, rd, dc)?;
+//! bus.command(0x2C); // ILI9341 Memory Write
+//! bus.write(&pixels);
+//! ```
+//!
+//! Port B is the only port with all eight pins on the BoosterPack headers,
+//! but PB6 and PB7 are joined to PD0 and PD1 by R9 and R10 (see
+//! `board::boosterpack`), so leave PD0 and PD1 alone, or take the resistors
+//! off.
+//!
+//! Writes go as fast as the CPU can strobe WR. Reads wait `read_wait` cycles
+//! (see `set_read_wait`) after RD falls, as most controllers are slower to
+//! answer.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::convert::Infallible;
+
+use cortex_m;
+use embedded_hal::digital::v2::OutputPin;
+
+use board::port_mask::PortMask;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// An 8080-style bus, with data on `PortMask` and strobes `WR`, `RD` and
+/// `DC`. The strobes are active low, and DC is low for a command.
+pub struct ParallelBus<WR, RD, DC> {
+    data: PortMask,
+    wr: WR,
+    rd: RD,
+    dc: DC,
+    read_wait: u32,
+}
+
+/// A strobe that isn't connected.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct NoPin;
+
+/// Things that can go wrong setting up a bus.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The data lines must be all eight pins of a port
+    NotEightPins,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How many cycles `read` waits by default - about 150 ns at 66.67 MHz.
+pub const DEFAULT_READ_WAIT: u32 = 10;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<WR, RD, DC> ParallelBus<WR, RD, DC>
+where
+    WR: OutputPin<Error = Infallible>,
+    RD: OutputPin<Error = Infallible>,
+    DC: OutputPin<Error = Infallible>,
+{
+    /// Make a bus, with the data lines driven and the strobes idle (high).
+    pub fn new(data: PortMask, wr: WR, rd: RD, dc: DC) -> Result<ParallelBus<WR, RD, DC>, Error> {
+        if data.pins() != 0xFF {
+            return Err(Error::NotEightPins);
+        }
+        let mut bus = ParallelBus {
+            data,
+            wr,
+            rd,
+            dc,
+            read_wait: DEFAULT_READ_WAIT,
+        };
+        bus.data.make_outputs();
+        // The strobes can't fail (they're `Infallible`), so there are no
+        // errors to pass on here or below
+        let _ = bus.wr.set_high();
+        let _ = bus.rd.set_high();
+        let _ = bus.dc.set_high();
+        Ok(bus)
+    }
+
+    /// Wait `cycles` after RD falls before reading the data lines.
+    pub fn set_read_wait(&mut self, cycles: u32) {
+        self.read_wait = cycles;
+    }
+
+    /// Send a command byte (DC low).
+    pub fn command(&mut self, command: u8) {
+        let _ = self.dc.set_low();
+        self.strobe(command);
+        let _ = self.dc.set_high();
+    }
+
+    /// Send data bytes (DC high).
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.strobe(byte);
+        }
+    }
+
+    /// Read data bytes (DC high). The data lines are inputs until it's done.
+    pub fn read(&mut self, buffer: &mut [u8]) {
+        self.data.make_inputs();
+        for byte in buffer.iter_mut() {
+            let _ = self.rd.set_low();
+            cortex_m::asm::delay(self.read_wait);
+            *byte = self.data.read();
+            let _ = self.rd.set_high();
+        }
+        self.data.make_outputs();
+    }
+
+    /// Give back the pins.
+    pub fn release(self) -> (PortMask, WR, RD, DC) {
+        (self.data, self.wr, self.rd, self.dc)
+    }
+}
+
+impl OutputPin for NoPin {
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl<WR, RD, DC> ParallelBus<WR, RD, DC>
+where
+    WR: OutputPin<Error = Infallible>,
+    RD: OutputPin<Error = Infallible>,
+    DC: OutputPin<Error = Infallible>,
+{
+    /// Put `byte` on the data lines, and latch it with WR (on the rising
+    /// edge).
+    fn strobe(&mut self, byte: u8) {
+        self.data.write(byte);
+        let _ = self.wr.set_low();
+        let _ = self.wr.set_high();
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,337 @@
+//! Giving pins their alternate functions, checked when you compile.
+//!
+//! `build.rs` generates a type for every function (`U1Rx`) from
+//! `pinmux.csv`, which is the datasheet's table of alternate functions, names
+//! the HAL's type for every pin (`PB0` is `gpiob::PB0<Tristate>`), and
+//! implements `OnPin<PB0> for U1Rx` for each pin a function is on.
+//! `PinMux::new` takes ports A to E and gives you their pins and one of
+//! each function, and `assign` takes both by value, so:
+//!
+//! * a function on a pin that doesn't have it doesn't compile (there's no
+//!   `OnPin` impl);
+//! * giving a pin two functions, or a function to two pins, doesn't compile
+//!   either (the pin or function has already been moved).
+//!
+//! `apply` then writes GPIOAFSEL, GPIOPCTL and friends and returns a
+//! `Report`, which prints as a table of what went where. The pins you don't
+//! assign are still in `Pins`, to use with the HAL as usual. `PINS` lists
+//! every pin's functions, for reports of your own.
+//!
+//! ```rust,ignore
+//! let (mut mux, pins, functions) = PinMux::new(board.gpio);
+//! mux.assign(pins.pb0, functions.u1rx);
+//! mux.assign(pins.pb1, functions.u1tx);
+//! mux.assign(pins.pe3, functions.ain0);
+//! // mux.assign(pins.pb0, functions.t2ccp0); - error: use of moved value
+//! // mux.assign(pins.pb2, functions.u5rx); - error: `U5Rx: OnPin<PB2>` not satisfied
+//! writeln!(uart, "{}", mux.apply())?;
+//! ```
+//!
+//! Port F (the LEDs and buttons, which `Board` has) is in `PINS`, but can't
+//! be assigned. JTAG (PC0 to PC3) can, but then the debugger can't connect.
+//! The PWM and QEI functions, and CAN1's, are only on the TM4C123, so they
+//! only exist with the `tm4c123g` feature (see `board::chip`).
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+use core::ptr;
+
+use tm4c123x_hal::gpio;
+
+use board::groups::Gpio;
+use common::launchpad::Port;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A pin.
+pub trait Pin {
+    /// The port it's on
+    const PORT: Port;
+    /// Its number in the port (0 to 7)
+    const NUMBER: u8;
+    /// Its name, e.g. `PB0`
+    const NAME: &'static str;
+}
+
+/// An alternate function.
+pub trait Function {
+    /// Its name in the datasheet, e.g. `U1Rx`
+    const NAME: &'static str;
+    /// Does the pin need to be open-drain (I2C data)?
+    const OPEN_DRAIN: bool;
+}
+
+/// A function that pin `P` has.
+pub trait OnPin<P: Pin>: Function {
+    /// How the function is selected on that pin
+    const SELECT: Select;
+}
+
+/// How a function is selected on a pin.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Select {
+    /// A digital function - the GPIOPCTL value
+    Digital(u8),
+    /// An analog function, selected with GPIOAMSEL
+    Analog,
+}
+
+/// A pin and all its functions.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PinInfo {
+    /// The pin's name, e.g. `PB0`
+    pub name: &'static str,
+    /// The port it's on
+    pub port: Port,
+    /// Its number in the port
+    pub number: u8,
+    /// Its functions, and how each is selected
+    pub functions: &'static [(Select, &'static str)],
+}
+
+/// Collects pin assignments, then applies them.
+pub struct PinMux {
+    report: Report,
+}
+
+/// Proof that pin `P` has been given function `F`.
+pub struct Assigned<P, F> {
+    pin: P,
+    function: F,
+}
+
+/// What `PinMux` did with each pin.
+#[derive(Debug, Clone, Copy)]
+pub struct Report {
+    entries: [Entry; MAX_ENTRIES],
+    len: usize,
+}
+
+/// One pin assignment.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    /// The pin's name
+    pub pin: &'static str,
+    /// The port it's on
+    pub port: Port,
+    /// Its number in the port
+    pub number: u8,
+    /// The function's name
+    pub function: &'static str,
+    /// How the function was selected
+    pub select: Select,
+    /// Whether the pin was made open-drain
+    pub open_drain: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+include!(concat!(env!("OUT_DIR"), "/pinmux.rs"));
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// There are 38 pins which can be assigned
+const MAX_ENTRIES: usize = 38;
+
+const GPIO_APB_BASE: [usize; 5] = [
+    0x4000_4000, // A
+    0x4000_5000, // B
+    0x4000_6000, // C
+    0x4000_7000, // D
+    0x4002_4000, // E
+];
+
+const GPIOAFSEL: usize = 0x420;
+const GPIOODR: usize = 0x50C;
+const GPIODEN: usize = 0x51C;
+const GPIOLOCK: usize = 0x520;
+const GPIOCR: usize = 0x524;
+const GPIOAMSEL: usize = 0x528;
+const GPIOPCTL: usize = 0x52C;
+/// Write to GPIOLOCK to unlock GPIOCR
+const UNLOCK_KEY: u32 = 0x4C4F_434B;
+
+const EMPTY_ENTRY: Entry = Entry {
+    pin: "",
+    port: Port::A,
+    number: 0,
+    function: "",
+    select: Select::Analog,
+    open_drain: false,
+};
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl PinMux {
+    /// Take ports A to E, and get their pins and one of every function to
+    /// assign.
+    pub fn new(gpio: Gpio) -> (PinMux, Pins, Functions) {
+        let pins = pins(gpio.porta, gpio.portb, gpio.portc, gpio.portd, gpio.porte);
+        let mux = PinMux {
+            report: Report {
+                entries: [EMPTY_ENTRY; MAX_ENTRIES],
+                len: 0,
+            },
+        };
+        (mux, pins, functions())
+    }
+
+    /// Give pin `P` function `F`, when `apply` is called.
+    pub fn assign<P: Pin, F: OnPin<P>>(&mut self, pin: P, function: F) -> Assigned<P, F> {
+        // There's one of each pin, so this can't overflow
+        self.report.entries[self.report.len] = Entry {
+            pin: P::NAME,
+            port: P::PORT,
+            number: P::NUMBER,
+            function: F::NAME,
+            select: F::SELECT,
+            open_drain: F::OPEN_DRAIN,
+        };
+        self.report.len += 1;
+        Assigned { pin, function }
+    }
+
+    /// Write the assignments to the GPIO registers.
+    pub fn apply(self) -> Report {
+        for entry in self.report.entries() {
+            unsafe { apply(entry) };
+        }
+        self.report
+    }
+}
+
+impl<P, F> Assigned<P, F> {
+    /// Give back the pin and the function, e.g. to set the pin up with the
+    /// HAL. The pin keeps the function until you do.
+    pub fn release(self) -> (P, F) {
+        (self.pin, self.function)
+    }
+}
+
+impl Report {
+    /// The assignments, in the order they were made.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..self.len]
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pin  Function  Selected by")?;
+        for entry in self.entries() {
+            write!(f, "{:<4} {:<9} ", entry.pin, entry.function)?;
+            match entry.select {
+                Select::Digital(pctl) => write!(f, "PCTL {}", pctl)?,
+                Select::Analog => write!(f, "AMSEL")?,
+            }
+            if entry.open_drain {
+                write!(f, ", open-drain")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PinInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        for (select, function) in self.functions {
+            match select {
+                Select::Digital(pctl) => write!(f, " {} ({})", function, pctl)?,
+                Select::Analog => write!(f, " {} (analog)", function)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Look a pin up by name, e.g. `PB0`.
+pub fn pin_info(name: &str) -> Option<&'static PinInfo> {
+    PINS.iter().find(|info| info.name == name)
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Write one assignment to its port's registers.
+unsafe fn apply(entry: &Entry) {
+    let base = GPIO_APB_BASE[entry.port as usize];
+    let bit = 1u32 << entry.number;
+    let register = |offset: usize| (base + offset) as *mut u32;
+    let set = |offset: usize, on: bool| {
+        let value = ptr::read_volatile(register(offset));
+        let value = if on { value | bit } else { value & !bit };
+        ptr::write_volatile(register(offset), value);
+    };
+    // PD7 can be NMI, and PC0 to PC3 are JTAG, so they're locked
+    let locked = match entry.port {
+        Port::C => entry.number < 4,
+        Port::D => entry.number == 7,
+        _ => false,
+    };
+    if locked {
+        ptr::write_volatile(register(GPIOLOCK), UNLOCK_KEY);
+        set(GPIOCR, true);
+    }
+    match entry.select {
+        Select::Digital(pctl) => {
+            let shift = u32::from(entry.number) * 4;
+            let value = ptr::read_volatile(register(GPIOPCTL));
+            let value = (value & !(0xF << shift)) | (u32::from(pctl) << shift);
+            ptr::write_volatile(register(GPIOPCTL), value);
+            set(GPIOAMSEL, false);
+            set(GPIOODR, entry.open_drain);
+            set(GPIOAFSEL, true);
+            set(GPIODEN, true);
+        }
+        Select::Analog => {
+            set(GPIODEN, false);
+            set(GPIOAFSEL, true);
+            set(GPIOAMSEL, true);
+        }
+    }
+    if locked {
+        // Any value but the key locks it again
+        set(GPIOCR, false);
+        ptr::write_volatile(register(GPIOLOCK), 0);
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,316 @@
+//! Reading and writing several pins of a port at once.
+//!
+//! GPIODATA isn't one register but 256: bits 9:2 of the address say which
+//! pins a read or write touches. Writing `0x0F` at the address for pins 0
+//! and 1 sets those two and leaves the other six alone - in one store, so
+//! there's no read-modify-write for an interrupt to get in the middle of. A
+//! `PortMask` is one of those addresses.
+//!
+//! Ports A to E come from `board::groups::IntoAperture::into_masked`, through
+//! whichever aperture you like (AHB is faster). `MaskedPort::outputs` and
+//! `inputs` set pins up and give you a `PortMask` for them. Each pin is in at
+//! most one `PortMask` at a time - asking for a pin that's already been
+//! handed out gets you `None` - until you give the mask back with
+//! `MaskedPort::release`:
+//!
+//! ```rust,ignore
+//! let mut portb = board.gpio.portb.into_masked(&board.power_control, Aperture::Ahb);
+//! let low = portb.outputs(0x0F).unwrap();
+//! let high = portb.inputs(0xF0).unwrap();
+//! assert!(portb.mask(0x01).is_none());
+//! low.write(0b0101);
+//! let nibble = high.read() >> 4;
+//! ```
+//!
+//! Port F has the LEDs and buttons, which `Board` has set up through APB, so
+//! it needs `MaskedPort::new`. The LEDs are PF1 to PF3:
+//!
+//! ```rust,ignore
+//! let mut portf = unsafe { MaskedPort::new(Port::F, Aperture::Apb) };
+//! let leds = portf.mask(0b1110).unwrap();
+//! leds.write(0b1010); // red and green (yellow), in one go
+//! ```
+//!
+//! PC0 to PC3 (JTAG) and PD7 (NMI) are locked, so `outputs` and `inputs`
+//! can't take them away from their special functions, and give you `None`
+//! if you ask for them.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::convert::Infallible;
+use core::ptr;
+
+use cortex_m;
+use embedded_hal::digital::v2::OutputPin;
+
+use common::launchpad::Port;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The two ways into a GPIO port's registers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Aperture {
+    /// The Advanced Peripheral Bus - what the HAL uses
+    Apb,
+    /// The Advanced High-Performance Bus - faster for back-to-back accesses
+    Ahb,
+}
+
+/// A GPIO port, driven through `PortMask`s.
+#[derive(Debug)]
+pub struct MaskedPort {
+    port: Port,
+    aperture: Aperture,
+    /// The pins in a `PortMask` right now
+    taken: u8,
+}
+
+/// Some pins of a port, read and written together. There's only ever one
+/// `PortMask` for a pin, so it can't be copied.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PortMask {
+    base: usize,
+    pins: u8,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const GPIO_APB_BASE: [usize; 6] = [
+    0x4000_4000, // A
+    0x4000_5000, // B
+    0x4000_6000, // C
+    0x4000_7000, // D
+    0x4002_4000, // E
+    0x4002_5000, // F
+];
+
+/// Port A - the rest follow at 4 KiB intervals
+const GPIO_AHB_BASE: usize = 0x4005_8000;
+
+const GPIODIR: usize = 0x400;
+const GPIOAFSEL: usize = 0x420;
+const GPIODEN: usize = 0x51C;
+const GPIOAMSEL: usize = 0x528;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl MaskedPort {
+    /// Drive `port` through `aperture`.
+    ///
+    /// # Safety
+    ///
+    /// The port must be powered up and switched to `aperture` (see
+    /// `board::groups::IntoAperture::into_masked`), and nothing else may be
+    /// setting up the pins you pass to `outputs` or `inputs`. There must only
+    /// be one `MaskedPort` for a port.
+    pub unsafe fn new(port: Port, aperture: Aperture) -> MaskedPort {
+        MaskedPort {
+            port,
+            aperture,
+            taken: 0,
+        }
+    }
+
+    /// Which port this is.
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Which aperture it's driven through.
+    pub fn aperture(&self) -> Aperture {
+        self.aperture
+    }
+
+    /// Make `pins` digital push-pull outputs. `None` if any of them are
+    /// locked, or have already been handed out.
+    pub fn outputs(&mut self, pins: u8) -> Option<PortMask> {
+        if pins & self.locked() != 0 {
+            return None;
+        }
+        let mask = self.mask(pins)?;
+        mask.make_digital();
+        mask.make_outputs();
+        Some(mask)
+    }
+
+    /// Make `pins` digital inputs. `None` if any of them are locked, or have
+    /// already been handed out.
+    pub fn inputs(&mut self, pins: u8) -> Option<PortMask> {
+        if pins & self.locked() != 0 {
+            return None;
+        }
+        let mask = self.mask(pins)?;
+        mask.make_digital();
+        mask.make_inputs();
+        Some(mask)
+    }
+
+    /// Get at `pins`, however they're set up. `None` if any of them have
+    /// already been handed out.
+    pub fn mask(&mut self, pins: u8) -> Option<PortMask> {
+        if self.taken & pins != 0 {
+            return None;
+        }
+        self.taken |= pins;
+        Some(PortMask {
+            base: self.base(),
+            pins,
+        })
+    }
+
+    /// Give back a mask's pins, so they can be handed out again.
+    ///
+    /// # Panics
+    ///
+    /// If the mask didn't come from this port.
+    pub fn release(&mut self, mask: PortMask) {
+        assert!(mask.base == self.base(), "PortMask from another port");
+        self.taken &= !mask.pins;
+    }
+
+    /// The pins in a `PortMask` right now.
+    pub fn taken(&self) -> u8 {
+        self.taken
+    }
+}
+
+impl PortMask {
+    /// The pins, as a bitmask (bit 0 is pin 0).
+    pub fn pins(&self) -> u8 {
+        self.pins
+    }
+
+    /// Read the pins. The other bits are zero.
+    pub fn read(&self) -> u8 {
+        unsafe { ptr::read_volatile(self.data()) as u8 }
+    }
+
+    /// Set the pins to the matching bits of `value`, all at once. The other
+    /// bits are ignored.
+    pub fn write(&self, value: u8) {
+        unsafe { ptr::write_volatile(self.data(), u32::from(value)) };
+    }
+
+    /// Drive all the pins high.
+    pub fn set(&self) {
+        self.write(0xFF);
+    }
+
+    /// Drive all the pins low.
+    pub fn clear(&self) {
+        self.write(0x00);
+    }
+
+    /// Turn the pins into outputs, e.g. to turn a bus around.
+    pub fn make_outputs(&self) {
+        self.modify(GPIODIR, true);
+    }
+
+    /// Turn the pins into inputs.
+    pub fn make_inputs(&self) {
+        self.modify(GPIODIR, false);
+    }
+}
+
+/// All the pins in the mask go high or low together.
+impl OutputPin for PortMask {
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.set();
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.clear();
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl MaskedPort {
+    /// The start of the port's registers, through our aperture.
+    fn base(&self) -> usize {
+        match self.aperture {
+            Aperture::Apb => GPIO_APB_BASE[self.port as usize],
+            Aperture::Ahb => GPIO_AHB_BASE + 0x1000 * self.port as usize,
+        }
+    }
+
+    /// The pins which are locked to their special functions.
+    fn locked(&self) -> u8 {
+        match self.port {
+            Port::C => 0x0F,
+            Port::D => 0x80,
+            _ => 0,
+        }
+    }
+}
+
+impl PortMask {
+    /// The GPIODATA address for these pins.
+    fn data(&self) -> *mut u32 {
+        (self.base + (usize::from(self.pins) << 2)) as *mut u32
+    }
+
+    /// Take the pins off their alternate and analog functions.
+    fn make_digital(&self) {
+        self.modify(GPIOAFSEL, false);
+        self.modify(GPIOAMSEL, false);
+        self.modify(GPIODEN, true);
+    }
+
+    /// Set or clear our bits in a register other `PortMask`s share.
+    fn modify(&self, offset: usize, on: bool) {
+        let register = (self.base + offset) as *mut u32;
+        let bits = u32::from(self.pins);
+        cortex_m::interrupt::free(|_| unsafe {
+            let value = ptr::read_volatile(register);
+            let value = if on { value | bits } else { value & !bits };
+            ptr::write_volatile(register, value);
+        });
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
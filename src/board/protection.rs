@@ -0,0 +1,405 @@
+//! Locking the flash and the debug port, for production boards.
+//!
+//! The flash is protected in 2 KiB blocks. A block can be made:
+//!
+//! * write-protected (FMPPEn) - it can be read and executed, but not
+//!   programmed or erased; or
+//! * execute-only (FMPREn and FMPPEn) - it can only be executed, so neither
+//!   a debugger nor any other code can read it back.
+//!
+//! and the JTAG/SWD port can be disabled (BOOTCFG.DBG1).
+//!
+//! Changes are made in two steps. `apply` writes the protection registers,
+//! which protects the flash straight away but only until the next reset.
+//! `commit` then makes the same protection permanent - it refuses anything
+//! that hasn't been applied first, so you can check the board still works
+//! with the protection in place. The debug port can only be disabled by a
+//! commit. Committed protection can only be undone by the debug port's
+//! unlock sequence, which mass-erases the flash - so once the debug port is
+//! disabled as well, it can't be undone at all.
+//!
+//! Plans that would leave the board unusable are refused: making the running
+//! image execute-only (it reads its own constants, so it would fault), and
+//! disabling the debug port when nothing could ever reprogram the flash
+//! again (the running image write-protected). With the boot loader (the
+//! `bootloader`, `slot-a` and `slot-b` features) the boot loader, the
+//! metadata and both slots can't be made execute-only, and the metadata and
+//! slots must stay writable if the debug port is disabled, so updates can
+//! still be installed.
+//!
+//! `dry_run` makes all the same checks and returns the register values that
+//! would be committed, without touching anything.
+//!
+//! ```rust,ignore
+//! let plan = ProtectionPlan::new()
+//!     .write_protect(0, 0xB800)?
+//!     .disable_debug_port();
+//! plan.apply(&mut flash)?;
+//! // ...check everything still works...
+//! let confirm = unsafe { PermanentProtectionConfirmation::new() };
+//! plan.commit(&mut flash, confirm)?;
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::ops::Range;
+use core::ptr;
+
+use board::flash::{self, Flash, FLASH_SIZE};
+#[cfg(any(feature = "bootloader", feature = "slot-a", feature = "slot-b"))]
+use common::update::{self, Region};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Protection to add. Protection can only be added, never removed, so the
+/// plan is combined with whatever is already in place.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ProtectionPlan {
+    /// Cleared bits are made execute-only
+    read_enable: [u32; REGISTERS],
+    /// Cleared bits are write-protected
+    program_enable: [u32; REGISTERS],
+    disable_debug_port: bool,
+}
+
+/// The register values a plan works out to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Registers {
+    /// FMPRE0..3 - a cleared bit makes a block execute-only
+    pub fmpre: [u32; REGISTERS],
+    /// FMPPE0..3 - a cleared bit makes a block write-protected
+    pub fmppe: [u32; REGISTERS],
+    /// BOOTCFG - only committed if the plan disables the debug port
+    pub bootcfg: u32,
+}
+
+/// Proof that you understand committed protection can't be undone (without
+/// erasing the flash, or at all, once the debug port is disabled).
+pub struct PermanentProtectionConfirmation(());
+
+/// Things that can go wrong changing the protection.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The range isn't inside the flash
+    OutOfBounds,
+    /// Ranges must start and end on a 2 KiB block boundary
+    NotAligned,
+    /// The plan makes blocks of the running image execute-only
+    ExecuteOnlyInUse,
+    /// The plan disables the debug port, and leaves no way to reprogram the
+    /// flash
+    LockedOut,
+    /// `commit` was asked for protection which hasn't been `apply`d
+    NotApplied,
+    /// The flash controller wouldn't commit a register
+    Flash(flash::Error),
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The size of a protection block, in bytes.
+pub const BLOCK_SIZE: u32 = 2048;
+
+/// How many of each protection register there are.
+pub const REGISTERS: usize = 4;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// FMPRE0..3, in the System Control block
+const FMPRE: *mut u32 = 0x400F_E200 as *mut u32;
+/// FMPPE0..3, in the System Control block
+const FMPPE: *mut u32 = 0x400F_E400 as *mut u32;
+/// BOOTCFG, in the System Control block
+const BOOTCFG: *const u32 = 0x400F_E1D0 as *const u32;
+/// Clearing this disables JTAG and SWD
+const BOOTCFG_DBG1: u32 = 1 << 1;
+
+/// The FMA values which select the register to commit: FMPREn is 2n, FMPPEn
+/// is 2n + 1.
+const COMMIT_BOOTCFG: u32 = 0x7510_0000;
+
+const BLOCKS_PER_REGISTER: u32 = 32;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl PermanentProtectionConfirmation {
+    /// Confirm that the protection may be made permanent.
+    ///
+    /// # Safety
+    ///
+    /// A mistake can't be undone - the flash may never be readable or
+    /// reprogrammable again.
+    pub unsafe fn new() -> PermanentProtectionConfirmation {
+        PermanentProtectionConfirmation(())
+    }
+}
+
+impl Default for ProtectionPlan {
+    fn default() -> ProtectionPlan {
+        ProtectionPlan::new()
+    }
+}
+
+impl ProtectionPlan {
+    /// A plan which adds no protection.
+    pub fn new() -> ProtectionPlan {
+        ProtectionPlan {
+            read_enable: [0xFFFF_FFFF; REGISTERS],
+            program_enable: [0xFFFF_FFFF; REGISTERS],
+            disable_debug_port: false,
+        }
+    }
+
+    /// Write-protect the blocks from `start` for `length` bytes.
+    pub fn write_protect(mut self, start: u32, length: u32) -> Result<ProtectionPlan, Error> {
+        for block in blocks(start, length)? {
+            clear_bit(&mut self.program_enable, block);
+        }
+        Ok(self)
+    }
+
+    /// Make the blocks from `start` for `length` bytes execute-only. This
+    /// write-protects them too.
+    pub fn execute_only(mut self, start: u32, length: u32) -> Result<ProtectionPlan, Error> {
+        for block in blocks(start, length)? {
+            clear_bit(&mut self.read_enable, block);
+            clear_bit(&mut self.program_enable, block);
+        }
+        Ok(self)
+    }
+
+    /// Disable JTAG and SWD, when the plan is committed.
+    pub fn disable_debug_port(mut self) -> ProtectionPlan {
+        self.disable_debug_port = true;
+        self
+    }
+
+    /// Check the plan, and work out the register values `commit` would
+    /// write, without changing anything.
+    pub fn dry_run(&self) -> Result<Registers, Error> {
+        let registers = self.registers();
+        check(&registers, self.disable_debug_port)?;
+        Ok(registers)
+    }
+
+    /// Protect the flash until the next reset. Returns the new register
+    /// values.
+    pub fn apply(&self, _flash: &mut Flash) -> Result<Registers, Error> {
+        let registers = self.dry_run()?;
+        for n in 0..REGISTERS {
+            unsafe {
+                ptr::write_volatile(FMPRE.add(n), registers.fmpre[n]);
+                ptr::write_volatile(FMPPE.add(n), registers.fmppe[n]);
+            }
+        }
+        Ok(registers)
+    }
+
+    /// Make the protection permanent, and disable the debug port if the plan
+    /// says so. The protection must already have been `apply`d. Returns the
+    /// committed register values.
+    pub fn commit(
+        &self,
+        flash: &mut Flash,
+        _confirm: PermanentProtectionConfirmation,
+    ) -> Result<Registers, Error> {
+        let registers = self.dry_run()?;
+        let current = current();
+        if registers.fmpre != current.fmpre || registers.fmppe != current.fmppe {
+            return Err(Error::NotApplied);
+        }
+        for n in 0..REGISTERS as u32 {
+            let index = n as usize;
+            flash
+                .commit_register(2 * n, registers.fmpre[index])
+                .map_err(Error::Flash)?;
+            flash
+                .commit_register(2 * n + 1, registers.fmppe[index])
+                .map_err(Error::Flash)?;
+        }
+        if self.disable_debug_port {
+            flash
+                .commit_register(COMMIT_BOOTCFG, registers.bootcfg)
+                .map_err(Error::Flash)?;
+        }
+        Ok(registers)
+    }
+
+    /// Combine the plan with the protection already in place.
+    fn registers(&self) -> Registers {
+        let current = current();
+        let mut registers = current;
+        for n in 0..REGISTERS {
+            registers.fmpre[n] &= self.read_enable[n];
+            registers.fmppe[n] &= self.program_enable[n];
+        }
+        if self.disable_debug_port {
+            registers.bootcfg &= !BOOTCFG_DBG1;
+        }
+        registers
+    }
+}
+
+impl Registers {
+    /// Is the block containing `address` write-protected?
+    pub fn is_write_protected(&self, address: u32) -> bool {
+        !bit(&self.fmppe, address / BLOCK_SIZE)
+    }
+
+    /// Is the block containing `address` execute-only?
+    pub fn is_execute_only(&self, address: u32) -> bool {
+        !bit(&self.fmpre, address / BLOCK_SIZE)
+    }
+
+    /// Is the debug port enabled?
+    pub fn debug_port_enabled(&self) -> bool {
+        self.bootcfg & BOOTCFG_DBG1 != 0
+    }
+}
+
+/// The protection in place right now.
+pub fn current() -> Registers {
+    let mut registers = Registers {
+        fmpre: [0; REGISTERS],
+        fmppe: [0; REGISTERS],
+        bootcfg: unsafe { ptr::read_volatile(BOOTCFG) },
+    };
+    for n in 0..REGISTERS {
+        unsafe {
+            registers.fmpre[n] = ptr::read_volatile(FMPRE.add(n));
+            registers.fmppe[n] = ptr::read_volatile(FMPPE.add(n));
+        }
+    }
+    registers
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Refuse register values which would leave the board unusable.
+fn check(registers: &Registers, disable_debug_port: bool) -> Result<(), Error> {
+    let (image_start, image_end) = flash::image_range();
+    if overlaps(
+        registers,
+        image_start,
+        image_end,
+        Registers::is_execute_only,
+    ) {
+        return Err(Error::ExecuteOnlyInUse);
+    }
+    #[cfg(any(feature = "bootloader", feature = "slot-a", feature = "slot-b"))]
+    {
+        let layout = update::LAUNCHPAD;
+        let boot_loader = Region {
+            start: 0,
+            size: layout.metadata.start,
+        };
+        // Each of these runs an image, or is read by the boot loader
+        for region in layout
+            .slots
+            .iter()
+            .chain(Some(&layout.metadata))
+            .chain(Some(&boot_loader))
+        {
+            let end = region.start + region.size;
+            if overlaps(registers, region.start, end, Registers::is_execute_only) {
+                return Err(Error::ExecuteOnlyInUse);
+            }
+        }
+        if !disable_debug_port {
+            return Ok(());
+        }
+        for region in layout.slots.iter().chain(Some(&layout.metadata)) {
+            let end = region.start + region.size;
+            if overlaps(registers, region.start, end, Registers::is_write_protected) {
+                return Err(Error::LockedOut);
+            }
+        }
+    }
+    #[cfg(not(any(feature = "bootloader", feature = "slot-a", feature = "slot-b")))]
+    {
+        // Without the boot loader, only the debug port can replace the image
+        if disable_debug_port
+            && overlaps(
+                registers,
+                image_start,
+                image_end,
+                Registers::is_write_protected,
+            )
+        {
+            return Err(Error::LockedOut);
+        }
+    }
+    Ok(())
+}
+
+/// Does `test` hold for any block between `start` and `end`?
+fn overlaps(
+    registers: &Registers,
+    start: u32,
+    end: u32,
+    test: fn(&Registers, u32) -> bool,
+) -> bool {
+    let first = start / BLOCK_SIZE;
+    let last = (end + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    (first..last).any(|block| test(registers, block * BLOCK_SIZE))
+}
+
+/// The blocks in a range, which must be block aligned.
+fn blocks(start: u32, length: u32) -> Result<Range<u32>, Error> {
+    let end = start.checked_add(length).ok_or(Error::OutOfBounds)?;
+    if end > FLASH_SIZE {
+        return Err(Error::OutOfBounds);
+    }
+    if start % BLOCK_SIZE != 0 || length % BLOCK_SIZE != 0 {
+        return Err(Error::NotAligned);
+    }
+    Ok(start / BLOCK_SIZE..end / BLOCK_SIZE)
+}
+
+fn bit(bits: &[u32; REGISTERS], block: u32) -> bool {
+    let register = (block / BLOCKS_PER_REGISTER) as usize;
+    bits[register] & (1 << (block % BLOCKS_PER_REGISTER)) != 0
+}
+
+fn clear_bit(bits: &mut [u32; REGISTERS], block: u32) {
+    let register = (block / BLOCKS_PER_REGISTER) as usize;
+    bits[register] &= !(1 << (block % BLOCKS_PER_REGISTER));
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,312 @@
+//! Bindings to the TivaWare driver library routines in the mask ROM.
+//!
+//! The ROM holds a copy of TI's driverlib, reached through a table of
+//! tables at `0x0100_0010` (`ROM_APITABLE` in TivaWare's `rom.h`). Calling
+//! the ROM copies saves flash, and the flash and clock routines are the
+//! ones TI tested.
+//!
+//! Each routine is described by an `Entry` - which table it's in, and where
+//! in that table, as given by `rom.h`. All the routines here are in the ROM
+//! of every TM4C123 revision. `Rom::new` checks DID0 to make sure this is a
+//! chip we know, and every call checks the table points into the ROM before
+//! jumping through it.
+//!
+//! ```rust,ignore
+//! let rom = Rom::new()?;
+//! rom.flash_erase(0x3_F000)?;
+//! rom.flash_program(0x3_F000, &[0x1234_5678])?;
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::convert::Infallible;
+use core::mem;
+use core::ptr;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The ROM, on a chip whose revision we know.
+pub struct Rom {
+    revision: Revision,
+}
+
+/// A TM4C123 silicon revision, from DID0. The ROM contents depend on it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Revision {
+    /// Revision A1
+    A1,
+    /// Revision A2
+    A2,
+    /// Revision A3
+    A3,
+    /// Revision B0
+    B0,
+    /// Revision B1 (most Launchpads)
+    B1,
+    /// Revision B2
+    B2,
+}
+
+/// One of the sub-tables in `ROM_APITABLE`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Table {
+    /// `ROM_UARTTABLE`
+    Uart = 1,
+    /// `ROM_FLASHTABLE`
+    Flash = 7,
+    /// `ROM_SYSCTLTABLE`
+    SysCtl = 13,
+}
+
+/// Where a ROM routine lives.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    /// The table it's in
+    pub table: Table,
+    /// Its position in that table
+    pub index: u8,
+}
+
+/// Things that can go wrong calling the ROM.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// This isn't a TM4C123 (or LM4F120), or its revision is one we don't
+    /// know the ROM layout for
+    UnknownChip {
+        /// The DID0 register
+        did0: u32,
+    },
+    /// The table doesn't point to a routine in ROM
+    NotInRom,
+    /// The ROM routine returned an error
+    Failed,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// `ROM_UARTCharPut`
+pub const UART_CHAR_PUT: Entry = Entry::new(Table::Uart, 0);
+
+/// `ROM_FlashProgram`
+pub const FLASH_PROGRAM: Entry = Entry::new(Table::Flash, 0);
+
+/// `ROM_FlashErase`
+pub const FLASH_ERASE: Entry = Entry::new(Table::Flash, 3);
+
+/// `ROM_SysCtlSRAMSizeGet`
+pub const SYSCTL_SRAM_SIZE_GET: Entry = Entry::new(Table::SysCtl, 1);
+
+/// `ROM_SysCtlFlashSizeGet`
+pub const SYSCTL_FLASH_SIZE_GET: Entry = Entry::new(Table::SysCtl, 2);
+
+/// `ROM_SysCtlReset`
+pub const SYSCTL_RESET: Entry = Entry::new(Table::SysCtl, 19);
+
+/// `ROM_SysCtlClockSet`
+pub const SYSCTL_CLOCK_SET: Entry = Entry::new(Table::SysCtl, 23);
+
+/// `ROM_SysCtlClockGet`
+pub const SYSCTL_CLOCK_GET: Entry = Entry::new(Table::SysCtl, 24);
+
+/// `ROM_SysCtlDelay`
+pub const SYSCTL_DELAY: Entry = Entry::new(Table::SysCtl, 34);
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type FlashProgramFn = unsafe extern "C" fn(*const u32, u32, u32) -> i32;
+type FlashEraseFn = unsafe extern "C" fn(u32) -> i32;
+type SizeGetFn = unsafe extern "C" fn() -> u32;
+type ClockSetFn = unsafe extern "C" fn(u32);
+type DelayFn = unsafe extern "C" fn(u32);
+type UartCharPutFn = unsafe extern "C" fn(u32, u8);
+type NoArgsFn = unsafe extern "C" fn();
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// `ROM_APITABLE`
+const API_TABLE: *const u32 = 0x0100_0010 as *const u32;
+
+/// The mask ROM's address range
+const ROM_START: usize = 0x0100_0000;
+const ROM_END: usize = 0x0100_C000;
+
+const DID0: *const u32 = 0x400F_E000 as *const u32;
+/// DID0 CLASS for the TM4C123 / LM4F120 ("Blizzard") family
+const DID0_CLASS_TM4C123: u32 = 0x05;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Entry {
+    /// Describe a ROM routine.
+    pub const fn new(table: Table, index: u8) -> Entry {
+        Entry { table, index }
+    }
+}
+
+impl Rom {
+    /// Check the chip and find out which ROM it has.
+    pub fn new() -> Result<Rom, Error> {
+        let did0 = unsafe { ptr::read_volatile(DID0) };
+        match revision(did0) {
+            Some(revision) => Ok(Rom { revision }),
+            None => Err(Error::UnknownChip { did0 }),
+        }
+    }
+
+    /// The silicon revision.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// The ROM's own version number (`ROM_VERSION`).
+    pub fn version(&self) -> u32 {
+        unsafe { ptr::read_volatile(API_TABLE) }
+    }
+
+    /// Find the address of a ROM routine.
+    pub fn address(&self, entry: Entry) -> Result<usize, Error> {
+        let address = unsafe {
+            let table = ptr::read_volatile(API_TABLE.add(entry.table as usize)) as *const u32;
+            ptr::read_volatile(table.add(usize::from(entry.index))) as usize
+        };
+        check_function(address)
+    }
+
+    /// Program words into flash with `ROM_FlashProgram`. The address must be
+    /// word aligned.
+    pub fn flash_program(&self, address: u32, words: &[u32]) -> Result<(), Error> {
+        let f: FlashProgramFn = unsafe { mem::transmute(self.address(FLASH_PROGRAM)?) };
+        match unsafe { f(words.as_ptr(), address, (words.len() * 4) as u32) } {
+            0 => Ok(()),
+            _ => Err(Error::Failed),
+        }
+    }
+
+    /// Erase the 1 KiB flash page at `address` with `ROM_FlashErase`.
+    pub fn flash_erase(&self, address: u32) -> Result<(), Error> {
+        let f: FlashEraseFn = unsafe { mem::transmute(self.address(FLASH_ERASE)?) };
+        match unsafe { f(address) } {
+            0 => Ok(()),
+            _ => Err(Error::Failed),
+        }
+    }
+
+    /// The size of SRAM, in bytes, from `ROM_SysCtlSRAMSizeGet`.
+    pub fn sram_size(&self) -> Result<u32, Error> {
+        let f: SizeGetFn = unsafe { mem::transmute(self.address(SYSCTL_SRAM_SIZE_GET)?) };
+        Ok(unsafe { f() })
+    }
+
+    /// The size of flash, in bytes, from `ROM_SysCtlFlashSizeGet`.
+    pub fn flash_size(&self) -> Result<u32, Error> {
+        let f: SizeGetFn = unsafe { mem::transmute(self.address(SYSCTL_FLASH_SIZE_GET)?) };
+        Ok(unsafe { f() })
+    }
+
+    /// The system clock rate, in Hz, as `ROM_SysCtlClockGet` works it out
+    /// from the RCC registers.
+    pub fn clock_get(&self) -> Result<u32, Error> {
+        let f: SizeGetFn = unsafe { mem::transmute(self.address(SYSCTL_CLOCK_GET)?) };
+        Ok(unsafe { f() })
+    }
+
+    /// Set up the system clock with `ROM_SysCtlClockSet`, using TivaWare's
+    /// `SYSCTL_*` configuration bits.
+    ///
+    /// This is unsafe because `board::clocks()` (and everything timed from
+    /// it) will be wrong afterwards, unless you set the same clock rate.
+    pub unsafe fn clock_set(&self, config: u32) -> Result<(), Error> {
+        let f: ClockSetFn = mem::transmute(self.address(SYSCTL_CLOCK_SET)?);
+        f(config);
+        Ok(())
+    }
+
+    /// Spin for `3 * loops` CPU cycles with `ROM_SysCtlDelay`. Being in ROM,
+    /// the timing isn't affected by flash wait states.
+    pub fn delay(&self, loops: u32) -> Result<(), Error> {
+        let f: DelayFn = unsafe { mem::transmute(self.address(SYSCTL_DELAY)?) };
+        unsafe { f(loops) };
+        Ok(())
+    }
+
+    /// Send a byte on a UART (given by its base address, e.g. `0x4000_C000`
+    /// for UART0) with `ROM_UARTCharPut`, waiting for space in the FIFO.
+    ///
+    /// This is unsafe because the UART may be owned by someone else.
+    pub unsafe fn uart_char_put(&self, uart_base: u32, byte: u8) -> Result<(), Error> {
+        let f: UartCharPutFn = mem::transmute(self.address(UART_CHAR_PUT)?);
+        f(uart_base, byte);
+        Ok(())
+    }
+
+    /// Reset the chip with `ROM_SysCtlReset`.
+    pub fn reset(&self) -> Result<Infallible, Error> {
+        let f: NoArgsFn = unsafe { mem::transmute(self.address(SYSCTL_RESET)?) };
+        unsafe { f() };
+        loop {}
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Check an address from the table is a Thumb function in the ROM.
+fn check_function(address: usize) -> Result<usize, Error> {
+    if address & 1 == 1 && (ROM_START..ROM_END).contains(&address) {
+        Ok(address)
+    } else {
+        Err(Error::NotInRom)
+    }
+}
+
+/// Work out the revision from DID0, if this is a chip we know.
+pub(crate) fn revision(did0: u32) -> Option<Revision> {
+    let class = (did0 >> 16) & 0xFF;
+    let major = (did0 >> 8) & 0xFF;
+    let minor = did0 & 0xFF;
+    if class != DID0_CLASS_TM4C123 {
+        return None;
+    }
+    match (major, minor) {
+        (0, 1) => Some(Revision::A1),
+        (0, 2) => Some(Revision::A2),
+        (0, 3) => Some(Revision::A3),
+        (1, 0) => Some(Revision::B0),
+        (1, 1) => Some(Revision::B1),
+        (1, 2) => Some(Revision::B2),
+        _ => None,
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
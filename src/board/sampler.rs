@@ -0,0 +1,285 @@
+//! A statistical sampling profiler, for finding hot spots without a debug
+//! probe.
+//!
+//! Wide Timer 5A interrupts the CPU at a fixed rate and the handler records
+//! the PC that was interrupted into a histogram in SRAM. Dump the histogram
+//! over the UART with `sampler::dump()` and feed the output to the
+//! `lp-symbolise` host tool (see `tools/`) along with the ELF file to get a
+//! ranked list of functions.
+//!
+//! Only available with the `sampling-profiler` feature, which also enables
+//! `ram-vectors` so the timer handler can be installed at run-time.
+//!
+//! Code which runs with interrupts masked (or at a priority more urgent than
+//! the sampler's) is never sampled, so it won't appear in the results.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::arch::global_asm;
+use core::{fmt, mem, ptr};
+
+use cortex_m;
+use cortex_m_rt::ExceptionFrame;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::WTIMER5;
+
+use super::groups::Timer;
+use super::interrupt::{self, Interrupt};
+use super::vectors::{self, Handler};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A running sampling profiler. Owns the timer it uses.
+pub struct Sampler {
+    timer: WTIMER5,
+    old_handler: Handler,
+}
+
+/// Why a sampler couldn't be started.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The rate was zero
+    ZeroRate,
+    /// The rate was faster than `MAX_RATE_HZ`
+    RateTooHigh,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The fastest `Sampler::start` will sample. Any faster and the CPU spends
+/// its time in the sampler's interrupt rather than the code being profiled.
+pub const MAX_RATE_HZ: u32 = 100_000;
+
+/// The number of distinct PC values we can record. Samples at any other PC
+/// are counted as dropped.
+pub const NUM_BUCKETS: usize = 256;
+
+/// Printed before the samples by `dump()`.
+pub const DUMP_START: &str = "--- samples ---";
+
+/// Printed after the samples by `dump()`.
+pub const DUMP_END: &str = "--- end ---";
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    pc: u32,
+    count: u32,
+}
+
+struct Histogram {
+    buckets: [Bucket; NUM_BUCKETS],
+    total: u32,
+    dropped: u32,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Used by the sampler interrupt, and by everything else with that interrupt
+/// disabled.
+static mut HISTOGRAM: Histogram = Histogram {
+    buckets: [Bucket { pc: 0, count: 0 }; NUM_BUCKETS],
+    total: 0,
+    dropped: 0,
+};
+
+/// Timer A time-out interrupt
+const TATOIM: u32 = 1 << 0;
+/// Timer A enable
+const TAEN: u32 = 1 << 0;
+/// 32-bit timer configuration, for a wide timer
+const CFG_32_BIT: u32 = 0x4;
+/// Timer A periodic mode
+const TAMR_PERIODIC: u32 = 0x2;
+
+// The timer handler. Works out which stack the exception frame was pushed to
+// and passes it to `__sampler_record`.
+global_asm!(
+    ".section .text.__sampler_trampoline,\"ax\",%progbits",
+    ".global __sampler_trampoline",
+    ".type __sampler_trampoline,%function",
+    ".thumb_func",
+    "__sampler_trampoline:",
+    "    tst lr, #4",
+    "    ite eq",
+    "    mrseq r0, msp",
+    "    mrsne r0, psp",
+    "    b __sampler_record",
+);
+
+extern "C" {
+    fn __sampler_trampoline();
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Sampler {
+    /// Start sampling `rate_hz` times a second at the given interrupt
+    /// priority (0 is the most urgent). Clears any previous samples. The
+    /// rate can't be zero or faster than `MAX_RATE_HZ`; if it is, you get
+    /// the timer back.
+    pub fn start(
+        timer: Timer<WTIMER5>,
+        power_control: &PowerControl,
+        rate_hz: u32,
+        priority: u8,
+        nvic: &mut cortex_m::peripheral::NVIC,
+    ) -> Result<Sampler, (Timer<WTIMER5>, Error)> {
+        let sysclk = super::clocks().sysclk.0;
+        if rate_hz == 0 {
+            return Err((timer, Error::ZeroRate));
+        }
+        if rate_hz > MAX_RATE_HZ {
+            return Err((timer, Error::RateTooHigh));
+        }
+        let reload = sysclk / rate_hz;
+        let timer = timer.into_inner();
+
+        sysctl::control_power(
+            power_control,
+            Domain::WideTimer5,
+            RunMode::Run,
+            PowerState::On,
+        );
+        sysctl::reset(power_control, Domain::WideTimer5);
+
+        clear();
+
+        let trampoline: Handler = unsafe { mem::transmute(__sampler_trampoline as usize) };
+        let old_handler = vectors::register(Interrupt::WTIMER5A, trampoline);
+
+        unsafe {
+            timer.ctl.write(|w| w.bits(0));
+            timer.cfg.write(|w| w.bits(CFG_32_BIT));
+            timer.tamr.write(|w| w.bits(TAMR_PERIODIC));
+            timer.tailr.write(|w| w.bits(reload - 1));
+            timer.icr.write(|w| w.bits(TATOIM));
+            timer.imr.write(|w| w.bits(TATOIM));
+            interrupt::set_priority(nvic, Interrupt::WTIMER5A, priority);
+            interrupt::enable(Interrupt::WTIMER5A);
+            timer.ctl.write(|w| w.bits(TAEN));
+        }
+
+        Ok(Sampler { timer, old_handler })
+    }
+
+    /// Stop sampling (the samples are kept until the next `start`) and give
+    /// back the timer.
+    pub fn stop(self) -> Timer<WTIMER5> {
+        interrupt::disable(Interrupt::WTIMER5A);
+        unsafe {
+            self.timer.ctl.write(|w| w.bits(0));
+            self.timer.imr.write(|w| w.bits(0));
+        }
+        vectors::register(Interrupt::WTIMER5A, self.old_handler);
+        Timer::new(self.timer)
+    }
+}
+
+/// Print the samples as one `0x<pc> <count>` line per PC, between
+/// `DUMP_START` and `DUMP_END` lines. Sampling pauses while this runs.
+pub fn dump<W>(w: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let was_enabled = interrupt::is_enabled(Interrupt::WTIMER5A);
+    interrupt::disable(Interrupt::WTIMER5A);
+    let result = write_histogram(w, unsafe { &*ptr::addr_of!(HISTOGRAM) });
+    if was_enabled {
+        unsafe { interrupt::enable(Interrupt::WTIMER5A) };
+    }
+    result
+}
+
+/// Forget all the samples taken so far.
+pub fn clear() {
+    let was_enabled = interrupt::is_enabled(Interrupt::WTIMER5A);
+    interrupt::disable(Interrupt::WTIMER5A);
+    unsafe {
+        let histogram = &mut *ptr::addr_of_mut!(HISTOGRAM);
+        for bucket in histogram.buckets.iter_mut() {
+            *bucket = Bucket { pc: 0, count: 0 };
+        }
+        histogram.total = 0;
+        histogram.dropped = 0;
+    }
+    if was_enabled {
+        unsafe { interrupt::enable(Interrupt::WTIMER5A) };
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn write_histogram<W>(w: &mut W, histogram: &Histogram) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    writeln!(w, "{}", DUMP_START)?;
+    writeln!(
+        w,
+        "# total {} dropped {}",
+        histogram.total, histogram.dropped
+    )?;
+    for bucket in histogram.buckets.iter().filter(|b| b.count != 0) {
+        writeln!(w, "0x{:08x} {}", bucket.pc, bucket.count)?;
+    }
+    writeln!(w, "{}", DUMP_END)
+}
+
+/// Called from `__sampler_trampoline` with the interrupted exception frame.
+#[no_mangle]
+unsafe extern "C" fn __sampler_record(frame: &ExceptionFrame) {
+    (*WTIMER5::ptr()).icr.write(|w| w.bits(TATOIM));
+    let histogram = &mut *ptr::addr_of_mut!(HISTOGRAM);
+    histogram.total = histogram.total.wrapping_add(1);
+    // Open addressing, starting at a hash of the (halfword aligned) PC
+    let pc = frame.pc;
+    let start = ((pc >> 1).wrapping_mul(0x9E37_79B1) >> 24) as usize % NUM_BUCKETS;
+    for probe in 0..NUM_BUCKETS {
+        let bucket = &mut histogram.buckets[(start + probe) % NUM_BUCKETS];
+        if bucket.count == 0 {
+            bucket.pc = pc;
+            bucket.count = 1;
+            return;
+        } else if bucket.pc == pc {
+            bucket.count = bucket.count.saturating_add(1);
+            return;
+        }
+    }
+    histogram.dropped = histogram.dropped.wrapping_add(1);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,181 @@
+//! Sharing peripherals (or anything else) between interrupt handlers and the
+//! main loop.
+//!
+//! Rather than writing `static mut` plus `Mutex<RefCell<Option<T>>>` every
+//! time, put the value in an `IsrShared` once it has been moved out of
+//! `Board`, then lock it wherever you need it:
+//!
+//! ```rust,ignore
+//! static UART1: IsrShared<Uart1> = IsrShared::new();
+//!
+//! // In stellaris_main
+//! UART1.init(uart);
+//!
+//! // In the interrupt handler (or the main loop)
+//! UART1.lock(|uart| uart.write_all("Hello\n"));
+//! ```
+//!
+//! With the `critical-section-impl` feature this module also provides the
+//! `critical-section` crate implementation for this chip, so drivers which
+//! use `critical_section::with` work out of the box. It's off by default, as
+//! a program can only have one implementation, and another crate (an RTOS,
+//! say) may bring its own.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::RefCell;
+
+use cortex_m;
+#[cfg(feature = "critical-section-impl")]
+use cortex_m::register::primask;
+#[cfg(feature = "critical-section-impl")]
+use critical_section;
+
+use super::interrupt;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A value which is initialised once and then shared between interrupt
+/// handlers and the main loop. Every access happens inside a critical
+/// section.
+pub struct IsrShared<T> {
+    inner: RefCell<Option<T>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Critical sections on a single-core Cortex-M, using PRIMASK.
+#[cfg(feature = "critical-section-impl")]
+struct SingleCoreCriticalSection;
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+#[cfg(feature = "critical-section-impl")]
+critical_section::set_impl!(SingleCoreCriticalSection);
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+// The RefCell is only ever touched with interrupts masked (or, for
+// `lock_below`, with every other user masked), and we're single core.
+unsafe impl<T> Sync for IsrShared<T> where T: Send {}
+
+impl<T> IsrShared<T> {
+    /// Create an empty `IsrShared`. Suitable for a `static`.
+    pub const fn new() -> IsrShared<T> {
+        IsrShared {
+            inner: RefCell::new(None),
+        }
+    }
+
+    /// Store the value. Panics if it was already initialised.
+    pub fn init(&self, value: T) {
+        if self.try_init(value).is_err() {
+            panic!("IsrShared initialised twice");
+        }
+    }
+
+    /// Store the value, or give it back if we were already initialised.
+    pub fn try_init(&self, value: T) -> Result<(), T> {
+        cortex_m::interrupt::free(|_| {
+            let mut inner = self.inner.borrow_mut();
+            if inner.is_some() {
+                Err(value)
+            } else {
+                *inner = Some(value);
+                Ok(())
+            }
+        })
+    }
+
+    /// Has `init` been called?
+    pub fn is_initialised(&self) -> bool {
+        cortex_m::interrupt::free(|_| self.inner.borrow().is_some())
+    }
+
+    /// Take the value back out, leaving this empty.
+    pub fn take(&self) -> Option<T> {
+        cortex_m::interrupt::free(|_| self.inner.borrow_mut().take())
+    }
+
+    /// Run `f` on the value with all interrupts masked. Returns `None` if
+    /// the value hasn't been initialised yet. Panics if called from within
+    /// `f` (i.e. re-entrantly).
+    pub fn lock<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        cortex_m::interrupt::free(|_| self.inner.borrow_mut().as_mut().map(f))
+    }
+
+    /// Run `f` on the value with only the interrupts at `ceiling` priority or
+    /// less urgent masked (using BASEPRI), so more urgent interrupts still
+    /// fire. Returns `None` if the value hasn't been initialised yet.
+    ///
+    /// This is `unsafe` because it is only sound if every interrupt handler
+    /// which accesses this value runs at `ceiling` priority or less urgent.
+    pub unsafe fn lock_below<F, R>(&self, ceiling: u8, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        interrupt::with_interrupts_masked_below(ceiling, || self.inner.borrow_mut().as_mut().map(f))
+    }
+}
+
+#[cfg(feature = "critical-section-impl")]
+unsafe impl critical_section::Impl for SingleCoreCriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+        let was_active = primask::read().is_active();
+        cortex_m::interrupt::disable();
+        was_active
+    }
+
+    unsafe fn release(was_active: critical_section::RawRestoreState) {
+        // Only re-enable interrupts if they were enabled before the critical
+        // section started.
+        if was_active {
+            cortex_m::interrupt::enable();
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,110 @@
+//! Launchpad glue for `common::update`: starting an image in a slot, and
+//! confirming that a newly installed image works.
+//!
+//! An application built with the `slot-a` or `slot-b` feature is started by
+//! the boot loader (see `bootloader/`). After an update it's on trial, and
+//! should call `confirm` once it's sure it works - or the boot loader goes
+//! back to the previous image after a few resets.
+//!
+//! ```rust,ignore
+//! let mut flash = board.system.flash;
+//! // ...check everything is working...
+//! board::update::confirm(&mut flash)?;
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use cortex_m;
+use cortex_m::peripheral::SCB;
+
+use board::flash::{self, Flash};
+use common::update::{self, Slot, Updater, LAUNCHPAD};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+extern "C" {
+    /// Where this image starts in flash (from `memory.x`)
+    static _flash_image_start: u32;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// The slot this program is running from, if it was built for one.
+pub fn running_slot() -> Option<Slot> {
+    let start = unsafe { &_flash_image_start as *const u32 as u32 };
+    LAUNCHPAD.slot_containing(start)
+}
+
+/// Tell the boot loader this image works, so it's kept. Does nothing if the
+/// image is already confirmed, or wasn't started by the boot loader.
+pub fn confirm(flash: &mut Flash) -> Result<(), update::Error<flash::Error>> {
+    match running_slot() {
+        Some(slot) => Updater::open(flash, LAUNCHPAD)?.confirm(slot),
+        None => Ok(()),
+    }
+}
+
+/// Start the image in a slot: point VTOR at its vector table, load its stack
+/// pointer and jump to its reset handler.
+///
+/// This is unsafe because the image must be valid (see
+/// `Updater::select`), and because nothing set up so far is undone - the
+/// boot loader should do this before touching anything but the flash and
+/// the GPIO.
+pub unsafe fn start(slot: Slot) -> ! {
+    let vector_table = LAUNCHPAD.slot(slot).start;
+    (*SCB::ptr()).vtor.write(vector_table);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+    cortex_m::asm::bootload(vector_table as *const u32)
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,145 @@
+//! A copy of the vector table in SRAM, so interrupt handlers can be
+//! registered and replaced at run-time instead of only at link time.
+//!
+//! Only available with the `ram-vectors` feature. At start-up, before
+//! `Board::new()`, the vector table currently pointed to by VTOR is copied
+//! into the first 1 KiB of SRAM (reserved by `memory.x`) and VTOR is pointed
+//! at the copy. Because the copy is taken from wherever VTOR points, an
+//! application started by a bootloader at some other flash offset works as
+//! long as the bootloader set VTOR before jumping.
+//!
+//! ```rust,ignore
+//! extern "C" fn uart1_handler() {
+//!     // ...
+//! }
+//!
+//! board::vectors::register(Interrupt::UART1, uart1_handler);
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::{mem, ptr};
+
+use cortex_m;
+use cortex_m::interrupt::Nr;
+use cortex_m::peripheral::SCB;
+
+use super::interrupt::{Interrupt, NUM_INTERRUPTS};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// An interrupt handler which can be placed in the vector table.
+pub type Handler = extern "C" fn();
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The number of entries in the vector table - the initial stack pointer,
+/// the 15 system exceptions and the interrupts.
+pub const NUM_VECTORS: usize = 16 + NUM_INTERRUPTS;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+extern "C" {
+    /// Defined in memory.x - the SRAM reserved for the vector table.
+    static mut _ram_vectors: [u32; NUM_VECTORS];
+}
+
+/// Where the vector table was before we relocated it.
+static mut ORIGINAL_VECTORS: *const u32 = ptr::null();
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Point `interrupt` at `handler`, returning the handler it had before.
+///
+/// The interrupt is not enabled - see `board::interrupt::enable`.
+pub fn register(interrupt: Interrupt, handler: Handler) -> Handler {
+    let slot = slot(interrupt);
+    cortex_m::interrupt::free(|_| unsafe {
+        let old = ptr::read_volatile(slot);
+        ptr::write_volatile(slot, handler as usize as u32);
+        cortex_m::asm::dsb();
+        to_handler(old)
+    })
+}
+
+/// Put back the handler `interrupt` had in the original (flash) vector table,
+/// returning the handler it had before.
+pub fn unregister(interrupt: Interrupt) -> Handler {
+    let index = 16 + interrupt.nr() as usize;
+    let original = unsafe { ptr::read_volatile(ORIGINAL_VECTORS.add(index)) };
+    register(interrupt, unsafe { to_handler(original) })
+}
+
+/// Get the handler currently registered for `interrupt`.
+pub fn handler(interrupt: Interrupt) -> Handler {
+    unsafe { to_handler(ptr::read_volatile(slot(interrupt))) }
+}
+
+/// Copy the current vector table into SRAM and point VTOR at it.
+///
+/// Called at start-up, before `Board::new()`.
+pub(crate) unsafe fn relocate() {
+    let scb = &*SCB::ptr();
+    let original = scb.vtor.read() as *const u32;
+    let table = ptr::addr_of_mut!(_ram_vectors) as *mut u32;
+    for index in 0..NUM_VECTORS {
+        ptr::write_volatile(table.add(index), ptr::read_volatile(original.add(index)));
+    }
+    ORIGINAL_VECTORS = original;
+    cortex_m::asm::dsb();
+    scb.vtor.write(table as u32);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Get a pointer to the entry in the SRAM table for `interrupt`.
+fn slot(interrupt: Interrupt) -> *mut u32 {
+    let index = 16 + interrupt.nr() as usize;
+    unsafe { (ptr::addr_of_mut!(_ram_vectors) as *mut u32).add(index) }
+}
+
+/// Convert a vector table entry back into a function pointer. Every entry for
+/// an implemented interrupt holds a valid (Thumb) function address.
+unsafe fn to_handler(entry: u32) -> Handler {
+    mem::transmute::<usize, Handler>(entry as usize)
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
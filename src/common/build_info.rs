@@ -0,0 +1,162 @@
+//! A record of how the firmware was built, so a board can say what it's
+//! running.
+//!
+//! `build.rs` fills in the record, and it's kept in flash (in
+//! `.rodata.build_info`, symbol `__build_info`) whether or not anything
+//! reads it. The layout is fixed, so `lp-buildinfo` (see `tools/`) can find
+//! it in an ELF file, or in a `.bin` file read back from a board:
+//!
+//! | Offset | Size | Field                                              |
+//! |--------|------|----------------------------------------------------|
+//! | 0      | 4    | Magic number, `LPBI`                               |
+//! | 4      | 4    | Layout of the rest of the record (2)               |
+//! | 8      | 16   | Crate version                                      |
+//! | 24     | 48   | Git commit (with `-dirty` for uncommitted changes) |
+//! | 72     | 8    | Build time, in seconds since 1970                  |
+//! | 80     | 16   | Cargo profile                                      |
+//! | 96     | 40   | Target triple                                      |
+//! | 136    | 256  | Enabled cargo features, separated by commas        |
+//! | 392    | 256  | Target features (e.g. `vfp4d16sp`), likewise       |
+//! | 648    | 20   | Board                                              |
+//! | 668    | 4    | CRC-32 of the above                                |
+//!
+//! Numbers are little-endian, and strings are padded with NULs (`build.rs`
+//! stops the build if one doesn't fit). The version
+//! and git commit are those of this crate, unless `LP_BUILD_VERSION` or
+//! `LP_BUILD_GIT_HASH` are set when building, and the build time is
+//! `SOURCE_DATE_EPOCH` if that's set.
+//!
+//! ```rust,ignore
+//! let info = board::build_info();
+//! writeln!(uart, "{} ({}) built {}", info.version(), info.git_hash(), info.timestamp());
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::str;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The build-info record.
+#[repr(C)]
+pub struct BuildInfo {
+    header: [u8; HEADER_LEN],
+    crc: u32,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The record for this image (see `board::build_info`). `memory.x` makes
+/// sure the linker keeps it.
+#[export_name = "__build_info"]
+#[link_section = ".rodata.build_info"]
+pub(crate) static BUILD_INFO: BuildInfo = include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Everything but the CRC
+const HEADER_LEN: usize = 668;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl BuildInfo {
+    /// The crate version.
+    pub fn version(&self) -> &str {
+        self.string(8, 16)
+    }
+
+    /// The git commit, `unknown` if it wasn't built from a git checkout.
+    pub fn git_hash(&self) -> &str {
+        self.string(24, 48)
+    }
+
+    /// When it was built, in seconds since 1970.
+    pub fn timestamp(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.header[72..80]);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// The cargo profile, e.g. `release`.
+    pub fn profile(&self) -> &str {
+        self.string(80, 16)
+    }
+
+    /// The target triple, e.g. `thumbv7em-none-eabihf`.
+    pub fn target(&self) -> &str {
+        self.string(96, 40)
+    }
+
+    /// The enabled cargo features, separated by commas.
+    pub fn features(&self) -> &str {
+        self.string(136, 256)
+    }
+
+    /// The target features the compiler was allowed to use, separated by
+    /// commas.
+    pub fn target_features(&self) -> &str {
+        self.string(392, 256)
+    }
+
+    /// The board, e.g. `ek-lm4f120xl`.
+    pub fn board(&self) -> &str {
+        self.string(648, 20)
+    }
+
+    /// The CRC `build.rs` calculated.
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    fn string(&self, offset: usize, len: usize) -> &str {
+        let field = &self.header[offset..offset + len];
+        let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+        match str::from_utf8(&field[..end]) {
+            Ok(string) => string,
+            // Cut short in the middle of a character
+            Err(e) => str::from_utf8(&field[..e.valid_up_to()]).unwrap_or(""),
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -7,6 +7,7 @@
 // ****************************************************************************
 
 use board;
+use core::fmt::Write;
 use core::panic::PanicInfo;
 
 // ****************************************************************************
@@ -66,8 +67,13 @@ pub extern "C" fn _Unwind_Resume() -> ! {
     board::panic();
 }
 
+/// Reports the panic on the UART (this includes heap allocation failures,
+/// with the `alloc` feature, after `common::heap`'s failure hook has run),
+/// then flashes the red LED.
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(info: &PanicInfo) -> ! {
+    let mut uart = unsafe { board::emergency_console() };
+    let _ = writeln!(uart, "{}", info);
     board::panic();
 }
 
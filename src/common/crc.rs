@@ -0,0 +1,132 @@
+//! CRC-32 (the IEEE 802.3 / zlib / PNG one), for checking data stored in
+//! flash or sent over the UART.
+//!
+//! ```rust
+//! use stellaris_launchpad::common::crc::{self, Crc32};
+//!
+//! assert_eq!(crc::crc32(b"123456789"), 0xCBF4_3926);
+//!
+//! // Or a piece at a time
+//! let mut crc = Crc32::new();
+//! crc.update(b"1234");
+//! crc.update(b"56789");
+//! assert_eq!(crc.finish(), 0xCBF4_3926);
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Calculates a CRC-32 a piece at a time.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// The reflected polynomial
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+static TABLE: [u32; 256] = make_table();
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Calculate the CRC-32 of some bytes.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+impl Crc32 {
+    /// Start a new calculation.
+    pub const fn new() -> Crc32 {
+        Crc32 { state: 0xFFFF_FFFF }
+    }
+
+    /// Add some more bytes.
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            let index = (self.state ^ u32::from(*byte)) & 0xFF;
+            self.state = TABLE[index as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Get the CRC of all the bytes so far.
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Crc32 {
+        Crc32::new()
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 != 0 {
+                (value >> 1) ^ POLYNOMIAL
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
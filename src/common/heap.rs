@@ -0,0 +1,221 @@
+//! A global heap allocator, so `alloc::vec::Vec`, `alloc::string::String`
+//! and friends can be used.
+//!
+//! Only available with the `alloc` feature. The heap is all the SRAM between
+//! the end of `.bss` and the stack region (see `memory.x`), and it's set up
+//! at start-up, before `Board::new()`. Allocation and de-allocation happen
+//! with interrupts masked, so the heap can be used from interrupt handlers.
+//!
+//! If an allocation fails, the failure hook is called first - by default
+//! `report_failure`, which prints what was asked for and the heap statistics
+//! on the UART. Then, unless it was a fallible allocation (like
+//! `Vec::try_reserve`), Rust calls the crate's panic handler, which reports
+//! the panic and flashes the red LED. Install your own hook with
+//! `set_failure_hook`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::ptr::{self, NonNull};
+
+use cortex_m;
+use cortex_m::interrupt::Mutex;
+use linked_list_allocator::Heap;
+
+use board;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Heap usage statistics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The total size of the heap, in bytes
+    pub size: usize,
+    /// Bytes currently allocated
+    pub used: usize,
+    /// The most bytes that have ever been allocated at once
+    pub peak: usize,
+    /// Number of successful allocations (it stops at `u32::max_value()`)
+    pub allocations: u32,
+    /// Number of de-allocations (likewise)
+    pub deallocations: u32,
+    /// Number of allocations which failed (likewise)
+    pub failures: u32,
+}
+
+/// Called when an allocation fails, with what was asked for and the heap
+/// statistics (which count this failure). It runs inside the allocator, so
+/// it mustn't allocate.
+pub type FailureHook = fn(Layout, &Stats);
+
+/// The global allocator. Wraps a first-fit linked-list allocator, counting
+/// what it does.
+pub struct BoardAllocator {
+    inner: Mutex<RefCell<Inner>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+struct Inner {
+    heap: Heap,
+    stats: Stats,
+    failure_hook: FailureHook,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+#[global_allocator]
+static ALLOCATOR: BoardAllocator = BoardAllocator {
+    inner: Mutex::new(RefCell::new(Inner {
+        heap: Heap::empty(),
+        stats: Stats {
+            size: 0,
+            used: 0,
+            peak: 0,
+            allocations: 0,
+            deallocations: 0,
+            failures: 0,
+        },
+        failure_hook: report_failure,
+    })),
+};
+
+extern "C" {
+    /// End of `.bss` - from cortex-m-rt's link.x
+    static __ebss: u32;
+    /// The end of the SRAM not used by statics or the stack - from memory.x
+    static _heap_end: u32;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Get the current heap statistics.
+pub fn stats() -> Stats {
+    cortex_m::interrupt::free(|cs| ALLOCATOR.inner.borrow(cs).borrow().stats)
+}
+
+/// Call `hook` whenever an allocation fails, instead of the current hook,
+/// which is returned.
+pub fn set_failure_hook(hook: FailureHook) -> FailureHook {
+    cortex_m::interrupt::free(|cs| {
+        let mut inner = ALLOCATOR.inner.borrow(cs).borrow_mut();
+        core::mem::replace(&mut inner.failure_hook, hook)
+    })
+}
+
+/// The default failure hook: print the failed request and the heap
+/// statistics on the UART (taking it over, as a fault handler does).
+pub fn report_failure(layout: Layout, stats: &Stats) {
+    let mut uart = unsafe { board::emergency_console() };
+    let _ = writeln!(
+        uart,
+        "Allocation of {} bytes (aligned to {}) failed",
+        layout.size(),
+        layout.align()
+    );
+    let _ = writeln!(uart, "{}", stats);
+}
+
+unsafe impl GlobalAlloc for BoardAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let result = cortex_m::interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            match inner.heap.allocate_first_fit(layout) {
+                Ok(p) => {
+                    inner.stats.allocations = inner.stats.allocations.saturating_add(1);
+                    inner.stats.used = inner.heap.used();
+                    inner.stats.peak = inner.stats.peak.max(inner.stats.used);
+                    Ok(p.as_ptr())
+                }
+                Err(()) => {
+                    inner.stats.failures = inner.stats.failures.saturating_add(1);
+                    Err((inner.failure_hook, inner.stats))
+                }
+            }
+        });
+        match result {
+            Ok(p) => p,
+            Err((hook, stats)) => {
+                // Outside the critical section, as printing takes a while
+                hook(layout, &stats);
+                ptr::null_mut()
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, p: *mut u8, layout: Layout) {
+        cortex_m::interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.heap.deallocate(NonNull::new_unchecked(p), layout);
+            inner.stats.deallocations = inner.stats.deallocations.saturating_add(1);
+            inner.stats.used = inner.heap.used();
+        })
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Heap: {} of {} bytes used ({} at peak), {} allocations, {} de-allocations, {} failures",
+            self.used, self.size, self.peak, self.allocations, self.deallocations, self.failures
+        )
+    }
+}
+
+/// Give the allocator all the free SRAM.
+///
+/// Called at start-up, before `Board::new()`.
+pub(crate) unsafe fn init() {
+    let start = &__ebss as *const u32 as usize;
+    let end = &_heap_end as *const u32 as usize;
+    cortex_m::interrupt::free(|cs| {
+        let mut inner = ALLOCATOR.inner.borrow(cs).borrow_mut();
+        inner.heap.init(start as *mut u8, end - start);
+        inner.stats.size = inner.heap.size();
+    })
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,178 @@
+//! Checks the flash image against a CRC-32 stamped into it after linking,
+//! for the `image-checksum` feature.
+//!
+//! The image reserves a 12-byte record in a section of its own
+//! (`.rodata.image_checksum`, symbol `__image_checksum`). After building,
+//! `lp-checksum` (see `tools/`) writes into it the length of the image and
+//! the CRC-32 of every byte of the image except the record itself. The
+//! start-up code checks this before calling `stellaris_main`, and
+//! `Board::image_integrity()` says what it found. With the
+//! `image-checksum-halt` feature, a damaged image doesn't run at all - the
+//! red LED blinks three times, over and over.
+//!
+//! Sign an image (with `lp-sign`) after stamping it, not before.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[cfg(feature = "image-checksum")]
+use core::{mem, ptr};
+
+#[cfg(feature = "image-checksum")]
+use common::crc::Crc32;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// What the start-up check found.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Integrity {
+    /// Built without the `image-checksum` feature
+    Unchecked,
+    /// `lp-checksum` hasn't been run on this image
+    NotStamped,
+    /// The image matches its checksum
+    Good,
+    /// The image has been damaged
+    Corrupt {
+        /// The CRC `lp-checksum` stamped in
+        expected: u32,
+        /// The CRC of the image in flash
+        actual: u32,
+    },
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How many times the red LED blinks when `image-checksum-halt` stops a
+/// damaged image.
+pub const BLINK_CODE: u32 = 3;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The record `lp-checksum` fills in.
+#[cfg(feature = "image-checksum")]
+#[repr(C)]
+struct Record {
+    magic: u32,
+    length: u32,
+    crc: u32,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// "CRC1" - `lp-checksum` has been here
+#[cfg(feature = "image-checksum")]
+const MAGIC: u32 = 0x3143_5243;
+
+/// Erased until `lp-checksum` stamps it
+#[cfg(feature = "image-checksum")]
+#[export_name = "__image_checksum"]
+#[link_section = ".rodata.image_checksum"]
+static IMAGE_CHECKSUM: Record = Record {
+    magic: 0xFFFF_FFFF,
+    length: 0xFFFF_FFFF,
+    crc: 0xFFFF_FFFF,
+};
+
+static mut INTEGRITY: Integrity = Integrity::Unchecked;
+
+#[cfg(feature = "image-checksum")]
+extern "C" {
+    /// Where this image starts in flash (from `memory.x`)
+    static _flash_image_start: u32;
+}
+
+/// The end of flash
+#[cfg(feature = "image-checksum")]
+const FLASH_END: usize = 0x0004_0000;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// What the start-up check found.
+pub fn integrity() -> Integrity {
+    unsafe { INTEGRITY }
+}
+
+/// Check the image, and remember what we found. Called once, by the start-up
+/// code, before `stellaris_main`.
+#[cfg(feature = "image-checksum")]
+pub(crate) unsafe fn check() -> Integrity {
+    INTEGRITY = calculate();
+    INTEGRITY
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+#[cfg(feature = "image-checksum")]
+unsafe fn calculate() -> Integrity {
+    // Read the record through a volatile pointer, or the compiler will use
+    // the values it was initialised with
+    let record = &IMAGE_CHECKSUM as *const Record;
+    if ptr::read_volatile(&(*record).magic) != MAGIC {
+        return Integrity::NotStamped;
+    }
+    let expected = ptr::read_volatile(&(*record).crc);
+    let length = ptr::read_volatile(&(*record).length) as usize;
+
+    let start = &_flash_image_start as *const u32 as usize;
+    let record_start = record as usize;
+    let record_end = record_start + mem::size_of::<Record>();
+    if length > FLASH_END - start || record_end > start + length {
+        return Integrity::Corrupt {
+            expected,
+            actual: 0,
+        };
+    }
+    let mut crc = Crc32::new();
+    update_from_flash(&mut crc, start, record_start);
+    update_from_flash(&mut crc, record_end, start + length);
+    let actual = crc.finish();
+    if actual == expected {
+        Integrity::Good
+    } else {
+        Integrity::Corrupt { expected, actual }
+    }
+}
+
+/// Add the bytes of flash from `start` up to `end` to the CRC. The image can
+/// start at address 0, so we can't make a slice of it - read it a byte at a
+/// time instead.
+#[cfg(feature = "image-checksum")]
+unsafe fn update_from_flash(crc: &mut Crc32, start: usize, end: usize) {
+    for address in start..end {
+        crc.update(&[ptr::read_volatile(address as *const u8)]);
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
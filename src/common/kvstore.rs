@@ -0,0 +1,577 @@
+//! A small persistent key/value store, kept in a range of NOR flash pages.
+//!
+//! Works with anything which implements the `embedded-storage` `NorFlash`
+//! traits - `board::flash::Flash` on the Launchpad, or
+//! `common::ram_flash::RamFlash` for testing (including on a PC, where
+//! `host/tests/kvstore.rs` cuts the power after every possible write).
+//!
+//! ```rust,ignore
+//! let flash = board.system.flash;
+//! // The last four pages of flash
+//! let mut store = KvStore::open(flash, 0x3_F000, 4)?;
+//! store.set(1, b"hello")?;
+//! let mut buffer = [0u8; 16];
+//! assert_eq!(store.get(1, &mut buffer)?, Some(5));
+//! ```
+//!
+//! ## How it works
+//!
+//! Each page in use starts with a header holding a magic number and a
+//! sequence number. Records are appended to the page with the highest
+//! sequence number, and the latest valid record for a key wins. Removing a
+//! key appends a 'tombstone' record. Each record is:
+//!
+//! * a word holding the key (low half) and value length (high half),
+//! * a CRC-32 of that word and the value, and
+//! * the value, padded to a whole number of words.
+//!
+//! The CRC is written last, so a record which was only partly written when
+//! the power failed is ignored and the previous value is used.
+//!
+//! When a page fills up, the next free page is erased and used. One page is
+//! always kept free. When that's the only free page left, the still-current
+//! records in the oldest page are copied into it, its header is written
+//! (which commits the copy), and then the oldest page is erased and becomes
+//! the free page. If the power fails before the header is written the copy
+//! is ignored; if it fails after, `open` spots that every page is in use and
+//! erases the oldest. The free page moves around, which spreads out the
+//! wear.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_storage::nor_flash::NorFlash;
+
+use common::crc::Crc32;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A key/value store over a range of flash pages.
+pub struct KvStore<F> {
+    flash: F,
+    start: u32,
+    num_pages: u32,
+    page_size: u32,
+    /// The pages in use, oldest first
+    pages: [u8; MAX_PAGES],
+    pages_in_use: usize,
+    next_sequence: u32,
+    /// Where the next record goes, in the newest page
+    write_offset: u32,
+}
+
+/// Things that can go wrong with the store.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<E> {
+    /// The underlying flash failed
+    Flash(E),
+    /// The page range isn't usable (too few or too many pages, not page
+    /// aligned, or outside the flash)
+    BadConfig,
+    /// `0xFFFF` can't be used as a key
+    BadKey,
+    /// The value won't fit in a page
+    TooBig,
+    /// The buffer passed to `get` is too small for the value
+    BufferTooSmall,
+    /// There's no room left, even after garbage collection
+    Full,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The most pages a store can use.
+pub const MAX_PAGES: usize = 32;
+
+/// The highest key that can be used.
+pub const MAX_KEY: u16 = 0xFFFE;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    /// Where the record starts in flash
+    address: u32,
+    header: u32,
+    crc: u32,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// "KVS1"
+const MAGIC: u32 = 0x3153_564B;
+const ERASED: u32 = 0xFFFF_FFFF;
+const PAGE_HEADER_SIZE: u32 = 8;
+const RECORD_HEADER_SIZE: u32 = 8;
+/// Set in the length of a record which removes a key
+const TOMBSTONE: u16 = 0x8000;
+/// How many bytes to read or write at a time
+const CHUNK_SIZE: usize = 32;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<F> KvStore<F>
+where
+    F: NorFlash,
+{
+    /// Open the store in `num_pages` flash pages starting at `start` (an
+    /// offset into `flash`). If none of the pages hold a store, a new empty
+    /// one is made.
+    pub fn open(flash: F, start: u32, num_pages: u32) -> Result<KvStore<F>, Error<F::Error>> {
+        let page_size = F::ERASE_SIZE as u32;
+        let fits = num_pages
+            .checked_mul(page_size)
+            .and_then(|size| size.checked_add(start))
+            .is_some_and(|end| end as usize <= flash.capacity());
+        if num_pages < 2
+            || num_pages as usize > MAX_PAGES
+            || start % page_size != 0
+            || !fits
+            || 4 % F::WRITE_SIZE != 0
+            || 4 % F::READ_SIZE != 0
+        {
+            return Err(Error::BadConfig);
+        }
+
+        let mut store = KvStore {
+            flash,
+            start,
+            num_pages,
+            page_size,
+            pages: [0; MAX_PAGES],
+            pages_in_use: 0,
+            next_sequence: 0,
+            write_offset: PAGE_HEADER_SIZE,
+        };
+
+        // Find the pages in use, and sort them oldest first
+        let mut sequences = [0u32; MAX_PAGES];
+        for page in 0..num_pages {
+            let base = store.page_base(page as u8);
+            if store.read_word(base)? != MAGIC {
+                continue;
+            }
+            let sequence = store.read_word(base + 4)?;
+            let mut i = store.pages_in_use;
+            while i > 0 && sequences[i - 1] > sequence {
+                sequences[i] = sequences[i - 1];
+                store.pages[i] = store.pages[i - 1];
+                i -= 1;
+            }
+            sequences[i] = sequence;
+            store.pages[i] = page as u8;
+            store.pages_in_use += 1;
+        }
+
+        if store.pages_in_use == 0 {
+            store.start_page()?;
+            return Ok(store);
+        }
+
+        store.next_sequence = sequences[store.pages_in_use - 1].wrapping_add(1);
+        if store.pages_in_use == num_pages as usize {
+            // We lost power after garbage collection copied the oldest page,
+            // but before it was erased.
+            let oldest = store.pages[0];
+            store.erase_page(oldest)?;
+            store.remove_oldest();
+        }
+        let newest = store.pages[store.pages_in_use - 1];
+        store.write_offset = store.log_end(newest)?;
+        Ok(store)
+    }
+
+    /// Copy the value for `key` into `buffer`. Returns the length of the
+    /// value, or `None` if the key isn't in the store.
+    pub fn get(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Error<F::Error>> {
+        let record = match self.find(key)? {
+            Some(record) if !record.is_tombstone() => record,
+            _ => return Ok(None),
+        };
+        let len = record.len();
+        if buffer.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        let mut address = record.value_address();
+        for piece in buffer[..len].chunks_mut(CHUNK_SIZE) {
+            let mut chunk = [0u8; CHUNK_SIZE];
+            let padded = round_up(piece.len());
+            self.flash
+                .read(address, &mut chunk[..padded])
+                .map_err(Error::Flash)?;
+            piece.copy_from_slice(&chunk[..piece.len()]);
+            address += padded as u32;
+        }
+        Ok(Some(len))
+    }
+
+    /// Is `key` in the store?
+    pub fn contains(&mut self, key: u16) -> Result<bool, Error<F::Error>> {
+        Ok(self.find(key)?.is_some_and(|r| !r.is_tombstone()))
+    }
+
+    /// Store `value` under `key`, replacing any previous value. Does nothing
+    /// if the store already holds exactly this value.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error<F::Error>> {
+        if key > MAX_KEY {
+            return Err(Error::BadKey);
+        }
+        if value.len() >= usize::from(TOMBSTONE) || value.len() > self.max_value_len() {
+            return Err(Error::TooBig);
+        }
+        if let Some(record) = self.find(key)? {
+            if !record.is_tombstone() && self.value_equals(&record, value)? {
+                return Ok(());
+            }
+        }
+        let header = u32::from(key) | ((value.len() as u32) << 16);
+        self.append(header, value)
+    }
+
+    /// Remove `key` from the store. Does nothing if it isn't there.
+    pub fn remove(&mut self, key: u16) -> Result<(), Error<F::Error>> {
+        if key > MAX_KEY {
+            return Err(Error::BadKey);
+        }
+        match self.find(key)? {
+            Some(record) if !record.is_tombstone() => {
+                let header = u32::from(key) | (u32::from(TOMBSTONE) << 16);
+                self.append(header, &[])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Erase every page, leaving an empty store.
+    pub fn format(&mut self) -> Result<(), Error<F::Error>> {
+        for page in 0..self.num_pages {
+            self.erase_page(page as u8)?;
+        }
+        self.pages_in_use = 0;
+        self.start_page()
+    }
+
+    /// The largest value which can be stored.
+    pub fn max_value_len(&self) -> usize {
+        (self.page_size - PAGE_HEADER_SIZE - RECORD_HEADER_SIZE) as usize
+    }
+
+    /// Give back the flash.
+    pub fn free(self) -> F {
+        self.flash
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Record {
+    fn key(&self) -> u16 {
+        self.header as u16
+    }
+
+    fn is_tombstone(&self) -> bool {
+        ((self.header >> 16) as u16 & TOMBSTONE) != 0
+    }
+
+    fn len(&self) -> usize {
+        usize::from((self.header >> 16) as u16 & !TOMBSTONE)
+    }
+
+    fn size(&self) -> u32 {
+        RECORD_HEADER_SIZE + round_up(self.len()) as u32
+    }
+
+    fn value_address(&self) -> u32 {
+        self.address + RECORD_HEADER_SIZE
+    }
+}
+
+impl<F> KvStore<F>
+where
+    F: NorFlash,
+{
+    /// Find the latest valid record for `key` (which might be a tombstone).
+    fn find(&mut self, key: u16) -> Result<Option<Record>, Error<F::Error>> {
+        let mut latest = None;
+        for i in 0..self.pages_in_use {
+            let page = self.pages[i];
+            let mut offset = PAGE_HEADER_SIZE;
+            while let Some(record) = self.record_at(page, offset)? {
+                if record.key() == key && self.is_valid(&record)? {
+                    latest = Some(record);
+                }
+                offset += record.size();
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Read the record at `offset` in `page`, if there is one.
+    fn record_at(&mut self, page: u8, offset: u32) -> Result<Option<Record>, Error<F::Error>> {
+        if offset + RECORD_HEADER_SIZE > self.page_size {
+            return Ok(None);
+        }
+        let address = self.page_base(page) + offset;
+        let header = self.read_word(address)?;
+        if header == ERASED {
+            return Ok(None);
+        }
+        let record = Record {
+            address,
+            header,
+            crc: self.read_word(address + 4)?,
+        };
+        if offset + record.size() > self.page_size {
+            return Ok(None);
+        }
+        Ok(Some(record))
+    }
+
+    /// Find where the next record in `page` should go.
+    fn log_end(&mut self, page: u8) -> Result<u32, Error<F::Error>> {
+        let mut offset = PAGE_HEADER_SIZE;
+        while let Some(record) = self.record_at(page, offset)? {
+            offset += record.size();
+        }
+        if offset + RECORD_HEADER_SIZE <= self.page_size
+            && self.read_word(self.page_base(page) + offset)? != ERASED
+        {
+            // Something's there which isn't a record - don't write over it
+            offset = self.page_size;
+        }
+        Ok(offset)
+    }
+
+    /// Check a record's CRC.
+    fn is_valid(&mut self, record: &Record) -> Result<bool, Error<F::Error>> {
+        let mut crc = Crc32::new();
+        crc.update(&record.header.to_le_bytes());
+        let mut address = record.value_address();
+        let mut remaining = record.len();
+        while remaining > 0 {
+            let mut chunk = [0u8; CHUNK_SIZE];
+            let len = remaining.min(CHUNK_SIZE);
+            let padded = round_up(len);
+            self.flash
+                .read(address, &mut chunk[..padded])
+                .map_err(Error::Flash)?;
+            crc.update(&chunk[..len]);
+            address += padded as u32;
+            remaining -= len;
+        }
+        Ok(crc.finish() == record.crc)
+    }
+
+    fn value_equals(&mut self, record: &Record, value: &[u8]) -> Result<bool, Error<F::Error>> {
+        if record.len() != value.len() {
+            return Ok(false);
+        }
+        let mut address = record.value_address();
+        for piece in value.chunks(CHUNK_SIZE) {
+            let mut chunk = [0u8; CHUNK_SIZE];
+            let padded = round_up(piece.len());
+            self.flash
+                .read(address, &mut chunk[..padded])
+                .map_err(Error::Flash)?;
+            if &chunk[..piece.len()] != piece {
+                return Ok(false);
+            }
+            address += padded as u32;
+        }
+        Ok(true)
+    }
+
+    /// Add a record to the newest page, making room if required.
+    fn append(&mut self, header: u32, value: &[u8]) -> Result<(), Error<F::Error>> {
+        let size = RECORD_HEADER_SIZE + round_up(value.len()) as u32;
+        let mut collections = 0;
+        while self.write_offset + size > self.page_size {
+            if self.pages_in_use < self.num_pages as usize - 1 {
+                self.start_page()?;
+            } else if collections < self.num_pages {
+                self.collect_garbage()?;
+                collections += 1;
+            } else {
+                return Err(Error::Full);
+            }
+        }
+
+        let newest = self.pages[self.pages_in_use - 1];
+        let address = self.page_base(newest) + self.write_offset;
+        // Whatever happens, don't try to write here again
+        self.write_offset += size;
+
+        let mut crc = Crc32::new();
+        crc.update(&header.to_le_bytes());
+        crc.update(value);
+        self.write_word(address, header)?;
+        let mut value_address = address + RECORD_HEADER_SIZE;
+        for piece in value.chunks(CHUNK_SIZE) {
+            let mut chunk = [0xFFu8; CHUNK_SIZE];
+            chunk[..piece.len()].copy_from_slice(piece);
+            let padded = round_up(piece.len());
+            self.flash
+                .write(value_address, &chunk[..padded])
+                .map_err(Error::Flash)?;
+            value_address += padded as u32;
+        }
+        // The CRC goes last - this commits the record
+        self.write_word(address + 4, crc.finish())
+    }
+
+    /// Erase a free page and make it the newest page.
+    fn start_page(&mut self) -> Result<(), Error<F::Error>> {
+        let page = self.free_page();
+        self.erase_page(page)?;
+        self.commit_page(page)?;
+        self.write_offset = PAGE_HEADER_SIZE;
+        Ok(())
+    }
+
+    /// Copy the current records out of the oldest page into the free page,
+    /// then erase the oldest page.
+    fn collect_garbage(&mut self) -> Result<(), Error<F::Error>> {
+        let oldest = self.pages[0];
+        let target = self.free_page();
+        self.erase_page(target)?;
+
+        let mut target_offset = PAGE_HEADER_SIZE;
+        let mut offset = PAGE_HEADER_SIZE;
+        while let Some(record) = self.record_at(oldest, offset)? {
+            offset += record.size();
+            if record.is_tombstone() {
+                // Nothing older left for it to hide
+                continue;
+            }
+            let current = match self.find(record.key())? {
+                Some(current) => current.address == record.address,
+                None => false,
+            };
+            if current {
+                self.copy_record(&record, self.page_base(target) + target_offset)?;
+                target_offset += record.size();
+            }
+        }
+
+        self.commit_page(target)?;
+        self.write_offset = target_offset;
+        self.erase_page(oldest)?;
+        self.remove_oldest();
+        Ok(())
+    }
+
+    fn copy_record(&mut self, record: &Record, to: u32) -> Result<(), Error<F::Error>> {
+        self.write_word(to, record.header)?;
+        let mut remaining = round_up(record.len());
+        let mut from = record.value_address();
+        let mut dest = to + RECORD_HEADER_SIZE;
+        while remaining > 0 {
+            let mut chunk = [0u8; CHUNK_SIZE];
+            let len = remaining.min(CHUNK_SIZE);
+            self.flash
+                .read(from, &mut chunk[..len])
+                .map_err(Error::Flash)?;
+            self.flash
+                .write(dest, &chunk[..len])
+                .map_err(Error::Flash)?;
+            from += len as u32;
+            dest += len as u32;
+            remaining -= len;
+        }
+        self.write_word(to + 4, record.crc)
+    }
+
+    /// Write a page header (sequence number, then magic number) and make the
+    /// page the newest one.
+    fn commit_page(&mut self, page: u8) -> Result<(), Error<F::Error>> {
+        let base = self.page_base(page);
+        let sequence = self.next_sequence;
+        self.write_word(base + 4, sequence)?;
+        self.write_word(base, MAGIC)?;
+        self.next_sequence = sequence.wrapping_add(1);
+        self.pages[self.pages_in_use] = page;
+        self.pages_in_use += 1;
+        Ok(())
+    }
+
+    /// Pick the first page not in use, after the newest page.
+    fn free_page(&self) -> u8 {
+        let after = if self.pages_in_use == 0 {
+            0
+        } else {
+            u32::from(self.pages[self.pages_in_use - 1]) + 1
+        };
+        (0..self.num_pages)
+            .map(|i| ((after + i) % self.num_pages) as u8)
+            .find(|page| !self.pages[..self.pages_in_use].contains(page))
+            .unwrap()
+    }
+
+    fn remove_oldest(&mut self) {
+        for i in 1..self.pages_in_use {
+            self.pages[i - 1] = self.pages[i];
+        }
+        self.pages_in_use -= 1;
+    }
+
+    fn erase_page(&mut self, page: u8) -> Result<(), Error<F::Error>> {
+        let base = self.page_base(page);
+        self.flash
+            .erase(base, base + self.page_size)
+            .map_err(Error::Flash)
+    }
+
+    fn page_base(&self, page: u8) -> u32 {
+        self.start + u32::from(page) * self.page_size
+    }
+
+    fn read_word(&mut self, address: u32) -> Result<u32, Error<F::Error>> {
+        let mut bytes = [0u8; 4];
+        self.flash.read(address, &mut bytes).map_err(Error::Flash)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn write_word(&mut self, address: u32, word: u32) -> Result<(), Error<F::Error>> {
+        self.flash
+            .write(address, &word.to_le_bytes())
+            .map_err(Error::Flash)
+    }
+}
+
+/// Round a length up to a whole number of words.
+fn round_up(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,247 @@
+//! What application code needs from a LaunchPad, whichever one it is.
+//!
+//! `LaunchpadBoard` covers the user LEDs and buttons, a console UART, the
+//! BoosterPack headers and the clocks. `board::Launchpad` implements it for
+//! the real hardware, and `common::sim_board::SimBoard` for tests on a PC, so
+//! code written against the trait runs on either.
+//!
+//! ```rust,ignore
+//! fn flash_while_pressed<B: LaunchpadBoard>(board: &mut B) {
+//!     let pressed = board.is_pressed(Button::One);
+//!     board.set_led(Led::Green, pressed);
+//!     writeln!(board.console(), "SW1 is {}", if pressed { "down" } else { "up" }).unwrap();
+//!     board.delay_ms(100);
+//! }
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+#[derive(PartialEq, Clone, Copy)]
+/// The Launchpad has a tri-colour LED, which we consider
+/// to be three separate LEDs.
+pub enum Led {
+    /// The Red LED
+    Red,
+    /// The Blue LED
+    Blue,
+    /// The Green LED
+    Green,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+/// The Launchpad has two buttons
+pub enum Button {
+    /// SW1
+    One,
+    /// SW2
+    Two,
+}
+
+/// A LaunchPad.
+pub trait LaunchpadBoard {
+    /// Which board this is, e.g. `ek-lm4f120xl`.
+    fn name(&self) -> &'static str;
+
+    /// Turn a user LED on or off.
+    fn set_led(&mut self, led: Led, on: bool);
+
+    /// Is a user button held down?
+    fn is_pressed(&self, button: Button) -> bool;
+
+    /// Send bytes to the console UART, waiting for room if need be.
+    fn console_write(&mut self, bytes: &[u8]);
+
+    /// Get the next byte received on the console UART, if there is one.
+    fn console_read(&mut self) -> Option<u8>;
+
+    /// The system clock, in Hz.
+    fn sysclk_hz(&self) -> u32;
+
+    /// Wait for at least `ms` milliseconds.
+    fn delay_ms(&mut self, ms: u32);
+
+    /// What is at `position` (1 to 10) on a BoosterPack header. By default,
+    /// the Stellaris LaunchPad's layout (`HEADER_PINS`).
+    fn header_pin(&self, header: Header, position: u8) -> Option<HeaderPin> {
+        match position {
+            1..=10 => Some(HEADER_PINS[header as usize][usize::from(position) - 1]),
+            _ => None,
+        }
+    }
+
+    /// The console, for `write!`.
+    fn console(&mut self) -> Console<'_, Self>
+    where
+        Self: Sized,
+    {
+        Console { board: self }
+    }
+}
+
+/// The console UART of a board, as a `core::fmt::Write`.
+pub struct Console<'a, B: 'a> {
+    board: &'a mut B,
+}
+
+/// The four BoosterPack headers. J1 and J2 are the outer rows.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Header {
+    /// J1 - power and analog, on the left
+    J1,
+    /// J2 - ground and SPI, on the right
+    J2,
+    /// J3 - 5V and analog, inside J1
+    J3,
+    /// J4 - PWM and GPIO, inside J2
+    J4,
+}
+
+/// A GPIO port.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Port {
+    /// Port A
+    A,
+    /// Port B
+    B,
+    /// Port C
+    C,
+    /// Port D
+    D,
+    /// Port E
+    E,
+    /// Port F
+    F,
+}
+
+/// What is at a position on a BoosterPack header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HeaderPin {
+    /// A GPIO pin - port and pin number
+    Gpio(Port, u8),
+    /// 3.3V supply
+    Power3v3,
+    /// 5V (VBUS) supply
+    Power5v,
+    /// Ground
+    Ground,
+    /// The reset line
+    Reset,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// What is at each position on the Stellaris LaunchPad's BoosterPack
+/// headers. Indexed by `Header`, then position less one.
+pub const HEADER_PINS: [[HeaderPin; 10]; 4] = [
+    // J1
+    [
+        HeaderPin::Power3v3,
+        HeaderPin::Gpio(Port::B, 5),
+        HeaderPin::Gpio(Port::B, 0),
+        HeaderPin::Gpio(Port::B, 1),
+        HeaderPin::Gpio(Port::E, 4),
+        HeaderPin::Gpio(Port::E, 5),
+        HeaderPin::Gpio(Port::B, 4),
+        HeaderPin::Gpio(Port::A, 5),
+        HeaderPin::Gpio(Port::A, 6),
+        HeaderPin::Gpio(Port::A, 7),
+    ],
+    // J2
+    [
+        HeaderPin::Ground,
+        HeaderPin::Gpio(Port::B, 2),
+        HeaderPin::Gpio(Port::E, 0),
+        HeaderPin::Gpio(Port::F, 0),
+        HeaderPin::Reset,
+        HeaderPin::Gpio(Port::B, 7),
+        HeaderPin::Gpio(Port::B, 6),
+        HeaderPin::Gpio(Port::A, 4),
+        HeaderPin::Gpio(Port::A, 3),
+        HeaderPin::Gpio(Port::A, 2),
+    ],
+    // J3
+    [
+        HeaderPin::Power5v,
+        HeaderPin::Ground,
+        HeaderPin::Gpio(Port::D, 0),
+        HeaderPin::Gpio(Port::D, 1),
+        HeaderPin::Gpio(Port::D, 2),
+        HeaderPin::Gpio(Port::D, 3),
+        HeaderPin::Gpio(Port::E, 1),
+        HeaderPin::Gpio(Port::E, 2),
+        HeaderPin::Gpio(Port::E, 3),
+        HeaderPin::Gpio(Port::F, 1),
+    ],
+    // J4
+    [
+        HeaderPin::Gpio(Port::F, 2),
+        HeaderPin::Gpio(Port::F, 3),
+        HeaderPin::Gpio(Port::B, 3),
+        HeaderPin::Gpio(Port::C, 4),
+        HeaderPin::Gpio(Port::C, 5),
+        HeaderPin::Gpio(Port::C, 6),
+        HeaderPin::Gpio(Port::C, 7),
+        HeaderPin::Gpio(Port::D, 6),
+        HeaderPin::Gpio(Port::D, 7),
+        HeaderPin::Gpio(Port::F, 4),
+    ],
+];
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<'a, B: LaunchpadBoard> fmt::Write for Console<'a, B> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.board.console_write(s.as_bytes());
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,290 @@
+//! Reports on how SRAM is being used.
+//!
+//! At start-up (before `Board::new()`) the unused part of the stack is filled
+//! with a known pattern. The deepest point the stack has ever reached can
+//! then be found by looking for the lowest address where the pattern has
+//! been overwritten.
+//!
+//! The stack lives in the region reserved by `memory.x` (4 KiB at the top of
+//! SRAM). The linker refuses to link a program whose `.data` and `.bss` would
+//! overlap it, but by default nothing stops the stack itself growing past the
+//! bottom of the region at run-time - keep an eye on
+//! `stack_high_water_mark()`. There are two features which turn an overflow
+//! into an immediate, reported, fault instead:
+//!
+//! * `stack-guard` uses the MPU to make the bottom `STACK_GUARD_SIZE` bytes
+//!   of the stack region inaccessible.
+//! * `stack-at-bottom` moves the stack to the bottom of SRAM, so it overflows
+//!   into unmapped memory rather than into `.bss`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+use core::ptr;
+
+#[cfg(feature = "alloc")]
+use common::heap;
+#[cfg(feature = "stack-guard")]
+use common::mpu::{self, Background, Region};
+use cortex_m;
+#[cfg(feature = "stack-guard")]
+use cortex_m::peripheral::MPU;
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+use cortex_m::peripheral::SCB;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The value written to every unused word of stack at start-up.
+pub const STACK_PAINT: u32 = 0xCCCC_CCCC;
+
+/// The size of the inaccessible guard region at the bottom of the stack.
+/// Large enough that a function with a moderately large stack frame (or an
+/// exception frame with FPU state) can't jump over it.
+#[cfg(feature = "stack-guard")]
+pub const STACK_GUARD_SIZE: usize = 256;
+
+/// The size of the inaccessible guard region at the bottom of the stack.
+#[cfg(not(feature = "stack-guard"))]
+pub const STACK_GUARD_SIZE: usize = 0;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// How many bytes below the current stack pointer to leave unpainted, to
+/// cover the frame of `paint_stack` itself.
+const PAINT_MARGIN: usize = 64;
+
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const SHCSR_MEMFAULTENA: u32 = 1 << 16;
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const SHCSR_BUSFAULTENA: u32 = 1 << 17;
+/// MemManage fault on exception entry stacking
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const CFSR_MSTKERR: u32 = 1 << 4;
+/// MMFAR holds a valid fault address
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const CFSR_MMARVALID: u32 = 1 << 7;
+/// BusFault on exception entry stacking
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const CFSR_STKERR: u32 = 1 << 12;
+/// BFAR holds a valid fault address
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+const CFSR_BFARVALID: u32 = 1 << 15;
+
+extern "C" {
+    /// Start of `.data` (in SRAM) - from cortex-m-rt's link.x
+    static __sdata: u32;
+    /// End of `.data` (in SRAM) - from cortex-m-rt's link.x
+    static __edata: u32;
+    /// Start of `.bss` - from cortex-m-rt's link.x
+    static __sbss: u32;
+    /// End of `.bss` - from cortex-m-rt's link.x
+    static __ebss: u32;
+    /// The lowest address the stack may use - from memory.x
+    static _stack_limit: u32;
+    /// The initial stack pointer (the stack grows down from here) - from memory.x
+    static _stack_start: u32;
+    /// The end of the SRAM not used by statics or the stack - from memory.x
+    static _heap_end: u32;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// The total size of the usable stack region (excluding any guard region),
+/// in bytes.
+pub fn stack_size() -> usize {
+    stack_top() - stack_bottom()
+}
+
+/// The most stack, in bytes, that has been used since start-up.
+///
+/// If this equals `stack_size()`, the stack has probably overflowed.
+pub fn stack_high_water_mark() -> usize {
+    let mut p = stack_bottom() as *const u32;
+    let top = stack_top() as *const u32;
+    while p < top && unsafe { ptr::read_volatile(p) } == STACK_PAINT {
+        p = unsafe { p.add(1) };
+    }
+    stack_top() - (p as usize)
+}
+
+/// The stack, in bytes, in use right now.
+pub fn stack_in_use() -> usize {
+    stack_top() - cortex_m::register::msp::read() as usize
+}
+
+/// The size of `.data` (initialised statics), in bytes.
+pub fn data_size() -> usize {
+    unsafe { (&__edata as *const u32 as usize) - (&__sdata as *const u32 as usize) }
+}
+
+/// The size of `.bss` (zero-initialised statics), in bytes.
+pub fn bss_size() -> usize {
+    unsafe { (&__ebss as *const u32 as usize) - (&__sbss as *const u32 as usize) }
+}
+
+/// The SRAM not used by `.data`, `.bss` or the stack region, in bytes. With
+/// the `alloc` feature, this is all given to the heap.
+pub fn free_ram() -> usize {
+    unsafe { (&_heap_end as *const u32 as usize) - (&__ebss as *const u32 as usize) }
+}
+
+/// Print a summary of SRAM usage, suitable for a console command.
+pub fn report<W>(w: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    writeln!(
+        w,
+        "Stack: {} of {} bytes used at peak ({} now)",
+        stack_high_water_mark(),
+        stack_size(),
+        stack_in_use()
+    )?;
+    writeln!(
+        w,
+        "Statics: {} bytes .data, {} bytes .bss",
+        data_size(),
+        bss_size()
+    )?;
+    #[cfg(feature = "alloc")]
+    {
+        let stats = heap::stats();
+        writeln!(
+            w,
+            "Heap: {} of {} bytes used ({} at peak, {} failed allocations)",
+            stats.used, stats.size, stats.peak, stats.failures
+        )?;
+    }
+    writeln!(w, "Free: {} bytes", free_ram())
+}
+
+/// Fill the stack below the current stack pointer with `STACK_PAINT`.
+///
+/// Called at start-up, before `Board::new()`.
+pub(crate) unsafe fn paint_stack() {
+    let bottom = stack_bottom() as *mut u32;
+    let top = (cortex_m::register::msp::read() as usize - PAINT_MARGIN) as *mut u32;
+    let mut p = bottom;
+    while p < top {
+        ptr::write_volatile(p, STACK_PAINT);
+        p = p.add(1);
+    }
+}
+
+/// Make the bottom of the stack region inaccessible with the MPU, so that
+/// overflowing the stack causes a MemManage fault.
+///
+/// Called at start-up, before `Board::new()`.
+#[cfg(feature = "stack-guard")]
+pub(crate) unsafe fn enable_stack_guard() {
+    let regs = &*MPU::ptr();
+    let base = &_stack_limit as *const u32 as u32;
+    // Higher numbered regions take priority, so the guard gets the highest.
+    let (rbar, rasr) = mpu::encode(
+        mpu::STACK_GUARD_REGION,
+        &Region::no_access(base, STACK_GUARD_SIZE as u32),
+    )
+    .unwrap();
+    mpu::write_region(regs, rbar, rasr);
+    // Everything else gets the default memory map
+    regs.ctrl
+        .write(mpu::encode_ctrl(Background::PrivilegedDefault));
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// Turn on the MemManage and BusFault exceptions, so that a stack overflow
+/// is reported as such rather than escalating to a HardFault.
+///
+/// Called at start-up, before `Board::new()`.
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+pub(crate) unsafe fn enable_fault_handlers() {
+    (*SCB::ptr())
+        .shcsr
+        .modify(|r| r | SHCSR_MEMFAULTENA | SHCSR_BUSFAULTENA);
+}
+
+/// Describe a MemManage fault or BusFault, spotting stack overflows.
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+pub(crate) fn describe_fault<W>(w: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let scb = unsafe { &*SCB::ptr() };
+    let cfsr = scb.cfsr.read();
+    let mmfar = scb.mmfar.read() as usize;
+    let guard = (&_stack_limit as *const u32 as usize)..stack_bottom();
+    if (cfsr & (CFSR_MSTKERR | CFSR_STKERR)) != 0
+        || ((cfsr & CFSR_MMARVALID) != 0 && guard.start <= mmfar && mmfar < guard.end)
+    {
+        writeln!(w, "Stack overflow! (CFSR=0x{:08x})", cfsr)
+    } else if (cfsr & CFSR_MMARVALID) != 0 {
+        writeln!(
+            w,
+            "MemManage fault at 0x{:08x} (CFSR=0x{:08x})",
+            mmfar, cfsr
+        )
+    } else if (cfsr & CFSR_BFARVALID) != 0 {
+        writeln!(
+            w,
+            "BusFault at 0x{:08x} (CFSR=0x{:08x})",
+            scb.bfar.read(),
+            cfsr
+        )
+    } else {
+        writeln!(w, "Fault (CFSR=0x{:08x})", cfsr)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// The lowest usable stack address - above the guard region, if any.
+fn stack_bottom() -> usize {
+    unsafe { &_stack_limit as *const u32 as usize + STACK_GUARD_SIZE }
+}
+
+fn stack_top() -> usize {
+    unsafe { &_stack_start as *const u32 as usize }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -6,9 +6,25 @@
 //
 // ****************************************************************************
 
-// pub mod memory;
+pub mod build_info;
 pub mod builtins;
+pub mod crc;
+#[cfg(feature = "alloc")]
+pub mod heap;
+pub mod image_checksum;
+pub mod kvstore;
+pub mod launchpad;
+pub mod memory;
+pub mod mpu;
+pub mod profiling;
+pub mod ram_flash;
+pub mod settings;
+#[cfg(feature = "signed-images")]
+pub mod signed_image;
+pub mod sim_board;
 pub mod startup;
+pub mod update;
+pub mod update_protocol;
 
 // ****************************************************************************
 //
@@ -0,0 +1,398 @@
+//! A typed interface to the Cortex-M4 Memory Protection Unit (MPU).
+//!
+//! Describe each region with a `Region` (or use one of the presets), then
+//! hand it to `Mpu::configure`. The checking and encoding is done by
+//! `encode()`, which only does arithmetic, so it can be exercised anywhere.
+//!
+//! ```rust,ignore
+//! let mut mpu = Mpu::new(board.core_peripherals.MPU);
+//! mpu.configure(0, &Region::flash_read_only(0x0000_0000, 256 * 1024))?;
+//! mpu.configure(1, &Region::ram_no_execute(0x2000_0000, 32 * 1024))?;
+//! mpu.configure(2, &Region::peripherals(0x4000_0000, 0x1000_0000))?;
+//! mpu.enable(Background::PrivilegedDefault);
+//! ```
+//!
+//! With the `stack-guard` feature, region 7 is used to protect the stack and
+//! cannot be configured here.
+//!
+//! Only `Mpu` touches the hardware; the rest builds on the host too, where
+//! `host/tests/mpu.rs` checks `encode()`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[cfg(target_arch = "arm")]
+use cortex_m;
+#[cfg(target_arch = "arm")]
+use cortex_m::peripheral::{mpu, MPU};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Who may read and write a region. Execution is controlled separately.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Access {
+    /// Any access faults
+    NoAccess,
+    /// Privileged code may read and write, unprivileged code may not access
+    PrivilegedReadWrite,
+    /// Privileged code may read and write, unprivileged code may only read
+    PrivilegedReadWriteUserReadOnly,
+    /// Anyone may read and write
+    ReadWrite,
+    /// Privileged code may only read, unprivileged code may not access
+    PrivilegedReadOnly,
+    /// Anyone may read, nobody may write
+    ReadOnly,
+}
+
+/// The memory type and cacheability of a region.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MemoryType {
+    /// All accesses happen in program order, exactly as written
+    StronglyOrdered,
+    /// Memory-mapped peripherals
+    Device,
+    /// Normal memory, write-through, no write allocate
+    WriteThrough,
+    /// Normal memory, write-back, no write allocate
+    WriteBack,
+    /// Normal memory, write-back, write and read allocate
+    WriteBackAllocate,
+    /// Normal memory, not cached
+    NonCacheable,
+}
+
+/// What happens to accesses which don't hit any enabled region.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Background {
+    /// Privileged code gets the default memory map; unprivileged code faults
+    PrivilegedDefault,
+    /// Any access outside an enabled region faults
+    Fault,
+}
+
+/// One MPU region.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Region {
+    /// The start address. Must be a multiple of `size`.
+    pub base: u32,
+    /// The size in bytes. Must be a power of two, at least 32 bytes, or
+    /// `FOUR_GIB` for the whole address space.
+    pub size: u32,
+    /// Read/write permissions
+    pub access: Access,
+    /// Can code be executed from this region? If not, the region is XN.
+    pub executable: bool,
+    /// Memory type and cacheability
+    pub memory_type: MemoryType,
+    /// Is the region shared between bus masters (e.g. the CPU and uDMA)?
+    pub shareable: bool,
+    /// Each region of 256 bytes or more is split into eight equal
+    /// sub-regions. Setting bit `n` excludes sub-region `n` (counting from
+    /// the base address) from this region.
+    pub subregions_disabled: u8,
+}
+
+/// Reasons a region can't be programmed into the MPU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// Regions must be at least 32 bytes
+    SizeTooSmall,
+    /// Region sizes must be a power of two
+    SizeNotPowerOfTwo,
+    /// The base address must be a multiple of the size
+    BaseNotAligned,
+    /// Sub-regions can only be disabled in regions of 256 bytes or more
+    SubregionsTooSmall,
+    /// There are only eight regions (0..=7)
+    BadRegionNumber,
+    /// This region number is in use elsewhere in this crate
+    RegionReserved,
+}
+
+/// Owns the MPU, and programs regions into it.
+#[cfg(target_arch = "arm")]
+pub struct Mpu {
+    mpu: MPU,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The number of regions the MPU supports.
+pub const NUM_REGIONS: u8 = 8;
+
+/// The region used by the `stack-guard` feature.
+pub const STACK_GUARD_REGION: u8 = 7;
+
+/// A `Region::size` meaning the whole 4 GiB address space, which doesn't fit
+/// in a `u32`.
+pub const FOUR_GIB: u32 = 0;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const MIN_SIZE: u32 = 32;
+const MIN_SUBREGION_SIZE: u32 = 256;
+
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+
+const RBAR_VALID: u32 = 1 << 4;
+
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_SIZE_SHIFT: u32 = 1;
+const RASR_SRD_SHIFT: u32 = 8;
+const RASR_B: u32 = 1 << 16;
+const RASR_C: u32 = 1 << 17;
+const RASR_S: u32 = 1 << 18;
+const RASR_TEX_SHIFT: u32 = 19;
+const RASR_AP_SHIFT: u32 = 24;
+const RASR_XN: u32 = 1 << 28;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Region {
+    /// Flash (or ROM): anyone may read and execute, nobody may write.
+    pub fn flash_read_only(base: u32, size: u32) -> Region {
+        Region {
+            base,
+            size,
+            access: Access::ReadOnly,
+            executable: true,
+            memory_type: MemoryType::WriteThrough,
+            shareable: false,
+            subregions_disabled: 0,
+        }
+    }
+
+    /// SRAM: anyone may read and write, nobody may execute.
+    pub fn ram_no_execute(base: u32, size: u32) -> Region {
+        Region {
+            base,
+            size,
+            access: Access::ReadWrite,
+            executable: false,
+            memory_type: MemoryType::WriteBack,
+            shareable: true,
+            subregions_disabled: 0,
+        }
+    }
+
+    /// Memory-mapped peripherals: privileged code may read and write, nobody
+    /// may execute.
+    pub fn peripherals(base: u32, size: u32) -> Region {
+        Region {
+            base,
+            size,
+            access: Access::PrivilegedReadWrite,
+            executable: false,
+            memory_type: MemoryType::Device,
+            shareable: true,
+            subregions_disabled: 0,
+        }
+    }
+
+    /// Nobody may read, write or execute. Useful as a guard region.
+    pub fn no_access(base: u32, size: u32) -> Region {
+        Region {
+            base,
+            size,
+            access: Access::NoAccess,
+            executable: false,
+            memory_type: MemoryType::StronglyOrdered,
+            shareable: false,
+            subregions_disabled: 0,
+        }
+    }
+}
+
+/// Check a region and work out the RBAR and RASR values which describe it.
+/// RBAR has the VALID bit set, so it selects `number` as well.
+pub fn encode(number: u8, region: &Region) -> Result<(u32, u32), Error> {
+    if number >= NUM_REGIONS {
+        return Err(Error::BadRegionNumber);
+    }
+    let log2_size = if region.size == FOUR_GIB {
+        32
+    } else {
+        if region.size < MIN_SIZE {
+            return Err(Error::SizeTooSmall);
+        }
+        if !region.size.is_power_of_two() {
+            return Err(Error::SizeNotPowerOfTwo);
+        }
+        if region.subregions_disabled != 0 && region.size < MIN_SUBREGION_SIZE {
+            return Err(Error::SubregionsTooSmall);
+        }
+        region.size.trailing_zeros()
+    };
+    // All ones for 4 GiB, so only a base of zero will do
+    if region.base & region.size.wrapping_sub(1) != 0 {
+        return Err(Error::BaseNotAligned);
+    }
+
+    let rbar = region.base | RBAR_VALID | u32::from(number);
+
+    // Region size is 2^(SIZE + 1) bytes
+    let size_field = log2_size - 1;
+    let ap = match region.access {
+        Access::NoAccess => 0b000,
+        Access::PrivilegedReadWrite => 0b001,
+        Access::PrivilegedReadWriteUserReadOnly => 0b010,
+        Access::ReadWrite => 0b011,
+        Access::PrivilegedReadOnly => 0b101,
+        Access::ReadOnly => 0b110,
+    };
+    let (tex, c, b) = match region.memory_type {
+        MemoryType::StronglyOrdered => (0b000, false, false),
+        MemoryType::Device => (0b000, false, true),
+        MemoryType::WriteThrough => (0b000, true, false),
+        MemoryType::WriteBack => (0b000, true, true),
+        MemoryType::NonCacheable => (0b001, false, false),
+        MemoryType::WriteBackAllocate => (0b001, true, true),
+    };
+    let mut rasr = RASR_ENABLE
+        | (size_field << RASR_SIZE_SHIFT)
+        | (u32::from(region.subregions_disabled) << RASR_SRD_SHIFT)
+        | (tex << RASR_TEX_SHIFT)
+        | (ap << RASR_AP_SHIFT);
+    if c {
+        rasr |= RASR_C;
+    }
+    if b {
+        rasr |= RASR_B;
+    }
+    if region.shareable {
+        rasr |= RASR_S;
+    }
+    if !region.executable {
+        rasr |= RASR_XN;
+    }
+    Ok((rbar, rasr))
+}
+
+/// Work out the CTRL value which turns the MPU on.
+pub fn encode_ctrl(background: Background) -> u32 {
+    match background {
+        Background::PrivilegedDefault => CTRL_ENABLE | CTRL_PRIVDEFENA,
+        Background::Fault => CTRL_ENABLE,
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl Mpu {
+    /// Take ownership of the MPU. It is left as it is (the `stack-guard`
+    /// feature will already have turned it on).
+    pub fn new(mpu: MPU) -> Mpu {
+        Mpu { mpu }
+    }
+
+    /// Program a region (0..=7). Higher numbered regions take priority
+    /// where regions overlap. Takes effect immediately if the MPU is on.
+    pub fn configure(&mut self, number: u8, region: &Region) -> Result<(), Error> {
+        check_reserved(number)?;
+        let (rbar, rasr) = encode(number, region)?;
+        unsafe { write_region(&self.mpu, rbar, rasr) };
+        Ok(())
+    }
+
+    /// Turn off a region.
+    pub fn disable_region(&mut self, number: u8) -> Result<(), Error> {
+        if number >= NUM_REGIONS {
+            return Err(Error::BadRegionNumber);
+        }
+        check_reserved(number)?;
+        unsafe {
+            self.mpu.rnr.write(u32::from(number));
+            self.mpu.rasr.write(0);
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+        Ok(())
+    }
+
+    /// Turn the MPU on.
+    pub fn enable(&mut self, background: Background) {
+        unsafe { self.mpu.ctrl.write(encode_ctrl(background)) };
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    /// Turn the MPU off. Every access uses the default memory map. This also
+    /// turns off the `stack-guard` feature's protection.
+    pub fn disable(&mut self) {
+        cortex_m::asm::dmb();
+        unsafe { self.mpu.ctrl.write(0) };
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    /// Give back the MPU.
+    pub fn free(self) -> MPU {
+        self.mpu
+    }
+}
+
+/// Program an already encoded region. RBAR must have the VALID bit set.
+#[cfg(target_arch = "arm")]
+pub(crate) unsafe fn write_region(regs: &mpu::RegisterBlock, rbar: u32, rasr: u32) {
+    // Turn the region off whilst we change it
+    regs.rnr.write(rbar & 0x7);
+    regs.rasr.write(0);
+    regs.rbar.write(rbar);
+    regs.rasr.write(rasr);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+#[cfg(all(target_arch = "arm", feature = "stack-guard"))]
+fn check_reserved(number: u8) -> Result<(), Error> {
+    if number == STACK_GUARD_REGION {
+        Err(Error::RegionReserved)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "stack-guard")))]
+fn check_reserved(_number: u8) -> Result<(), Error> {
+    Ok(())
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
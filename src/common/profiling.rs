@@ -0,0 +1,257 @@
+//! Measures how long code takes to run, using the DWT cycle counter.
+//!
+//! Call `enable()` once with the DCB and DWT from `Board::core_peripherals`,
+//! then wrap the code you're interested in with `measure()`, or accumulate
+//! many runs in a `Stopwatch`:
+//!
+//! ```rust,ignore
+//! profiling::enable(&mut board.core_peripherals.DCB, &mut board.core_peripherals.DWT);
+//! let mut draw = Stopwatch::new("draw");
+//! loop {
+//!     draw.time(|| redraw());
+//!     profiling::report(&mut uart, &[&draw]).unwrap();
+//! }
+//! ```
+//!
+//! The counter is 32 bits, so it wraps after about 64 seconds at 66.67 MHz.
+//! Measurements longer than that will be wrong.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+
+use board;
+use cortex_m::peripheral::{DCB, DWT};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The time taken by one run of some code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Measurement {
+    /// The number of CPU cycles which elapsed
+    pub cycles: u32,
+}
+
+/// Accumulates measurements of the same piece of code.
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    name: &'static str,
+    count: u32,
+    total: u64,
+    min: u32,
+    max: u32,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Turn on the DWT cycle counter.
+pub fn enable(dcb: &mut DCB, dwt: &mut DWT) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+}
+
+/// Read the free-running cycle counter.
+pub fn cycles() -> u32 {
+    unsafe { (*DWT::ptr()).cyccnt.read() }
+}
+
+/// Run `f` and return its result, along with how long it took.
+pub fn measure<F, R>(f: F) -> (R, Measurement)
+where
+    F: FnOnce() -> R,
+{
+    let start = cycles();
+    let result = f();
+    let end = cycles();
+    (
+        result,
+        Measurement {
+            cycles: end.wrapping_sub(start),
+        },
+    )
+}
+
+/// Convert a number of cycles at the current system clock rate into
+/// nanoseconds. Whole seconds are split off first, so the multiply can't
+/// overflow; the result saturates at `u64::max_value()`.
+pub fn cycles_to_ns(cycles: u64) -> u64 {
+    let sysclk = u64::from(board::clocks().sysclk.0);
+    let seconds = cycles / sysclk;
+    // Less than sysclk (a u32), so this fits in a u64
+    let rest = (cycles % sysclk) * 1_000_000_000 / sysclk;
+    seconds.saturating_mul(1_000_000_000).saturating_add(rest)
+}
+
+/// Print a table of stopwatches.
+pub fn report<W>(w: &mut W, stopwatches: &[&Stopwatch]) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    writeln!(
+        w,
+        "{:<16} {:>8} {:>12} {:>12} {:>12}",
+        "name", "count", "min ns", "mean ns", "max ns"
+    )?;
+    for stopwatch in stopwatches {
+        writeln!(w, "{}", stopwatch)?;
+    }
+    Ok(())
+}
+
+impl Measurement {
+    /// How long this took, in nanoseconds, at the current system clock rate.
+    pub fn nanoseconds(&self) -> u64 {
+        cycles_to_ns(u64::from(self.cycles))
+    }
+}
+
+impl Stopwatch {
+    /// Create a new, empty, stopwatch.
+    pub const fn new(name: &'static str) -> Stopwatch {
+        Stopwatch {
+            name,
+            count: 0,
+            total: 0,
+            min: u32::max_value(),
+            max: 0,
+        }
+    }
+
+    /// Run `f`, add how long it took to this stopwatch, and return its
+    /// result.
+    pub fn time<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let (result, measurement) = measure(f);
+        self.record(measurement);
+        result
+    }
+
+    /// Add a measurement to this stopwatch. Once `count` has reached
+    /// `u32::max_value()` the mean stops changing, but `min` and `max` are
+    /// still kept up to date.
+    pub fn record(&mut self, measurement: Measurement) {
+        if let Some(count) = self.count.checked_add(1) {
+            // At most u32::MAX measurements of at most u32::MAX cycles, so
+            // the total can't overflow
+            self.count = count;
+            self.total += u64::from(measurement.cycles);
+        }
+        self.min = self.min.min(measurement.cycles);
+        self.max = self.max.max(measurement.cycles);
+    }
+
+    /// Forget all the measurements so far.
+    pub fn reset(&mut self) {
+        *self = Stopwatch::new(self.name);
+    }
+
+    /// The name given to this stopwatch
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// How many measurements have been taken
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The shortest measurement, in cycles.
+    pub fn min(&self) -> Option<u32> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.min)
+        }
+    }
+
+    /// The longest measurement, in cycles.
+    pub fn max(&self) -> Option<u32> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.max)
+        }
+    }
+
+    /// The mean measurement, in cycles.
+    pub fn mean(&self) -> Option<u32> {
+        if self.count == 0 {
+            None
+        } else {
+            Some((self.total / u64::from(self.count)) as u32)
+        }
+    }
+}
+
+impl fmt::Display for Stopwatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.min(), self.mean(), self.max()) {
+            (Some(min), Some(mean), Some(max)) => write!(
+                f,
+                "{:<16} {:>8} {:>12} {:>12} {:>12}",
+                self.name,
+                self.count,
+                cycles_to_ns(u64::from(min)),
+                cycles_to_ns(u64::from(mean)),
+                cycles_to_ns(u64::from(max))
+            ),
+            _ => write!(
+                f,
+                "{:<16} {:>8} {:>12} {:>12} {:>12}",
+                self.name, 0, "-", "-", "-"
+            ),
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,189 @@
+//! A NOR flash simulator in SRAM (or on a PC).
+//!
+//! `RamFlash` implements the same `embedded-storage` traits as
+//! `board::flash::Flash`, with the same page and word sizes, so code written
+//! against those traits can be exercised without touching the real flash.
+//! Like real NOR flash, erasing sets every bit in a page and writing can only
+//! clear bits.
+//!
+//! To test what happens if the power fails part way through, call
+//! `fail_after()`. Once that many more words have been written (or pages
+//! erased) every operation fails, as if the power had gone, and the flash
+//! keeps whatever state it had reached.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_storage::nor_flash::{self, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// `SIZE` bytes of simulated NOR flash. `SIZE` must be a multiple of
+/// `ERASE_SIZE`.
+pub struct RamFlash<const SIZE: usize> {
+    data: [u8; SIZE],
+    remaining: Option<usize>,
+    writes: usize,
+    erases: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<const SIZE: usize> RamFlash<SIZE> {
+    /// Make a new flash, fully erased.
+    pub const fn new() -> RamFlash<SIZE> {
+        RamFlash {
+            data: [0xFF; SIZE],
+            remaining: None,
+            writes: 0,
+            erases: 0,
+        }
+    }
+
+    /// Let `operations` more words be written or pages be erased, then fail
+    /// everything.
+    pub fn fail_after(&mut self, operations: usize) {
+        self.remaining = Some(operations);
+    }
+
+    /// Restore the power, after `fail_after()`.
+    pub fn restore(&mut self) {
+        self.remaining = None;
+    }
+
+    /// Has the simulated power failed?
+    pub fn has_failed(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// The number of words written so far.
+    pub fn writes(&self) -> usize {
+        self.writes
+    }
+
+    /// The number of pages erased so far.
+    pub fn erases(&self) -> usize {
+        self.erases
+    }
+
+    /// The raw contents of the flash.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn operation(&mut self) -> Result<(), NorFlashErrorKind> {
+        match self.remaining {
+            Some(0) => Err(NorFlashErrorKind::Other),
+            Some(ref mut n) => {
+                *n -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<const SIZE: usize> Default for RamFlash<SIZE> {
+    fn default() -> RamFlash<SIZE> {
+        RamFlash::new()
+    }
+}
+
+impl<const SIZE: usize> ErrorType for RamFlash<SIZE> {
+    type Error = NorFlashErrorKind;
+}
+
+impl<const SIZE: usize> ReadNorFlash for RamFlash<SIZE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), NorFlashErrorKind> {
+        nor_flash::check_read(self, offset, bytes.len())?;
+        let start = offset as usize;
+        bytes.copy_from_slice(&self.data[start..start + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        SIZE
+    }
+}
+
+impl<const SIZE: usize> NorFlash for RamFlash<SIZE> {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = 1024;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), NorFlashErrorKind> {
+        nor_flash::check_erase(self, from, to)?;
+        for page in (from as usize..to as usize).step_by(Self::ERASE_SIZE) {
+            self.operation()?;
+            for byte in &mut self.data[page..page + Self::ERASE_SIZE] {
+                *byte = 0xFF;
+            }
+            self.erases += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), NorFlashErrorKind> {
+        nor_flash::check_write(self, offset, bytes.len())?;
+        let start = offset as usize;
+        for (i, word) in bytes.chunks(Self::WRITE_SIZE).enumerate() {
+            self.operation()?;
+            let at = start + i * Self::WRITE_SIZE;
+            for (stored, byte) in self.data[at..at + word.len()].iter_mut().zip(word) {
+                *stored &= *byte;
+            }
+            self.writes += 1;
+        }
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
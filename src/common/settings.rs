@@ -0,0 +1,449 @@
+//! Typed, versioned settings, stored as compact CRC-protected records.
+//!
+//! Implement `Settings` for a struct (it must also implement `Default`),
+//! then save and load it with `board::eeprom::Eeprom::save_settings` and
+//! `load_settings`, or use `save` and `load` here with any byte buffer.
+//!
+//! ```rust,ignore
+//! #[derive(Default)]
+//! struct Config {
+//!     baud: u32,
+//!     brightness: u8,
+//!     // Added in version 2
+//!     inverted: bool,
+//! }
+//!
+//! impl Settings for Config {
+//!     const VERSION: u16 = 2;
+//!
+//!     fn encode(&self, w: &mut Writer) {
+//!         w.u32(self.baud);
+//!         w.u8(self.brightness);
+//!         w.bool(self.inverted);
+//!     }
+//!
+//!     fn decode(version: u16, r: &mut Reader) -> Option<Config> {
+//!         let mut config = Config::default();
+//!         config.baud = r.u32()?;
+//!         config.brightness = r.u8()?;
+//!         if version >= 2 {
+//!             config.inverted = r.bool()?;
+//!         }
+//!         Some(config)
+//!     }
+//! }
+//! ```
+//!
+//! A record is a header (magic number, version, payload length and sequence
+//! number, two bytes each), the payload as written by `encode` (all
+//! little-endian, with no padding) and a CRC-32 of everything before it.
+//!
+//! So that a power cut while saving can't lose everything, keep two copies
+//! and write them alternately: `load_newest` picks the newest good copy, and
+//! says which copy to overwrite next, and with what sequence number.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use common::crc;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Something which can be saved as settings.
+pub trait Settings: Default {
+    /// The current version of the format. Increase it whenever `encode`
+    /// changes.
+    const VERSION: u16;
+
+    /// Write out the fields.
+    fn encode(&self, w: &mut Writer);
+
+    /// Read back fields written by `encode` - possibly by an older version
+    /// (but never a newer one). Return `None` if the data makes no sense.
+    fn decode(version: u16, r: &mut Reader) -> Option<Self>;
+}
+
+/// What `load` found.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    /// The settings were loaded
+    Loaded,
+    /// The settings were saved by an older version, and have been upgraded
+    Migrated {
+        /// The version which saved them
+        from: u16,
+    },
+    /// Nothing has been saved - you get the defaults
+    Blank,
+    /// The settings are damaged (or `decode` rejected them) - you get the
+    /// defaults
+    Corrupt,
+    /// The settings were saved by a newer version - you get the defaults
+    TooNew {
+        /// The version which saved them
+        version: u16,
+    },
+}
+
+/// Which copy `load_newest` wants written next.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Next {
+    /// The copy to overwrite (0 or 1) - never the one that was loaded
+    pub copy: usize,
+    /// The sequence number to give it
+    pub sequence: u16,
+}
+
+/// The settings didn't fit in the buffer given to `save`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TooBig;
+
+/// Writes the fields of a settings struct.
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+    overflowed: bool,
+}
+
+/// Reads the fields of a settings struct.
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The bytes added to the payload: an eight byte header and a four byte CRC.
+pub const OVERHEAD: usize = HEADER_LEN + CRC_LEN;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// "ST"
+const MAGIC: u16 = 0x5453;
+const HEADER_LEN: usize = 8;
+const CRC_LEN: usize = 4;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Write `settings` as a record at the start of `buffer`. Returns the length
+/// of the record. If you only keep one copy, the sequence number can be
+/// anything.
+pub fn save<S>(settings: &S, sequence: u16, buffer: &mut [u8]) -> Result<usize, TooBig>
+where
+    S: Settings,
+{
+    if buffer.len() < OVERHEAD {
+        return Err(TooBig);
+    }
+    let payload_len = {
+        let end = buffer.len() - CRC_LEN;
+        let mut w = Writer::new(&mut buffer[HEADER_LEN..end]);
+        settings.encode(&mut w);
+        if w.overflowed || w.position > usize::from(u16::MAX) {
+            return Err(TooBig);
+        }
+        w.position
+    };
+    buffer[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+    buffer[2..4].copy_from_slice(&S::VERSION.to_le_bytes());
+    buffer[4..6].copy_from_slice(&(payload_len as u16).to_le_bytes());
+    buffer[6..8].copy_from_slice(&sequence.to_le_bytes());
+    let crc_at = HEADER_LEN + payload_len;
+    let crc = crc::crc32(&buffer[..crc_at]);
+    buffer[crc_at..crc_at + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+    Ok(crc_at + CRC_LEN)
+}
+
+/// Read settings from a record at the start of `buffer`. If there isn't a
+/// usable record, you get the defaults.
+pub fn load<S>(buffer: &[u8]) -> (S, Outcome)
+where
+    S: Settings,
+{
+    let payload = match check(buffer) {
+        Ok((payload, _)) => payload,
+        Err(outcome) => return (S::default(), outcome),
+    };
+    let version = u16::from_le_bytes([buffer[2], buffer[3]]);
+    if version > S::VERSION {
+        return (S::default(), Outcome::TooNew { version });
+    }
+    let mut r = Reader::new(payload);
+    match S::decode(version, &mut r) {
+        Some(settings) if version == S::VERSION => (settings, Outcome::Loaded),
+        Some(settings) => (settings, Outcome::Migrated { from: version }),
+        None => (S::default(), Outcome::Corrupt),
+    }
+}
+
+/// Read settings from whichever of two copies is the newest good one. If the
+/// newest can't be used, the other is tried. Write the next save to the copy
+/// in `Next`, so the copy that was loaded survives if it is interrupted.
+pub fn load_newest<S>(copies: [&[u8]; 2]) -> (S, Outcome, Next)
+where
+    S: Settings,
+{
+    let sequences = [sequence(copies[0]), sequence(copies[1])];
+    let newest = match sequences {
+        [Some(a), Some(b)] if (b.wrapping_sub(a) as i16) > 0 => 1,
+        [None, Some(_)] => 1,
+        _ => 0,
+    };
+    let mut first = None;
+    for &copy in [newest, 1 - newest].iter() {
+        let sequence = match sequences[copy] {
+            Some(sequence) => sequence,
+            None => continue,
+        };
+        let (settings, outcome) = load(copies[copy]);
+        let next = Next {
+            copy: 1 - copy,
+            sequence: sequence.wrapping_add(1),
+        };
+        match outcome {
+            Outcome::Loaded | Outcome::Migrated { .. } => return (settings, outcome, next),
+            _ if first.is_none() => first = Some((outcome, next)),
+            _ => {}
+        }
+    }
+    match first {
+        Some((outcome, next)) => (S::default(), outcome, next),
+        None => {
+            let damaged = copies
+                .iter()
+                .any(|copy| matches!(check(copy), Err(Outcome::Corrupt)));
+            let outcome = if damaged {
+                Outcome::Corrupt
+            } else {
+                Outcome::Blank
+            };
+            (
+                S::default(),
+                outcome,
+                Next {
+                    copy: 0,
+                    sequence: 0,
+                },
+            )
+        }
+    }
+}
+
+/// The sequence number of the record at the start of `buffer`, if it is
+/// intact.
+pub fn sequence(buffer: &[u8]) -> Option<u16> {
+    check(buffer).ok().map(|(_, sequence)| sequence)
+}
+
+impl<'a> Writer<'a> {
+    /// Write into `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Writer<'a> {
+        Writer {
+            buffer,
+            position: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Write some bytes as they are. The reader needs to know how many.
+    pub fn bytes(&mut self, data: &[u8]) {
+        let end = self.position + data.len();
+        if end > self.buffer.len() {
+            self.overflowed = true;
+            return;
+        }
+        self.buffer[self.position..end].copy_from_slice(data);
+        self.position = end;
+    }
+
+    /// Write a `bool` as one byte.
+    pub fn bool(&mut self, value: bool) {
+        self.u8(u8::from(value));
+    }
+
+    /// Write a `u8`.
+    pub fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    /// Write a `u16`.
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Write a `u32`.
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Write an `i8`.
+    pub fn i8(&mut self, value: i8) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Write an `i16`.
+    pub fn i16(&mut self, value: i16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Write an `i32`.
+    pub fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Write an `f32`.
+    pub fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// How many bytes have been written.
+    pub fn len(&self) -> usize {
+        self.position
+    }
+
+    /// Has nothing been written yet?
+    pub fn is_empty(&self) -> bool {
+        self.position == 0
+    }
+
+    /// Did we run out of space?
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Read from `buffer`.
+    pub fn new(buffer: &'a [u8]) -> Reader<'a> {
+        Reader {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// Read `len` bytes as they are.
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(len)?;
+        let data = self.buffer.get(self.position..end)?;
+        self.position = end;
+        Some(data)
+    }
+
+    /// Read a `bool`. Anything other than 0 or 1 is an error.
+    pub fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Read a `u8`.
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+
+    /// Read a `u16`.
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.array()?))
+    }
+
+    /// Read a `u32`.
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.array()?))
+    }
+
+    /// Read an `i8`.
+    pub fn i8(&mut self) -> Option<i8> {
+        Some(i8::from_le_bytes(self.array()?))
+    }
+
+    /// Read an `i16`.
+    pub fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_le_bytes(self.array()?))
+    }
+
+    /// Read an `i32`.
+    pub fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_le_bytes(self.array()?))
+    }
+
+    /// Read an `f32`.
+    pub fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.array()?))
+    }
+
+    /// How many bytes are left.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut result = [0u8; N];
+        result.copy_from_slice(self.bytes(N)?);
+        Some(result)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Check the magic number, length and CRC of a record. Gives the payload and
+/// the sequence number.
+fn check(buffer: &[u8]) -> Result<(&[u8], u16), Outcome> {
+    if buffer.len() < OVERHEAD || u16::from_le_bytes([buffer[0], buffer[1]]) != MAGIC {
+        return Err(Outcome::Blank);
+    }
+    let payload_len = usize::from(u16::from_le_bytes([buffer[4], buffer[5]]));
+    let crc_at = HEADER_LEN + payload_len;
+    if crc_at + CRC_LEN > buffer.len() {
+        return Err(Outcome::Corrupt);
+    }
+    let crc = u32::from_le_bytes([
+        buffer[crc_at],
+        buffer[crc_at + 1],
+        buffer[crc_at + 2],
+        buffer[crc_at + 3],
+    ]);
+    if crc != crc::crc32(&buffer[..crc_at]) {
+        return Err(Outcome::Corrupt);
+    }
+    let sequence = u16::from_le_bytes([buffer[6], buffer[7]]);
+    Ok((&buffer[HEADER_LEN..crc_at], sequence))
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,305 @@
+//! Signed firmware images, for the `signed-images` feature.
+//!
+//! `lp-sign` (see `tools/`) appends a header to an image, so the vector table
+//! can stay at the start of the slot:
+//!
+//! | Offset | Size | Field                                          |
+//! |--------|------|------------------------------------------------|
+//! | 0      | 4    | Magic number, `LPS1`                           |
+//! | 4      | 4    | Version                                        |
+//! | 8      | 4    | Size of the image (the offset of this header)  |
+//! | 12     | 4    | The address the image is linked for            |
+//! | 16     | 32   | SHA-256 of the image                           |
+//! | 48     | 64   | Ed25519 signature of the 48 bytes above        |
+//!
+//! All numbers are little-endian. The boot loader checks the signature with
+//! the public key built into it, and refuses any image older than the
+//! newest one that has confirmed itself (the anti-rollback version, which it
+//! keeps in the EEPROM).
+//!
+//! ```rust,ignore
+//! let check = SignatureCheck::new(PUBLIC_KEY, minimum_version);
+//! let updater = Updater::open_with(flash, update::LAUNCHPAD, check)?;
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use ed25519_compact::{PublicKey, Signature};
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use sha2::{Digest, Sha256};
+
+use common::update::Verifier;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The header on the end of a signed image.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Header {
+    /// The version of the image
+    pub version: u32,
+    /// The size of the image, not counting the header
+    pub size: u32,
+    /// The address the image is linked for
+    pub load_address: u32,
+    /// SHA-256 of the image
+    pub hash: [u8; 32],
+    /// Ed25519 signature of the fields above
+    pub signature: [u8; 64],
+}
+
+/// Why an image was refused.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<E> {
+    /// The underlying flash failed
+    Flash(E),
+    /// There's no header on the end of the image
+    NoHeader,
+    /// The image is linked for somewhere else
+    WrongAddress,
+    /// The image doesn't match the hash in its header
+    BadHash,
+    /// The header wasn't signed with our key
+    BadSignature,
+    /// The image is older than the anti-rollback version
+    TooOld {
+        /// The image's version
+        version: u32,
+        /// The oldest version we accept
+        minimum: u32,
+    },
+}
+
+/// An `update::Verifier` which only accepts images signed with our key, and
+/// no older than a given version.
+pub struct SignatureCheck {
+    key: [u8; KEY_LEN],
+    minimum_version: u32,
+    accepted: Option<Header>,
+    refused: Option<Error<()>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The length of the header.
+pub const HEADER_LEN: usize = SIGNED_LEN + SIGNATURE_LEN;
+
+/// The length of the part of the header which is signed.
+pub const SIGNED_LEN: usize = 48;
+
+/// The length of an Ed25519 public key.
+pub const KEY_LEN: usize = 32;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// "LPS1"
+const MAGIC: u32 = 0x3153_504C;
+const SIGNATURE_LEN: usize = 64;
+/// How many bytes to read at a time when hashing
+const CHUNK_SIZE: usize = 64;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Header {
+    /// Decode a header. Returns `None` if the magic number is wrong.
+    pub fn parse(bytes: &[u8; HEADER_LEN]) -> Option<Header> {
+        if word(bytes, 0) != MAGIC {
+            return None;
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&bytes[16..48]);
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&bytes[SIGNED_LEN..]);
+        Some(Header {
+            version: word(bytes, 4),
+            size: word(bytes, 8),
+            load_address: word(bytes, 12),
+            hash,
+            signature,
+        })
+    }
+
+    /// Encode the header.
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.size.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.load_address.to_le_bytes());
+        bytes[16..48].copy_from_slice(&self.hash);
+        bytes[SIGNED_LEN..].copy_from_slice(&self.signature);
+        bytes
+    }
+}
+
+/// Check the signed image of `len` bytes (including its header) at `start`
+/// in flash, and return its header.
+pub fn verify<F>(
+    flash: &mut F,
+    start: u32,
+    len: u32,
+    key: &[u8; KEY_LEN],
+    minimum_version: u32,
+) -> Result<Header, Error<F::Error>>
+where
+    F: ReadNorFlash,
+{
+    let header_len = HEADER_LEN as u32;
+    if len < header_len {
+        return Err(Error::NoHeader);
+    }
+    let mut bytes = [0u8; HEADER_LEN];
+    flash
+        .read(start + len - header_len, &mut bytes)
+        .map_err(Error::Flash)?;
+    let header = match Header::parse(&bytes) {
+        Some(header) if header.size == len - header_len => header,
+        _ => return Err(Error::NoHeader),
+    };
+    if header.load_address != start {
+        return Err(Error::WrongAddress);
+    }
+
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; CHUNK_SIZE];
+    let mut offset = 0;
+    while offset < header.size {
+        let chunk = CHUNK_SIZE.min((header.size - offset) as usize);
+        flash
+            .read(start + offset, &mut buffer[..chunk])
+            .map_err(Error::Flash)?;
+        hasher.update(&buffer[..chunk]);
+        offset += chunk as u32;
+    }
+    if hasher.finalize().as_slice() != &header.hash[..] {
+        return Err(Error::BadHash);
+    }
+
+    let signature = Signature::new(header.signature);
+    PublicKey::new(*key)
+        .verify(&bytes[..SIGNED_LEN], &signature)
+        .map_err(|_| Error::BadSignature)?;
+
+    if header.version < minimum_version {
+        return Err(Error::TooOld {
+            version: header.version,
+            minimum: minimum_version,
+        });
+    }
+    Ok(header)
+}
+
+impl SignatureCheck {
+    /// Accept images signed with `key`, whose version is at least
+    /// `minimum_version`.
+    pub fn new(key: [u8; KEY_LEN], minimum_version: u32) -> SignatureCheck {
+        SignatureCheck {
+            key,
+            minimum_version,
+            accepted: None,
+            refused: None,
+        }
+    }
+
+    /// Refuse anything older than `version` from now on. The minimum is
+    /// never lowered.
+    pub fn raise_minimum(&mut self, version: u32) {
+        self.minimum_version = self.minimum_version.max(version);
+    }
+
+    /// The oldest version accepted.
+    pub fn minimum_version(&self) -> u32 {
+        self.minimum_version
+    }
+
+    /// The header of the last image accepted - after `Updater::select`, the
+    /// image which is about to be booted.
+    pub fn accepted(&self) -> Option<Header> {
+        self.accepted
+    }
+
+    /// Why the last image was refused, if it was.
+    pub fn refused(&self) -> Option<Error<()>> {
+        self.refused
+    }
+}
+
+impl<F> Verifier<F> for SignatureCheck
+where
+    F: NorFlash,
+{
+    fn verify(&mut self, flash: &mut F, start: u32, len: u32) -> Result<bool, F::Error> {
+        self.refused = None;
+        match verify(flash, start, len, &self.key, self.minimum_version) {
+            Ok(header) => {
+                self.accepted = Some(header);
+                Ok(true)
+            }
+            Err(Error::Flash(e)) => Err(e),
+            Err(e) => {
+                self.refused = Some(without_flash(e));
+                Ok(false)
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Drop the flash error type, for an error which isn't `Error::Flash`.
+fn without_flash<E>(error: Error<E>) -> Error<()> {
+    match error {
+        Error::Flash(_) => Error::Flash(()),
+        Error::NoHeader => Error::NoHeader,
+        Error::WrongAddress => Error::WrongAddress,
+        Error::BadHash => Error::BadHash,
+        Error::BadSignature => Error::BadSignature,
+        Error::TooOld { version, minimum } => Error::TooOld { version, minimum },
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,207 @@
+//! A LaunchPad simulator in SRAM (or on a PC).
+//!
+//! `SimBoard` implements `common::launchpad::LaunchpadBoard`, so application
+//! code written against that trait can be exercised without a board. The
+//! test presses the buttons and feeds the console with `press` and
+//! `type_input`, and checks what the code did with `led` and `output`.
+//! `delay_ms` doesn't wait - it just adds to `elapsed_ms`. The host crate
+//! builds it, so these tests run with `make test` (see
+//! `host/tests/sim_board.rs`).
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use common::launchpad::{Button, LaunchpadBoard, Led};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A simulated LaunchPad, with the Stellaris LaunchPad's BoosterPack
+/// headers. Console output past `OUTPUT_LEN` bytes is dropped.
+pub struct SimBoard {
+    leds: [bool; 3],
+    buttons: [bool; 2],
+    output: [u8; OUTPUT_LEN],
+    output_len: usize,
+    dropped: usize,
+    input: [u8; INPUT_LEN],
+    input_start: usize,
+    input_len: usize,
+    elapsed_ms: u64,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How much console output is kept.
+pub const OUTPUT_LEN: usize = 512;
+
+/// How much console input can be queued.
+pub const INPUT_LEN: usize = 64;
+
+/// The system clock we pretend to have - the same as `board::Board`.
+pub const SYSCLK_HZ: u32 = 66_666_667;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Default for SimBoard {
+    fn default() -> SimBoard {
+        SimBoard::new()
+    }
+}
+
+impl SimBoard {
+    /// Make a new board, with the LEDs off, no buttons pressed and nothing
+    /// on the console.
+    pub const fn new() -> SimBoard {
+        SimBoard {
+            leds: [false; 3],
+            buttons: [false; 2],
+            output: [0; OUTPUT_LEN],
+            output_len: 0,
+            dropped: 0,
+            input: [0; INPUT_LEN],
+            input_start: 0,
+            input_len: 0,
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Is the LED on?
+    pub fn led(&self, led: Led) -> bool {
+        self.leds[led_index(led)]
+    }
+
+    /// Press or release a button.
+    pub fn press(&mut self, button: Button, pressed: bool) {
+        self.buttons[button_index(button)] = pressed;
+    }
+
+    /// Queue bytes for `console_read`. Returns how many fitted.
+    pub fn type_input(&mut self, bytes: &[u8]) -> usize {
+        let space = INPUT_LEN - self.input_len;
+        let count = bytes.len().min(space);
+        for &byte in &bytes[..count] {
+            let end = (self.input_start + self.input_len) % INPUT_LEN;
+            self.input[end] = byte;
+            self.input_len += 1;
+        }
+        count
+    }
+
+    /// Everything written to the console (up to `OUTPUT_LEN` bytes).
+    pub fn output(&self) -> &[u8] {
+        &self.output[..self.output_len]
+    }
+
+    /// How many bytes of console output didn't fit.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Forget the console output so far.
+    pub fn clear_output(&mut self) {
+        self.output_len = 0;
+        self.dropped = 0;
+    }
+
+    /// How long `delay_ms` has waited in total.
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+}
+
+impl LaunchpadBoard for SimBoard {
+    fn name(&self) -> &'static str {
+        "sim"
+    }
+
+    fn set_led(&mut self, led: Led, on: bool) {
+        self.leds[led_index(led)] = on;
+    }
+
+    fn is_pressed(&self, button: Button) -> bool {
+        self.buttons[button_index(button)]
+    }
+
+    fn console_write(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(OUTPUT_LEN - self.output_len);
+        self.output[self.output_len..self.output_len + count].copy_from_slice(&bytes[..count]);
+        self.output_len += count;
+        self.dropped += bytes.len() - count;
+    }
+
+    fn console_read(&mut self) -> Option<u8> {
+        if self.input_len == 0 {
+            return None;
+        }
+        let byte = self.input[self.input_start];
+        self.input_start = (self.input_start + 1) % INPUT_LEN;
+        self.input_len -= 1;
+        Some(byte)
+    }
+
+    fn sysclk_hz(&self) -> u32 {
+        SYSCLK_HZ
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.elapsed_ms += u64::from(ms);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn led_index(led: Led) -> usize {
+    match led {
+        Led::Red => 0,
+        Led::Blue => 1,
+        Led::Green => 2,
+    }
+}
+
+fn button_index(button: Button) -> usize {
+    match button {
+        Button::One => 0,
+        Button::Two => 1,
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
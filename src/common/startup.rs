@@ -10,8 +10,16 @@
 use cortex_m;
 use cortex_m_rt::{entry, exception, ExceptionFrame};
 
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+use core::arch::global_asm;
+use core::fmt::Write;
+
 use board;
-use cpu::{gpio::GpioExt, serial, sysctl::SysctlExt, time::Bps};
+#[cfg(feature = "alloc")]
+use common::heap;
+#[cfg(feature = "image-checksum")]
+use common::image_checksum::{self, Integrity};
+use common::memory;
 
 extern "Rust" {
     fn stellaris_main(board: board::Board);
@@ -59,10 +67,32 @@ extern "Rust" {
 /// This routine is specified at the reset vector in the ISR vector table.
 ///
 /// Copies global .data init from flash to SRAM and then
-/// zeros the bss segment.
+/// zeros the bss segment. Paints the unused stack so its high water mark
+/// can be measured, and sets up any stack overflow protection. With the
+/// `ram-vectors` feature, also moves the vector table into SRAM, and with
+/// the `alloc` feature, gives the free SRAM to the heap allocator. With the
+/// `image-checksum` feature, checks the flash image before the application
+/// starts.
 #[entry]
 unsafe fn call_main() -> ! {
+    memory::paint_stack();
+    #[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+    memory::enable_fault_handlers();
+    #[cfg(feature = "stack-guard")]
+    memory::enable_stack_guard();
+    #[cfg(feature = "ram-vectors")]
+    board::vectors::relocate();
+    #[cfg(feature = "alloc")]
+    heap::init();
     let board = board::Board::new();
+    // After the PLL is running, so the check is quick
+    #[cfg(feature = "image-checksum")]
+    {
+        let integrity = image_checksum::check();
+        if cfg!(feature = "image-checksum-halt") && matches!(integrity, Integrity::Corrupt { .. }) {
+            board::blink_code(image_checksum::BLINK_CODE);
+        }
+    }
     stellaris_main(board);
     loop {
         cortex_m::asm::wfi();
@@ -84,21 +114,7 @@ unsafe fn HardFault(sf: &ExceptionFrame) -> ! {
     // Need ITM support for this to work
     // iprintln!("EXCEPTION {:?} @ PC=0x{:08x}", Exception::active(), sf.pc);
 
-    let peripherals = tm4c123x_hal::Peripherals::steal();
-    let sysctl = peripherals.SYSCTL.constrain();
-    let mut pins = peripherals.GPIO_PORTA.split(&sysctl.power_control);
-    use core::fmt::Write;
-    let mut uart = serial::Serial::uart0(
-        peripherals.UART0,
-        pins.pa1.into_af_push_pull(&mut pins.control),
-        pins.pa0.into_af_push_pull(&mut pins.control),
-        (),
-        (),
-        Bps(115200),
-        serial::NewlineMode::SwapLFtoCRLF,
-        board::clocks(),
-        &sysctl.power_control,
-    );
+    let mut uart = board::emergency_console();
     writeln!(uart, "SF: {:?}", sf).unwrap();
 
     cortex_m::asm::bkpt();
@@ -122,6 +138,7 @@ fn NonMaskableInt() {
 /// determines this fault, for both instruction and data memory transactions.
 /// This fault is always used to abort instruction accesses to Execute Never
 /// (XN) memory regions.
+#[cfg(not(any(feature = "stack-guard", feature = "stack-at-bottom")))]
 #[exception]
 fn MemoryManagement() {
     board::panic();
@@ -130,11 +147,48 @@ fn MemoryManagement() {
 /// A BusFault is an exception that occurs because of a memory related fault
 /// for an instruction or data memory transaction. This might be from an error
 /// detected on a bus in the memory system.
+#[cfg(not(any(feature = "stack-guard", feature = "stack-at-bottom")))]
 #[exception]
 fn BusFault() {
     board::panic();
 }
 
+// With stack overflow protection, the MemManage and BusFault handlers are
+// likely to be entered with the stack pointer at (or past) the bottom of the
+// stack, so they can't push anything. Put the stack pointer back at the top
+// of the stack (we're never returning) before calling any Rust code.
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+global_asm!(
+    ".section .text.MemoryManagement,\"ax\",%progbits",
+    ".global MemoryManagement",
+    ".type MemoryManagement,%function",
+    ".thumb_func",
+    "MemoryManagement:",
+    "    ldr r0, =_stack_start",
+    "    msr msp, r0",
+    "    b __memory_fault",
+    ".ltorg",
+    ".section .text.BusFault,\"ax\",%progbits",
+    ".global BusFault",
+    ".type BusFault,%function",
+    ".thumb_func",
+    "BusFault:",
+    "    ldr r0, =_stack_start",
+    "    msr msp, r0",
+    "    b __memory_fault",
+    ".ltorg",
+);
+
+/// Reports a MemManage fault or BusFault (most likely a stack overflow), then
+/// panics. Called from the `MemoryManagement` and `BusFault` handlers above.
+#[cfg(any(feature = "stack-guard", feature = "stack-at-bottom"))]
+#[no_mangle]
+unsafe extern "C" fn __memory_fault() -> ! {
+    let mut uart = board::emergency_console();
+    let _ = memory::describe_fault(&mut uart);
+    board::panic();
+}
+
 /// A UsageFault is an exception that occurs because of a fault related to instruction execution. This includes:
 /// * an undefined instruction
 /// * an illegal unaligned access
@@ -178,9 +232,23 @@ fn SysTick() {
     // Nothing
 }
 
-/// A place-holder ISR used when we have nothing better to use.
+/// A place-holder ISR used when we have nothing better to use. Reports
+/// which interrupt fired without a handler, then panics.
 #[exception]
-unsafe fn DefaultHandler(_irq_number: i16) -> ! {
+unsafe fn DefaultHandler(irq_number: i16) -> ! {
+    let mut uart = board::emergency_console();
+    match board::Interrupt::from_irqn(irq_number) {
+        Some(interrupt) => {
+            let _ = writeln!(
+                uart,
+                "Unhandled interrupt {:?} (IRQ {})",
+                interrupt, irq_number
+            );
+        }
+        None => {
+            let _ = writeln!(uart, "Unhandled exception (IRQ {})", irq_number);
+        }
+    }
     board::panic();
 }
 
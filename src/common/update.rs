@@ -0,0 +1,630 @@
+//! Dual-slot firmware updates.
+//!
+//! The flash is split into a boot loader, a couple of metadata pages and two
+//! application slots (see `LAUNCHPAD`). Applications run where they are
+//! stored, so each one is linked for a particular slot (with the `slot-a`
+//! or `slot-b` feature). A new image is always written into the slot which
+//! isn't holding the confirmed image, so there's always something to go
+//! back to.
+//!
+//! The metadata is a single record in a `KvStore`, so changing it is atomic:
+//! an update is either selected or it isn't, whenever the power fails.
+//!
+//! 1. The boot loader receives an image (`begin`, `write`, `finish`), checks
+//!    its CRC, and selects it for a trial.
+//! 2. On each boot, `select` counts the trial boots. If the new image
+//!    hasn't confirmed itself after `max_boots` boots, it is abandoned and
+//!    the confirmed image is booted instead.
+//! 3. Once the new image is happy, it calls `confirm` (see
+//!    `board::update::confirm`), and it becomes the confirmed image.
+//!
+//! Everything here works with any `NorFlash`, so it can be tried out on a PC
+//! against `common::ram_flash::RamFlash` (`host/tests/update.rs` does):
+//!
+//! ```rust,ignore
+//! let mut updater = Updater::open(RamFlash::<0x4_0000>::new(), LAUNCHPAD)?;
+//! let slot = updater.begin(image.len() as u32, crc::crc32(&image))?;
+//! updater.write(0, &image)?;
+//! updater.finish()?;
+//! assert_eq!(updater.select(3)?, Some(slot));
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_storage::nor_flash::NorFlash;
+
+use common::crc::Crc32;
+use common::kvstore::{self, KvStore};
+use common::settings::{Reader, Writer};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// One of the two application slots.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Slot {
+    /// The first slot
+    A,
+    /// The second slot
+    B,
+}
+
+/// A range of flash.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Region {
+    /// The first address
+    pub start: u32,
+    /// The length in bytes
+    pub size: u32,
+}
+
+/// Where everything goes in flash.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Layout {
+    /// The application slots
+    pub slots: [Region; 2],
+    /// The pages holding the metadata
+    pub metadata: Region,
+    /// The SRAM, which an image's initial stack pointer must be inside
+    pub ram: Region,
+}
+
+/// A slot's image.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Image {
+    /// The length in bytes
+    pub len: u32,
+    /// The CRC-32 of those bytes
+    pub crc: u32,
+}
+
+/// A new image which hasn't confirmed itself yet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Trial {
+    /// Where the image is
+    pub slot: Slot,
+    /// How many times it has been booted
+    pub boots: u8,
+}
+
+/// What the metadata says.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct State {
+    /// The slot holding the last image to confirm itself
+    pub confirmed: Option<Slot>,
+    /// A new image on trial
+    pub trial: Option<Trial>,
+    /// The image in each slot
+    pub images: [Option<Image>; 2],
+}
+
+/// Manages the slots and the metadata.
+pub struct Updater<F, V = AcceptAll> {
+    flash: F,
+    layout: Layout,
+    state: State,
+    receiving: Option<Receiving>,
+    verifier: V,
+}
+
+/// Extra checks on an image, on top of its CRC and vector table (see
+/// `common::signed_image` for one which checks signatures).
+pub trait Verifier<F>
+where
+    F: NorFlash,
+{
+    /// Is the image of `len` bytes at `start` acceptable?
+    fn verify(&mut self, flash: &mut F, start: u32, len: u32) -> Result<bool, F::Error>;
+}
+
+/// A `Verifier` which accepts every image.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AcceptAll;
+
+/// Things that can go wrong with an update.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<E> {
+    /// The underlying flash failed
+    Flash(E),
+    /// The metadata store failed
+    Store(kvstore::Error<E>),
+    /// The layout doesn't fit the flash, or isn't page aligned
+    BadConfig,
+    /// The image is empty, or bigger than a slot
+    TooBig,
+    /// `write` or `finish` was called without `begin`
+    NotStarted,
+    /// The data didn't follow on from the last `write`, or went past the
+    /// end of the image
+    OutOfOrder,
+    /// The image in flash doesn't match the CRC given to `begin`
+    BadCrc,
+    /// The image doesn't start with a vector table for this slot
+    BadImage,
+    /// The `Verifier` didn't accept the image
+    Rejected,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The Launchpad's flash layout - `build.rs` has the same numbers.
+///
+/// | Region      | Start       | Size    |
+/// |-------------|-------------|---------|
+/// | Boot loader | 0x0000_0000 | 46 KiB  |
+/// | Metadata    | 0x0000_B800 | 2 KiB   |
+/// | Slot A      | 0x0000_C000 | 104 KiB |
+/// | Slot B      | 0x0002_6000 | 104 KiB |
+pub const LAUNCHPAD: Layout = Layout {
+    slots: [
+        Region {
+            start: 0x0000_C000,
+            size: 0x0001_A000,
+        },
+        Region {
+            start: 0x0002_6000,
+            size: 0x0001_A000,
+        },
+    ],
+    metadata: Region {
+        start: 0x0000_B800,
+        size: 0x0000_0800,
+    },
+    ram: Region {
+        start: 0x2000_0000,
+        size: 0x0000_8000,
+    },
+};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// An image being received.
+#[derive(Debug, Clone, Copy)]
+struct Receiving {
+    slot: Slot,
+    image: Image,
+    /// How much has been written so far
+    next: u32,
+    /// Where the last `write` started, so it can be repeated
+    last: u32,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// The `KvStore` key holding the `State`
+const STATE_KEY: u16 = 1;
+/// The length of the encoded `State`
+const STATE_LEN: usize = 20;
+/// How `Option<Slot>` is stored when it's `None`
+const NO_SLOT: u8 = 0xFF;
+/// The biggest `NorFlash::WRITE_SIZE` we can pad the end of an image to
+const MAX_WRITE_SIZE: usize = 16;
+/// How many bytes to read at a time when checking an image
+const CHUNK_SIZE: usize = 64;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Slot {
+    /// The other slot.
+    pub fn other(self) -> Slot {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+
+    /// 0 for slot A, 1 for slot B.
+    pub fn index(self) -> usize {
+        match self {
+            Slot::A => 0,
+            Slot::B => 1,
+        }
+    }
+
+    /// The slot with the given index.
+    pub fn from_index(index: usize) -> Option<Slot> {
+        match index {
+            0 => Some(Slot::A),
+            1 => Some(Slot::B),
+            _ => None,
+        }
+    }
+}
+
+impl Region {
+    /// Is the address inside this region?
+    pub fn contains(&self, address: u32) -> bool {
+        address >= self.start && address - self.start < self.size
+    }
+}
+
+impl Layout {
+    /// The region for a slot.
+    pub fn slot(&self, slot: Slot) -> Region {
+        self.slots[slot.index()]
+    }
+
+    /// Which slot holds the address, if any.
+    pub fn slot_containing(&self, address: u32) -> Option<Slot> {
+        if self.slots[0].contains(address) {
+            Some(Slot::A)
+        } else if self.slots[1].contains(address) {
+            Some(Slot::B)
+        } else {
+            None
+        }
+    }
+}
+
+impl<F> Updater<F, AcceptAll>
+where
+    F: NorFlash,
+{
+    /// Read the metadata. If there isn't any, no slot holds an image.
+    pub fn open(flash: F, layout: Layout) -> Result<Updater<F>, Error<F::Error>> {
+        Updater::open_with(flash, layout, AcceptAll)
+    }
+}
+
+impl<F, V> Updater<F, V>
+where
+    F: NorFlash,
+    V: Verifier<F>,
+{
+    /// Like `open`, but every image must also be accepted by `verifier` -
+    /// both when it's received, and each time it's booted.
+    pub fn open_with(
+        flash: F,
+        layout: Layout,
+        verifier: V,
+    ) -> Result<Updater<F, V>, Error<F::Error>> {
+        let page_size = F::ERASE_SIZE as u32;
+        let mut regions = layout.slots.iter().chain(Some(&layout.metadata));
+        if regions.any(|r| {
+            r.start % page_size != 0
+                || r.size % page_size != 0
+                || r.start as usize + r.size as usize > flash.capacity()
+        }) || F::WRITE_SIZE > MAX_WRITE_SIZE
+        {
+            return Err(Error::BadConfig);
+        }
+        let mut updater = Updater {
+            flash,
+            layout,
+            state: State::default(),
+            receiving: None,
+            verifier,
+        };
+        let mut buffer = [0u8; STATE_LEN];
+        if let Some(len) = updater
+            .store()?
+            .get(STATE_KEY, &mut buffer)
+            .map_err(Error::Store)?
+        {
+            updater.state = decode(&buffer[..len]).unwrap_or_default();
+        }
+        Ok(updater)
+    }
+
+    /// What the metadata says.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Where everything is.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The `Verifier` checking our images.
+    pub fn verifier(&self) -> &V {
+        &self.verifier
+    }
+
+    /// The `Verifier`, to change how it checks images.
+    pub fn verifier_mut(&mut self) -> &mut V {
+        &mut self.verifier
+    }
+
+    /// Decide which slot to boot, counting the boot if it's a trial. A trial
+    /// image which has been booted `max_boots` times without confirming
+    /// itself (or which has been damaged) is abandoned. Returns `None` if
+    /// there's nothing worth booting.
+    pub fn select(&mut self, max_boots: u8) -> Result<Option<Slot>, Error<F::Error>> {
+        if let Some(trial) = self.state.trial {
+            let mut state = self.state;
+            if trial.boots < max_boots && self.is_valid(trial.slot)? {
+                state.trial = Some(Trial {
+                    slot: trial.slot,
+                    boots: trial.boots + 1,
+                });
+                self.save(state)?;
+                return Ok(Some(trial.slot));
+            }
+            // Roll back
+            state.trial = None;
+            self.save(state)?;
+        }
+        let confirmed = self.state.confirmed;
+        match confirmed {
+            Some(slot) if self.is_valid(slot)? => Ok(Some(slot)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Make a trial image the confirmed one, once it's running happily.
+    /// Does nothing if `slot` isn't on trial.
+    pub fn confirm(&mut self, slot: Slot) -> Result<(), Error<F::Error>> {
+        match self.state.trial {
+            Some(trial) if trial.slot == slot => {
+                let mut state = self.state;
+                state.confirmed = Some(slot);
+                state.trial = None;
+                self.save(state)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The slot the next update will go into - whichever one isn't holding
+    /// the confirmed image.
+    pub fn target(&self) -> Slot {
+        self.state.confirmed.map_or(Slot::A, Slot::other)
+    }
+
+    /// Does the slot hold an image, with the right CRC and a plausible
+    /// vector table?
+    pub fn is_valid(&mut self, slot: Slot) -> Result<bool, Error<F::Error>> {
+        let image = match self.state.images[slot.index()] {
+            Some(image) => image,
+            None => return Ok(false),
+        };
+        if image.len > self.layout.slot(slot).size {
+            return Ok(false);
+        }
+        match self.check(slot, image) {
+            Ok(()) => Ok(true),
+            Err(Error::BadCrc) | Err(Error::BadImage) | Err(Error::Rejected) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Start receiving an image of `len` bytes into the `target` slot. Any
+    /// image already there is forgotten and erased.
+    pub fn begin(&mut self, len: u32, crc: u32) -> Result<Slot, Error<F::Error>> {
+        let slot = self.target();
+        let region = self.layout.slot(slot);
+        if len == 0 || len > region.size {
+            return Err(Error::TooBig);
+        }
+        self.receiving = None;
+        let mut state = self.state;
+        if state.trial.is_some_and(|t| t.slot == slot) {
+            state.trial = None;
+        }
+        state.images[slot.index()] = None;
+        if state != self.state {
+            self.save(state)?;
+        }
+        let page_size = F::ERASE_SIZE as u32;
+        let end = region.start + (len + page_size - 1) / page_size * page_size;
+        self.flash.erase(region.start, end).map_err(Error::Flash)?;
+        self.receiving = Some(Receiving {
+            slot,
+            image: Image { len, crc },
+            next: 0,
+            last: 0,
+        });
+        Ok(slot)
+    }
+
+    /// Write the next piece of the image, at `offset` bytes from its start.
+    /// Every piece but the last must be a whole number of words. Writing the
+    /// previous piece again is allowed (and does nothing), in case the
+    /// sender didn't hear that it worked. Returns how much has been written
+    /// so far.
+    pub fn write(&mut self, offset: u32, data: &[u8]) -> Result<u32, Error<F::Error>> {
+        let mut receiving = self.receiving.ok_or(Error::NotStarted)?;
+        let end = offset
+            .checked_add(data.len() as u32)
+            .ok_or(Error::OutOfOrder)?;
+        if offset == receiving.last && end == receiving.next && offset != end {
+            return Ok(receiving.next);
+        }
+        if offset != receiving.next || end > receiving.image.len {
+            return Err(Error::OutOfOrder);
+        }
+        let address = self.layout.slot(receiving.slot).start + offset;
+        let word_size = F::WRITE_SIZE;
+        let whole = data.len() - data.len() % word_size;
+        if whole != data.len() && end != receiving.image.len {
+            return Err(Error::OutOfOrder);
+        }
+        self.flash
+            .write(address, &data[..whole])
+            .map_err(Error::Flash)?;
+        if whole != data.len() {
+            // Pad the end of the image out to a whole word
+            let mut last = [0xFFu8; MAX_WRITE_SIZE];
+            let tail = &data[whole..];
+            last[..tail.len()].copy_from_slice(tail);
+            self.flash
+                .write(address + whole as u32, &last[..word_size])
+                .map_err(Error::Flash)?;
+        }
+        receiving.last = offset;
+        receiving.next = end;
+        self.receiving = Some(receiving);
+        Ok(end)
+    }
+
+    /// Check the whole image arrived intact, and select it for a trial.
+    pub fn finish(&mut self) -> Result<Slot, Error<F::Error>> {
+        let receiving = self.receiving.ok_or(Error::NotStarted)?;
+        if receiving.next != receiving.image.len {
+            return Err(Error::OutOfOrder);
+        }
+        self.check(receiving.slot, receiving.image)?;
+        self.receiving = None;
+        let mut state = self.state;
+        state.images[receiving.slot.index()] = Some(receiving.image);
+        state.trial = Some(Trial {
+            slot: receiving.slot,
+            boots: 0,
+        });
+        self.save(state)?;
+        Ok(receiving.slot)
+    }
+
+    /// Give back the flash.
+    pub fn free(self) -> F {
+        self.flash
+    }
+
+    /// Check the image in a slot against its CRC, check the vector table
+    /// starts in SRAM and resets into the slot, and ask the `Verifier`.
+    fn check(&mut self, slot: Slot, image: Image) -> Result<(), Error<F::Error>> {
+        let region = self.layout.slot(slot);
+        let mut crc = Crc32::new();
+        let mut buffer = [0u8; CHUNK_SIZE];
+        let mut offset = 0;
+        while offset < image.len {
+            let len = CHUNK_SIZE.min((image.len - offset) as usize);
+            self.flash
+                .read(region.start + offset, &mut buffer[..len])
+                .map_err(Error::Flash)?;
+            crc.update(&buffer[..len]);
+            offset += len as u32;
+        }
+        if crc.finish() != image.crc {
+            return Err(Error::BadCrc);
+        }
+        if image.len < 8 {
+            return Err(Error::BadImage);
+        }
+        self.flash
+            .read(region.start, &mut buffer[..8])
+            .map_err(Error::Flash)?;
+        let stack = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+        let reset = u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+        let stack_ok =
+            stack > self.layout.ram.start && stack - self.layout.ram.start <= self.layout.ram.size;
+        if !stack_ok || reset & 1 == 0 || !region.contains(reset & !1) {
+            return Err(Error::BadImage);
+        }
+        match self
+            .verifier
+            .verify(&mut self.flash, region.start, image.len)
+        {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(Error::Rejected),
+            Err(e) => Err(Error::Flash(e)),
+        }
+    }
+
+    /// Write out a new state, and keep it if that worked.
+    fn save(&mut self, state: State) -> Result<(), Error<F::Error>> {
+        let mut buffer = [0u8; STATE_LEN];
+        encode(&state, &mut buffer);
+        self.store()?
+            .set(STATE_KEY, &buffer)
+            .map_err(Error::Store)?;
+        self.state = state;
+        Ok(())
+    }
+
+    fn store(&mut self) -> Result<KvStore<&mut F>, Error<F::Error>> {
+        let page_size = F::ERASE_SIZE as u32;
+        let metadata = self.layout.metadata;
+        KvStore::open(&mut self.flash, metadata.start, metadata.size / page_size)
+            .map_err(Error::Store)
+    }
+}
+
+impl<F> Verifier<F> for AcceptAll
+where
+    F: NorFlash,
+{
+    fn verify(&mut self, _flash: &mut F, _start: u32, _len: u32) -> Result<bool, F::Error> {
+        Ok(true)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn encode(state: &State, buffer: &mut [u8; STATE_LEN]) {
+    let mut w = Writer::new(buffer);
+    w.u8(state.confirmed.map_or(NO_SLOT, |s| s.index() as u8));
+    w.u8(state.trial.map_or(NO_SLOT, |t| t.slot.index() as u8));
+    w.u8(state.trial.map_or(0, |t| t.boots));
+    w.u8(0);
+    for image in &state.images {
+        let image = image.unwrap_or(Image { len: 0, crc: 0 });
+        w.u32(image.len);
+        w.u32(image.crc);
+    }
+}
+
+fn decode(buffer: &[u8]) -> Option<State> {
+    let mut r = Reader::new(buffer);
+    let confirmed = slot_or_none(r.u8()?)?;
+    let trial_slot = slot_or_none(r.u8()?)?;
+    let boots = r.u8()?;
+    let _reserved = r.u8()?;
+    let mut images = [None; 2];
+    for image in images.iter_mut() {
+        let len = r.u32()?;
+        let crc = r.u32()?;
+        if len != 0 {
+            *image = Some(Image { len, crc });
+        }
+    }
+    Some(State {
+        confirmed,
+        trial: trial_slot.map(|slot| Trial { slot, boots }),
+        images,
+    })
+}
+
+/// Decode an `Option<Slot>`. The outer `Option` is `None` if the byte is
+/// nonsense.
+fn slot_or_none(byte: u8) -> Option<Option<Slot>> {
+    if byte == NO_SLOT {
+        Some(None)
+    } else {
+        Slot::from_index(usize::from(byte)).map(Some)
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
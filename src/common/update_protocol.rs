@@ -0,0 +1,391 @@
+//! The serial protocol the boot loader uses to receive an image.
+//!
+//! Everything is sent in frames:
+//!
+//! ```text
+//! 0xA5 | kind (1) | length (2) | payload (length) | CRC-32 (4)
+//! ```
+//!
+//! All numbers are little-endian, and the CRC covers everything after the
+//! `0xA5`. The host sends a command and waits for the reply before sending
+//! the next one. If a reply doesn't arrive, or says the command frame was
+//! damaged, the host sends the command again.
+//!
+//! | Command  | Payload                 | Reply                  |
+//! |----------|-------------------------|------------------------|
+//! | `HELLO`  | -                       | `INFO`                 |
+//! | `BEGIN`  | length (4), CRC-32 (4)  | `ACK` with the slot    |
+//! | `DATA`   | offset (4), bytes       | `ACK` with the total   |
+//! | `FINISH` | -                       | `ACK` with the slot    |
+//! | `BOOT`   | -                       | `ACK`, then a reset    |
+//!
+//! Anything that goes wrong gets a `NAK` holding one of the `NAK_*` codes.
+//! `tools/src/bin/lp-update.rs` is the other end of this protocol.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_storage::nor_flash::NorFlash;
+
+use common::crc;
+use common::update::{AcceptAll, Error, Slot, Updater, Verifier};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Picks frames out of a stream of bytes.
+pub struct Decoder {
+    buffer: [u8; MAX_FRAME],
+    received: usize,
+    in_frame: bool,
+}
+
+/// A frame which arrived intact.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// What sort of frame it is
+    pub kind: u8,
+    /// The payload
+    pub payload: &'a [u8],
+}
+
+/// A frame which didn't arrive intact.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameError {
+    /// The length was more than `MAX_PAYLOAD`
+    BadLength,
+    /// The CRC didn't match
+    BadCrc,
+}
+
+/// Handles commands from the host.
+pub struct Server<F, V = AcceptAll> {
+    updater: Updater<F, V>,
+    decoder: Decoder,
+}
+
+/// What to do after `Server::receive`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Event {
+    /// Nothing yet - keep receiving
+    Nothing,
+    /// Send this many bytes of the reply buffer
+    Reply(usize),
+    /// Send this many bytes of the reply buffer, then reset
+    Boot(usize),
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// Starts every frame.
+pub const START: u8 = 0xA5;
+/// The version of this protocol, sent in `INFO`.
+pub const VERSION: u8 = 1;
+/// The most image bytes in a `DATA` frame.
+pub const MAX_CHUNK: usize = 256;
+/// The longest payload.
+pub const MAX_PAYLOAD: usize = 4 + MAX_CHUNK;
+/// The longest frame, including the start byte.
+pub const MAX_FRAME: usize = 1 + HEADER_LEN + MAX_PAYLOAD + CRC_LEN;
+
+/// Asks for `INFO`.
+pub const HELLO: u8 = 0x01;
+/// Starts an update.
+pub const BEGIN: u8 = 0x02;
+/// Carries part of the image.
+pub const DATA: u8 = 0x03;
+/// Ends an update.
+pub const FINISH: u8 = 0x04;
+/// Leaves the boot loader.
+pub const BOOT: u8 = 0x05;
+/// The command worked.
+pub const ACK: u8 = 0x80;
+/// The command failed.
+pub const NAK: u8 = 0x81;
+/// Protocol version (1), target slot (1), confirmed slot (1, or `0xFF`),
+/// reserved (1), target slot address (4), target slot size (4), `MAX_CHUNK`
+/// (2).
+pub const INFO: u8 = 0x82;
+
+/// The command frame was damaged - send it again
+pub const NAK_BAD_FRAME: u8 = 1;
+/// The command isn't one we know
+pub const NAK_UNKNOWN: u8 = 2;
+/// The payload is the wrong length
+pub const NAK_BAD_PAYLOAD: u8 = 3;
+/// See `update::Error::TooBig`
+pub const NAK_TOO_BIG: u8 = 4;
+/// See `update::Error::NotStarted`
+pub const NAK_NOT_STARTED: u8 = 5;
+/// See `update::Error::OutOfOrder`
+pub const NAK_OUT_OF_ORDER: u8 = 6;
+/// See `update::Error::BadCrc`
+pub const NAK_BAD_CRC: u8 = 7;
+/// See `update::Error::BadImage`
+pub const NAK_BAD_IMAGE: u8 = 8;
+/// The flash (or the metadata in it) failed
+pub const NAK_FLASH: u8 = 9;
+/// See `update::Error::Rejected`
+pub const NAK_REJECTED: u8 = 10;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// A short reply payload.
+struct Payload {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Kind and length
+const HEADER_LEN: usize = 3;
+const CRC_LEN: usize = 4;
+/// How `INFO` says there's no confirmed slot
+const NO_SLOT: u8 = 0xFF;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Build a frame in `buffer`, returning its length. Panics if the payload is
+/// longer than `MAX_PAYLOAD`.
+pub fn encode(kind: u8, payload: &[u8], buffer: &mut [u8; MAX_FRAME]) -> usize {
+    assert!(payload.len() <= MAX_PAYLOAD);
+    let crc_at = 1 + HEADER_LEN + payload.len();
+    buffer[0] = START;
+    buffer[1] = kind;
+    buffer[2..4].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+    buffer[4..crc_at].copy_from_slice(payload);
+    let crc = crc::crc32(&buffer[1..crc_at]);
+    buffer[crc_at..crc_at + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+    crc_at + CRC_LEN
+}
+
+impl Decoder {
+    /// Wait for the start of a frame.
+    pub const fn new() -> Decoder {
+        Decoder {
+            buffer: [0; MAX_FRAME],
+            received: 0,
+            in_frame: false,
+        }
+    }
+
+    /// Take the next byte. Returns something when a frame ends. Bytes
+    /// between frames are ignored.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Frame<'_>, FrameError>> {
+        if !self.in_frame {
+            if byte == START {
+                self.in_frame = true;
+                self.received = 0;
+            }
+            return None;
+        }
+        self.buffer[self.received] = byte;
+        self.received += 1;
+        if self.received < HEADER_LEN {
+            return None;
+        }
+        let len = usize::from(u16::from_le_bytes([self.buffer[1], self.buffer[2]]));
+        if len > MAX_PAYLOAD {
+            self.in_frame = false;
+            return Some(Err(FrameError::BadLength));
+        }
+        let crc_at = HEADER_LEN + len;
+        if self.received < crc_at + CRC_LEN {
+            return None;
+        }
+        self.in_frame = false;
+        let crc = u32::from_le_bytes([
+            self.buffer[crc_at],
+            self.buffer[crc_at + 1],
+            self.buffer[crc_at + 2],
+            self.buffer[crc_at + 3],
+        ]);
+        if crc != crc::crc32(&self.buffer[..crc_at]) {
+            return Some(Err(FrameError::BadCrc));
+        }
+        Some(Ok(Frame {
+            kind: self.buffer[0],
+            payload: &self.buffer[HEADER_LEN..crc_at],
+        }))
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+impl<F, V> Server<F, V>
+where
+    F: NorFlash,
+    V: Verifier<F>,
+{
+    /// Serve updates into the updater's slots.
+    pub fn new(updater: Updater<F, V>) -> Server<F, V> {
+        Server {
+            updater,
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Take the next byte from the host. When a command is complete, it's
+    /// carried out and the reply is built in `reply`.
+    pub fn receive(&mut self, byte: u8, reply: &mut [u8; MAX_FRAME]) -> Event {
+        let (kind, payload, boot) = match self.decoder.push(byte) {
+            None => return Event::Nothing,
+            Some(Err(_)) => (NAK, Payload::nak(NAK_BAD_FRAME), false),
+            Some(Ok(frame)) => {
+                let boot = frame.kind == BOOT;
+                match handle(&mut self.updater, &frame) {
+                    Ok(payload) => (if frame.kind == HELLO { INFO } else { ACK }, payload, boot),
+                    Err(code) => (NAK, Payload::nak(code), false),
+                }
+            }
+        };
+        let len = encode(kind, payload.as_slice(), reply);
+        if boot {
+            Event::Boot(len)
+        } else {
+            Event::Reply(len)
+        }
+    }
+
+    /// Give back the updater.
+    pub fn free(self) -> Updater<F, V> {
+        self.updater
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Payload {
+    fn new() -> Payload {
+        Payload {
+            bytes: [0; 16],
+            len: 0,
+        }
+    }
+
+    fn nak(code: u8) -> Payload {
+        let mut payload = Payload::new();
+        payload.push(&[code]);
+        payload
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.bytes[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Carry out a command, returning the reply payload or a `NAK_*` code.
+fn handle<F, V>(updater: &mut Updater<F, V>, frame: &Frame) -> Result<Payload, u8>
+where
+    F: NorFlash,
+    V: Verifier<F>,
+{
+    let mut reply = Payload::new();
+    let payload = frame.payload;
+    match frame.kind {
+        HELLO => {
+            let target = updater.target();
+            let region = updater.layout().slot(target);
+            let confirmed = updater
+                .state()
+                .confirmed
+                .map_or(NO_SLOT, |s| s.index() as u8);
+            reply.push(&[VERSION, target.index() as u8, confirmed, 0]);
+            reply.push(&region.start.to_le_bytes());
+            reply.push(&region.size.to_le_bytes());
+            reply.push(&(MAX_CHUNK as u16).to_le_bytes());
+        }
+        BEGIN => {
+            if payload.len() != 8 {
+                return Err(NAK_BAD_PAYLOAD);
+            }
+            let slot = updater
+                .begin(word(payload, 0), word(payload, 4))
+                .map_err(nak_code)?;
+            reply.push(&[slot_byte(slot)]);
+        }
+        DATA => {
+            if payload.len() < 4 || payload.len() > MAX_PAYLOAD {
+                return Err(NAK_BAD_PAYLOAD);
+            }
+            let total = updater
+                .write(word(payload, 0), &payload[4..])
+                .map_err(nak_code)?;
+            reply.push(&total.to_le_bytes());
+        }
+        FINISH => {
+            let slot = updater.finish().map_err(nak_code)?;
+            reply.push(&[slot_byte(slot)]);
+        }
+        BOOT => {}
+        _ => return Err(NAK_UNKNOWN),
+    }
+    Ok(reply)
+}
+
+fn nak_code<E>(error: Error<E>) -> u8 {
+    match error {
+        Error::TooBig => NAK_TOO_BIG,
+        Error::NotStarted => NAK_NOT_STARTED,
+        Error::OutOfOrder => NAK_OUT_OF_ORDER,
+        Error::BadCrc => NAK_BAD_CRC,
+        Error::BadImage => NAK_BAD_IMAGE,
+        Error::Rejected => NAK_REJECTED,
+        Error::Flash(_) | Error::Store(_) | Error::BadConfig => NAK_FLASH,
+    }
+}
+
+fn slot_byte(slot: Slot) -> u8 {
+    slot.index() as u8
+}
+
+fn word(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -3,8 +3,9 @@
 //! the older MSP430 Launchpad). TI cancelled the Stellaris/LM4F range not
 //! long after it came out and replaced it with Tiva-C/TM4C range. The [Tiva-C
 //! Launchpad TM4C123G-XL](http://www.ti.com/tool/ek-tm4c123gxl) is almost
-//! exactly the same as a Stellaris Launchpad and should be software
-//! compatible. The Ethernet-enabled [TM4C1294 Connected
+//! exactly the same as a Stellaris Launchpad and is software compatible -
+//! build with the `tm4c123g` feature to use the PWM and QEI modules only it
+//! has. The Ethernet-enabled [TM4C1294 Connected
 //! Launchpad](http://www.ti.com/tool/ek-tm4c1294xl) is not supported.
 //!
 //! It's very much a work in progress, but so far the UART, SysTick, Timer
@@ -22,9 +23,20 @@
 //
 // ****************************************************************************
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 extern crate cortex_m;
 extern crate cortex_m_rt;
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+#[cfg(feature = "signed-images")]
+extern crate ed25519_compact;
 extern crate embedded_hal;
+extern crate embedded_storage;
+#[cfg(feature = "alloc")]
+extern crate linked_list_allocator;
+#[cfg(feature = "signed-images")]
+extern crate sha2;
 pub extern crate tm4c123x_hal;
 extern crate volatile_register;
 
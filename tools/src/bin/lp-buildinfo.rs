@@ -0,0 +1,108 @@
+//! Says how a firmware image was built, from the record `build.rs` puts in
+//! it (see `common::build_info`).
+//!
+//! ```text
+//! lp-buildinfo <elf-or-bin-file>...
+//! ```
+//!
+//! Works on ELF files, on `.bin` files, and on dumps of a board's flash
+//! (e.g. from `lm4flash` or OpenOCD's `dump_image`).
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::{env, fs, process};
+
+use launchpad_tools::build_info;
+use launchpad_tools::elf::{self, Elf};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type Error = Box<dyn std::error::Error>;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <elf-or-bin-file>...", args[0]);
+        process::exit(1);
+    }
+
+    for path in &args[1..] {
+        if let Err(e) = show(path) {
+            eprintln!("Error: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn show(path: &str) -> Result<(), Error> {
+    let data = fs::read(path)?;
+    let (base, image) = match Elf::parse(&data) {
+        Ok(elf) => elf.flash_image()?,
+        Err(elf::Error::NotElf) => (0, data),
+        Err(e) => return Err(e.into()),
+    };
+    let info = build_info::find(&image).ok_or("no build-info record")?;
+    println!("{}:", path);
+    println!("  Record:          0x{:08x}", base as usize + info.offset);
+    println!("  Version:         {}", info.version);
+    println!("  Git:             {}", info.git_hash);
+    println!("  Built:           {}", utc(info.timestamp));
+    println!("  Profile:         {}", info.profile);
+    println!("  Target:          {}", info.target);
+    println!("  Features:        {}", info.features.join(", "));
+    println!("  Target features: {}", info.target_features.join(", "));
+    println!("  Board:           {}", info.board);
+    Ok(())
+}
+
+/// Format seconds since 1970 as a UTC date and time.
+fn utc(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    // Howard Hinnant's days_from_civil, backwards
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
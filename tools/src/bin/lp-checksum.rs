@@ -0,0 +1,115 @@
+//! Stamps the CRC-32 of a firmware image into it, for the `image-checksum`
+//! feature.
+//!
+//! ```text
+//! lp-checksum <elf-file>...
+//! ```
+//!
+//! Each ELF file is changed in place: the length and CRC of its flash image
+//! are written into the `__image_checksum` record (see
+//! `common::image_checksum`). Run this after building and before making a
+//! binary with `objcopy` or signing the image with `lp-sign`. Stamping an
+//! image twice does no harm. Any gaps between segments count as `0x00`,
+//! which is how `objcopy -O binary` fills them (see `Elf::flash_image`).
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::{env, fs, process};
+
+use launchpad_tools::elf::Elf;
+use launchpad_tools::update::crc32_parts;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type Error = Box<dyn std::error::Error>;
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const SYMBOL: &str = "__image_checksum";
+/// "CRC1"
+const MAGIC: u32 = 0x3143_5243;
+/// Magic number, length and CRC
+const RECORD_LEN: usize = 12;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <elf-file>...", args[0]);
+        process::exit(1);
+    }
+
+    for path in &args[1..] {
+        if let Err(e) = stamp(path) {
+            eprintln!("Error: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn stamp(path: &str) -> Result<(), Error> {
+    let mut data = fs::read(path)?;
+    let (file_offset, length, crc) = {
+        let elf = Elf::parse(&data)?;
+        let symbol = elf
+            .symbols()?
+            .into_iter()
+            .find(|s| s.name == SYMBOL)
+            .ok_or("no checksum record - was it built with the image-checksum feature?")?;
+        if symbol.size as usize != RECORD_LEN {
+            return Err(format!("{} is {} bytes, not {}", SYMBOL, symbol.size, RECORD_LEN).into());
+        }
+        let segment = elf
+            .segments()?
+            .into_iter()
+            .find(|s| {
+                s.virtual_address <= symbol.address
+                    && symbol.address as usize + RECORD_LEN
+                        <= s.virtual_address as usize + s.data.len()
+            })
+            .ok_or("the checksum record isn't in flash")?;
+        let (base, image) = elf.flash_image()?;
+        let physical = segment.physical_address + (symbol.address - segment.virtual_address);
+        let offset = (physical - base) as usize;
+        let crc = crc32_parts(&[&image[..offset], &image[offset + RECORD_LEN..]]);
+        let file_offset = segment.file_offset + (symbol.address - segment.virtual_address) as usize;
+        (file_offset, image.len() as u32, crc)
+    };
+
+    let record = &mut data[file_offset..file_offset + RECORD_LEN];
+    record[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+    record[4..8].copy_from_slice(&length.to_le_bytes());
+    record[8..12].copy_from_slice(&crc.to_le_bytes());
+    fs::write(path, &data)?;
+    println!("{}: {} bytes, CRC 0x{:08x}", path, length, crc);
+    Ok(())
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
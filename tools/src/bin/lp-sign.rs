@@ -0,0 +1,138 @@
+//! Signs application images for a boot loader built with the `signed`
+//! feature.
+//!
+//! ```text
+//! lp-sign keygen <secret-key-file> <public-key-file>
+//! lp-sign sign <secret-key-file> <version> <image> <output> [<load-address>]
+//! ```
+//!
+//! `keygen` makes a new Ed25519 key pair; build the boot loader with
+//! `LP_PUBLIC_KEY` set to the public key file, and keep the secret key
+//! somewhere safe.
+//!
+//! `sign` takes an ELF file (or a raw binary, with the address it's linked
+//! for) and writes a binary with a signed header on the end, ready for
+//! `lp-update`. The boot loader never starts an image with a lower version
+//! than the newest one to have confirmed itself.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::{env, process};
+
+use ed25519_compact::{KeyPair, SecretKey, Seed};
+
+use launchpad_tools::elf::Elf;
+use launchpad_tools::signed_image;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type Error = Box<dyn std::error::Error>;
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let result = match args.get(1).map(String::as_str) {
+        Some("keygen") if args.len() == 4 => keygen(&args[2], &args[3]),
+        Some("sign") if args.len() == 6 || args.len() == 7 => {
+            sign(&args[2], &args[3], &args[4], &args[5], args.get(6))
+        }
+        _ => {
+            eprintln!(
+                "Usage: {} keygen <secret-key-file> <public-key-file>",
+                args[0]
+            );
+            eprintln!(
+                "       {} sign <secret-key-file> <version> <image> <output> [<load-address>]",
+                args[0]
+            );
+            process::exit(1);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn keygen(secret_path: &str, public_path: &str) -> Result<(), Error> {
+    let mut seed = [0u8; Seed::BYTES];
+    File::open("/dev/urandom")?.read_exact(&mut seed)?;
+    let key_pair = KeyPair::from_seed(Seed::new(seed));
+    fs::write(secret_path, &key_pair.sk[..])?;
+    fs::write(public_path, &key_pair.pk[..])?;
+    println!("Wrote {} and {}", secret_path, public_path);
+    Ok(())
+}
+
+fn sign(
+    secret_path: &str,
+    version: &str,
+    image_path: &str,
+    output_path: &str,
+    load_address: Option<&String>,
+) -> Result<(), Error> {
+    let key = SecretKey::from_slice(&fs::read(secret_path)?)
+        .map_err(|_| format!("{} isn't a secret key from `keygen`", secret_path))?;
+    let version: u32 = version
+        .parse()
+        .map_err(|_| "the version must be a number")?;
+
+    let data = fs::read(image_path)?;
+    let (base, image) = if data.starts_with(ELF_MAGIC) {
+        Elf::parse(&data)?.flash_image()?
+    } else {
+        let address = load_address.ok_or("give the load address of a raw binary")?;
+        let address = u32::from_str_radix(address.trim_start_matches("0x"), 16)
+            .map_err(|_| "the load address must be in hex")?;
+        (address, data)
+    };
+    if image.is_empty() {
+        return Err(format!("{} has nothing to put in flash", image_path).into());
+    }
+
+    let signed = signed_image::sign(&image, version, base, &key);
+    fs::write(output_path, &signed)?;
+    println!(
+        "Signed {} as version {} for 0x{:08x} ({} bytes)",
+        image_path,
+        version,
+        base,
+        signed.len()
+    );
+    Ok(())
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
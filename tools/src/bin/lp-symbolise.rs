@@ -0,0 +1,120 @@
+//! Turns the output of `board::sampler::dump()` into a ranked list of
+//! functions, using the symbol table in the ELF file.
+//!
+//! ```text
+//! lp-symbolise <elf-file> [<samples-file>]
+//! ```
+//!
+//! The samples are read from standard input if no file is given, so you can
+//! pipe a UART capture straight in. Anything outside the
+//! `--- samples ---` / `--- end ---` markers is ignored.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::{env, fs, process};
+
+use launchpad_tools::elf::{self, Elf};
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} <elf-file> [<samples-file>]", args[0]);
+        process::exit(1);
+    }
+
+    if let Err(e) = run(&args[1], args.get(2).map(|s| s.as_str())) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn run(elf_path: &str, samples_path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let elf_data = fs::read(elf_path)?;
+    let elf = Elf::parse(&elf_data)?;
+    let symbols = elf.symbols()?;
+
+    let samples_text = match samples_path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut s = String::new();
+            io::stdin().read_to_string(&mut s)?;
+            s
+        }
+    };
+    let samples = parse_samples(&samples_text);
+
+    let mut per_function: HashMap<String, u64> = HashMap::new();
+    let mut total = 0u64;
+    for (pc, count) in samples {
+        let name = match elf::lookup(&symbols, pc) {
+            Some(symbol) => symbol.name.clone(),
+            None => format!("<unknown 0x{:08x}>", pc),
+        };
+        *per_function.entry(name).or_insert(0) += u64::from(count);
+        total += u64::from(count);
+    }
+
+    let mut ranked: Vec<(String, u64)> = per_function.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    println!("{:>7} {:>8}  function", "%", "samples");
+    for (name, count) in ranked {
+        let percent = if total == 0 {
+            0.0
+        } else {
+            (count as f64 * 100.0) / total as f64
+        };
+        println!("{:>6.2}% {:>8}  {}", percent, count, name);
+    }
+    Ok(())
+}
+
+/// Pull `0x<pc> <count>` pairs out of a sampler dump.
+fn parse_samples(text: &str) -> Vec<(u32, u32)> {
+    let mut samples = Vec::new();
+    let mut in_dump = false;
+    for line in text.lines().map(|l| l.trim()) {
+        if line == "--- samples ---" {
+            in_dump = true;
+            continue;
+        } else if line == "--- end ---" {
+            in_dump = false;
+            continue;
+        }
+        if !in_dump || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let pc = parts
+            .next()
+            .and_then(|p| u32::from_str_radix(p.trim_start_matches("0x"), 16).ok());
+        let count = parts.next().and_then(|c| c.parse::<u32>().ok());
+        if let (Some(pc), Some(count)) = (pc, count) {
+            samples.push((pc, count));
+        }
+    }
+    samples
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
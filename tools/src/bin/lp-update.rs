@@ -0,0 +1,215 @@
+//! Sends a new application image to the Launchpad boot loader over a serial
+//! port.
+//!
+//! ```text
+//! lp-update <serial-port> <image>...
+//! ```
+//!
+//! Applications run where they are stored, so give the `slot-a` and
+//! `slot-b` builds of the application: the boot loader says which slot it
+//! will write, and the image linked for that slot is sent. Images are ELF
+//! files, or the output of `lp-sign` for a boot loader which only takes
+//! signed images. Hold SW1 while
+//! resetting the Launchpad to make the boot loader wait for an update.
+//!
+//! The port is set up with `stty`, so this only works on Linux and macOS.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use std::{env, process};
+
+use launchpad_tools::elf::Elf;
+use launchpad_tools::signed_image;
+use launchpad_tools::update::{self, Decoder, Frame, Info};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+type Error = Box<dyn std::error::Error>;
+
+/// A serial port with the boot loader on the other end.
+struct Port {
+    file: File,
+    decoder: Decoder,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const BAUD_RATE: &str = "115200";
+/// How many times to send a command before giving up
+const ATTEMPTS: usize = 5;
+/// How long most commands take
+const TIMEOUT: Duration = Duration::from_secs(1);
+/// How long `BEGIN` (which erases the slot) and `FINISH` (which checks it)
+/// can take
+const SLOW_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <serial-port> <image>...", args[0]);
+        process::exit(1);
+    }
+
+    if let Err(e) = run(&args[1], &args[2..]) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn run(port_path: &str, image_paths: &[String]) -> Result<(), Error> {
+    let mut images = Vec::new();
+    for path in image_paths {
+        let data = fs::read(path)?;
+        let (base, image) = match signed_image::parse(&data) {
+            Some(header) => (header.load_address, data),
+            None => Elf::parse(&data)?.flash_image()?,
+        };
+        images.push((path, base, image));
+    }
+
+    let mut port = Port::open(port_path)?;
+    let reply = port.command(update::HELLO, &[], TIMEOUT)?;
+    let info = Info::parse(&reply.payload).ok_or("bad INFO from the boot loader")?;
+    if info.version != update::VERSION {
+        return Err(format!("boot loader speaks protocol version {}", info.version).into());
+    }
+    let slot_name = if info.target == 0 { "A" } else { "B" };
+    println!(
+        "Boot loader will write slot {} (0x{:08x}, {} KiB)",
+        slot_name,
+        info.slot_address,
+        info.slot_size / 1024
+    );
+
+    let (path, _, image) = images
+        .iter()
+        .find(|(_, base, _)| *base == info.slot_address)
+        .ok_or_else(|| {
+            format!(
+                "none of the images is linked for slot {} - build one with the slot-{} feature",
+                slot_name,
+                slot_name.to_lowercase()
+            )
+        })?;
+    if image.len() as u32 > info.slot_size {
+        return Err(format!("{} is too big for the slot", path).into());
+    }
+    println!("Sending {} ({} bytes)", path, image.len());
+
+    let mut begin = (image.len() as u32).to_le_bytes().to_vec();
+    begin.extend_from_slice(&update::crc32(image).to_le_bytes());
+    port.command(update::BEGIN, &begin, SLOW_TIMEOUT)?;
+
+    let mut offset = 0u32;
+    for chunk in image.chunks(info.max_chunk.min(update::MAX_PAYLOAD - 4)) {
+        let mut data = offset.to_le_bytes().to_vec();
+        data.extend_from_slice(chunk);
+        port.command(update::DATA, &data, TIMEOUT)?;
+        offset += chunk.len() as u32;
+        print!("\r{:>3}%", (offset as usize * 100) / image.len());
+        std::io::stdout().flush()?;
+    }
+    println!();
+
+    port.command(update::FINISH, &[], SLOW_TIMEOUT)?;
+    port.command(update::BOOT, &[], TIMEOUT)?;
+    println!("Done - the new image is on trial until it confirms itself");
+    Ok(())
+}
+
+impl Port {
+    /// Open the port, and set it to 115200 bps, raw, with reads that time
+    /// out after a tenth of a second.
+    fn open(path: &str) -> Result<Port, Error> {
+        let device_flag = if cfg!(target_os = "macos") {
+            "-f"
+        } else {
+            "-F"
+        };
+        let status = process::Command::new("stty")
+            .arg(device_flag)
+            .arg(path)
+            .args([BAUD_RATE, "raw", "-echo", "min", "0", "time", "1"])
+            .status()?;
+        if !status.success() {
+            return Err(format!("stty couldn't set up {}", path).into());
+        }
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Port {
+            file,
+            decoder: Decoder::new(),
+        })
+    }
+
+    /// Send a command until it gets an `ACK` or `INFO` back. A `NAK` for a
+    /// damaged frame, or no reply at all, means try again; any other `NAK`
+    /// is an error.
+    fn command(&mut self, kind: u8, payload: &[u8], timeout: Duration) -> Result<Frame, Error> {
+        let frame = update::encode(kind, payload);
+        for _ in 0..ATTEMPTS {
+            self.file.write_all(&frame)?;
+            let reply = match self.reply(timeout)? {
+                Some(reply) => reply,
+                None => continue,
+            };
+            match reply.kind {
+                update::ACK | update::INFO => return Ok(reply),
+                update::NAK if reply.payload.first() == Some(&update::NAK_BAD_FRAME) => continue,
+                update::NAK => {
+                    let code = reply.payload.first().cloned().unwrap_or(0);
+                    return Err(format!("boot loader said: {}", update::nak_reason(code)).into());
+                }
+                _ => continue,
+            }
+        }
+        Err("no reply from the boot loader - is it waiting for an update?".into())
+    }
+
+    /// Wait for a reply frame.
+    fn reply(&mut self, timeout: Duration) -> Result<Option<Frame>, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut buffer = [0u8; 64];
+        while Instant::now() < deadline {
+            let count = self.file.read(&mut buffer)?;
+            for byte in &buffer[..count] {
+                if let Some(frame) = self.decoder.push(*byte) {
+                    return Ok(Some(frame));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,122 @@
+//! Finding the build-info record in a firmware image. The layout is
+//! described in `common::build_info` in the firmware crate, which this must
+//! match.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use crate::update::crc32;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// How an image was built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Where the record is in the image
+    pub offset: usize,
+    /// The crate version
+    pub version: String,
+    /// The git commit
+    pub git_hash: String,
+    /// When it was built, in seconds since 1970
+    pub timestamp: u64,
+    /// The cargo profile
+    pub profile: String,
+    /// The target triple
+    pub target: String,
+    /// The enabled cargo features
+    pub features: Vec<String>,
+    /// The target features the compiler was allowed to use
+    pub target_features: Vec<String>,
+    /// The board
+    pub board: String,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The length of the record.
+pub const RECORD_LEN: usize = 672;
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// "LPBI"
+const MAGIC: &[u8] = b"LPBI";
+/// The layout we understand
+const LAYOUT: u32 = 2;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Search an image (or a dump of flash) for the record. Only records with
+/// the right CRC count, so stray copies of the magic number are skipped.
+pub fn find(image: &[u8]) -> Option<BuildInfo> {
+    (0..image.len().saturating_sub(RECORD_LEN - 1))
+        .step_by(4)
+        .find_map(|offset| parse(&image[offset..offset + RECORD_LEN], offset))
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn parse(record: &[u8], offset: usize) -> Option<BuildInfo> {
+    let word =
+        |i: usize| u32::from_le_bytes([record[i], record[i + 1], record[i + 2], record[i + 3]]);
+    if &record[0..4] != MAGIC || word(RECORD_LEN - 4) != crc32(&record[..RECORD_LEN - 4]) {
+        return None;
+    }
+    if word(4) != LAYOUT {
+        return None;
+    }
+    let string = |start: usize, len: usize| {
+        let field = &record[start..start + len];
+        let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+        String::from_utf8_lossy(&field[..end]).into_owned()
+    };
+    let mut timestamp = [0u8; 8];
+    timestamp.copy_from_slice(&record[72..80]);
+    let list = |start: usize, len: usize| -> Vec<String> {
+        string(start, len)
+            .split(',')
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    Some(BuildInfo {
+        offset,
+        version: string(8, 16),
+        git_hash: string(24, 48),
+        timestamp: u64::from_le_bytes(timestamp),
+        profile: string(80, 16),
+        target: string(96, 40),
+        features: list(136, 256),
+        target_features: list(392, 256),
+        board: string(648, 20),
+    })
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,281 @@
+//! Just enough of an ELF32 (little-endian) reader to find the symbols and
+//! loadable segments in a Cortex-M firmware image.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::convert::TryFrom;
+use std::fmt;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Things that can go wrong reading an ELF file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Doesn't start with the ELF magic number
+    NotElf,
+    /// Not a 32-bit little-endian ELF file
+    Unsupported,
+    /// A header points outside the file
+    Truncated,
+    /// A segment runs past the end of the address space
+    BadAddress,
+}
+
+/// A function (or object) from the symbol table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The symbol name
+    pub name: String,
+    /// Start address, with the Thumb bit cleared
+    pub address: u32,
+    /// Size in bytes
+    pub size: u32,
+    /// Is this a function (as opposed to data)?
+    pub is_function: bool,
+}
+
+/// A `PT_LOAD` program header, and the bytes it loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Offset of the bytes in the ELF file
+    pub file_offset: usize,
+    /// Where the bytes are stored (i.e. where they are in flash)
+    pub physical_address: u32,
+    /// Where the bytes are used (e.g. SRAM for `.data`)
+    pub virtual_address: u32,
+    /// The bytes in the file
+    pub data: Vec<u8>,
+}
+
+/// A section header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// The section name
+    pub name: String,
+    /// Where the section is used
+    pub address: u32,
+    /// Offset of the section in the ELF file
+    pub file_offset: usize,
+    /// Size in bytes
+    pub size: u32,
+}
+
+/// A parsed ELF file.
+pub struct Elf<'a> {
+    data: &'a [u8],
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<'a> Elf<'a> {
+    /// Check the header and wrap up the bytes of an ELF file.
+    pub fn parse(data: &'a [u8]) -> Result<Elf<'a>, Error> {
+        if data.len() < 52 || &data[0..4] != ELF_MAGIC {
+            return Err(Error::NotElf);
+        }
+        if data[4] != ELFCLASS32 || data[5] != ELFDATA2LSB {
+            return Err(Error::Unsupported);
+        }
+        Ok(Elf { data })
+    }
+
+    /// Get the `PT_LOAD` segments which have bytes in the file.
+    pub fn segments(&self) -> Result<Vec<Segment>, Error> {
+        let phoff = self.u32_at(28)? as usize;
+        let phentsize = self.u16_at(42)? as usize;
+        let phnum = self.u16_at(44)? as usize;
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let base = phoff + i * phentsize;
+            if self.u32_at(base)? != PT_LOAD {
+                continue;
+            }
+            let offset = self.u32_at(base + 4)? as usize;
+            let vaddr = self.u32_at(base + 8)?;
+            let paddr = self.u32_at(base + 12)?;
+            let filesz = self.u32_at(base + 16)? as usize;
+            if filesz == 0 {
+                continue;
+            }
+            segments.push(Segment {
+                file_offset: offset,
+                physical_address: paddr,
+                virtual_address: vaddr,
+                data: self.bytes(offset, filesz)?.to_vec(),
+            });
+        }
+        Ok(segments)
+    }
+
+    /// Get all the section headers.
+    pub fn sections(&self) -> Result<Vec<Section>, Error> {
+        let shoff = self.u32_at(32)? as usize;
+        let shentsize = self.u16_at(46)? as usize;
+        let shnum = self.u16_at(48)? as usize;
+        let shstrndx = self.u16_at(50)? as usize;
+        if shoff == 0 || shnum == 0 {
+            return Ok(Vec::new());
+        }
+        let names_offset = self.u32_at(shoff + shstrndx * shentsize + 16)? as usize;
+        let mut sections = Vec::new();
+        for i in 0..shnum {
+            let base = shoff + i * shentsize;
+            sections.push(Section {
+                name: self.string_at(names_offset + self.u32_at(base)? as usize)?,
+                address: self.u32_at(base + 12)?,
+                file_offset: self.u32_at(base + 16)? as usize,
+                size: self.u32_at(base + 20)?,
+            });
+        }
+        Ok(sections)
+    }
+
+    /// Get the function and object symbols, sorted by address.
+    pub fn symbols(&self) -> Result<Vec<Symbol>, Error> {
+        let shoff = self.u32_at(32)? as usize;
+        let shentsize = self.u16_at(46)? as usize;
+        let shnum = self.u16_at(48)? as usize;
+        let mut symbols = Vec::new();
+        for i in 0..shnum {
+            let base = shoff + i * shentsize;
+            if self.u32_at(base + 4)? != SHT_SYMTAB {
+                continue;
+            }
+            let offset = self.u32_at(base + 16)? as usize;
+            let size = self.u32_at(base + 20)? as usize;
+            let link = self.u32_at(base + 24)? as usize;
+            let entsize = self.u32_at(base + 36)? as usize;
+            let strtab = self.u32_at(shoff + link * shentsize + 16)? as usize;
+            if entsize == 0 {
+                continue;
+            }
+            for j in 0..(size / entsize) {
+                let sym = offset + j * entsize;
+                let kind = self.bytes(sym + 12, 1)?[0] & 0x0F;
+                if kind != STT_FUNC && kind != STT_OBJECT {
+                    continue;
+                }
+                let name = self.string_at(strtab + self.u32_at(sym)? as usize)?;
+                symbols.push(Symbol {
+                    name,
+                    address: self.u32_at(sym + 4)? & !1,
+                    size: self.u32_at(sym + 8)?,
+                    is_function: kind == STT_FUNC,
+                });
+            }
+        }
+        symbols.sort_by_key(|s| s.address);
+        Ok(symbols)
+    }
+
+    /// Build the image that ends up in flash, starting at the lowest
+    /// physical address of any loadable segment. Returns the base address
+    /// and the bytes.
+    ///
+    /// Gaps between segments are filled with `0x00`, as `objcopy -O binary`
+    /// does, so the image - and the CRC `lp-checksum` stamps into it - is the
+    /// same whether it's flashed from the ELF file (`lp-update`, `lp-sign`)
+    /// or from a binary made by `objcopy`.
+    pub fn flash_image(&self) -> Result<(u32, Vec<u8>), Error> {
+        let segments = self.segments()?;
+        let base = match segments.iter().map(|s| s.physical_address).min() {
+            Some(base) => base,
+            None => return Ok((0, Vec::new())),
+        };
+        let mut end = base;
+        for segment in &segments {
+            let segment_end = u32::try_from(segment.data.len())
+                .ok()
+                .and_then(|len| segment.physical_address.checked_add(len))
+                .ok_or(Error::BadAddress)?;
+            end = end.max(segment_end);
+        }
+        let len = end.checked_sub(base).ok_or(Error::BadAddress)?;
+        let mut image = vec![0u8; len as usize];
+        for segment in &segments {
+            let start = (segment.physical_address - base) as usize;
+            image[start..start + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        Ok((base, image))
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        self.data
+            .get(offset..offset.checked_add(len).ok_or(Error::Truncated)?)
+            .ok_or(Error::Truncated)
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16, Error> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32, Error> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string_at(&self, offset: usize) -> Result<String, Error> {
+        let tail = self.data.get(offset..).ok_or(Error::Truncated)?;
+        let len = tail.iter().position(|&b| b == 0).ok_or(Error::Truncated)?;
+        Ok(String::from_utf8_lossy(&tail[..len]).into_owned())
+    }
+}
+
+/// Find the function containing `address` in a list of symbols sorted by
+/// address.
+pub fn lookup(symbols: &[Symbol], address: u32) -> Option<&Symbol> {
+    let address = address & !1;
+    // Several symbols can share an address, so start after the last one
+    // which starts at or before this address, and search backwards for a
+    // function which covers it.
+    let end = symbols.partition_point(|s| s.address <= address);
+    symbols[..end].iter().rev().find(|s| {
+        s.is_function && u64::from(address) < u64::from(s.address) + u64::from(s.size.max(1))
+    })
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotElf => write!(f, "not an ELF file"),
+            Error::Unsupported => write!(f, "not a 32-bit little-endian ELF file"),
+            Error::Truncated => write!(f, "ELF file is truncated"),
+            Error::BadAddress => write!(f, "ELF segment runs past the end of memory"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,22 @@
+//! Host-side tools for working with stellaris-launchpad firmware images.
+//!
+//! These run on your PC, not on the Launchpad. The root of this repository
+//! is configured to build for `thumbv7em-none-eabihf`, so build these with an
+//! explicit host target - `make tools` does this for you.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+pub mod build_info;
+pub mod elf;
+pub mod signed_image;
+pub mod update;
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
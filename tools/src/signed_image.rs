@@ -0,0 +1,54 @@
+//! Signing images for the boot loader's `signed` feature. The header is
+//! `common::signed_image::Header` from the firmware crate (built for the host
+//! by the `host` crate), so it's the same one the boot loader checks.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use ed25519_compact::SecretKey;
+use sha2::{Digest, Sha256};
+
+pub use stellaris_launchpad::common::signed_image::{Header, HEADER_LEN, SIGNED_LEN};
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Pad an image to a whole number of words, and append a signed header.
+pub fn sign(image: &[u8], version: u32, load_address: u32, key: &SecretKey) -> Vec<u8> {
+    let mut signed = image.to_vec();
+    while signed.len() % 4 != 0 {
+        signed.push(0xFF);
+    }
+    let mut header = Header {
+        version,
+        size: signed.len() as u32,
+        load_address,
+        hash: Sha256::digest(&signed).into(),
+        signature: [0; 64],
+    };
+    let signature = key.sign(&header.to_bytes()[..SIGNED_LEN], None);
+    header.signature = *signature;
+    signed.extend_from_slice(&header.to_bytes());
+    signed
+}
+
+/// Read the header from the end of a signed image. Returns `None` if the
+/// image doesn't have one. The signature isn't checked.
+pub fn parse(signed: &[u8]) -> Option<Header> {
+    let start = signed.len().checked_sub(HEADER_LEN)?;
+    let mut bytes = [0u8; HEADER_LEN];
+    bytes.copy_from_slice(&signed[start..]);
+    Header::parse(&bytes).filter(|header| header.size as usize == start)
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,160 @@
+//! The host end of the boot loader's serial protocol. The frame format, the
+//! commands and the CRC come from `common::update_protocol` in the firmware
+//! crate (built for the host by the `host` crate), so both ends agree.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use stellaris_launchpad::common::crc::Crc32;
+use stellaris_launchpad::common::update_protocol::{self as protocol, MAX_FRAME};
+
+pub use stellaris_launchpad::common::crc::crc32;
+pub use stellaris_launchpad::common::update_protocol::{
+    ACK, BEGIN, BOOT, DATA, FINISH, HELLO, INFO, MAX_CHUNK, MAX_PAYLOAD, NAK, NAK_BAD_CRC,
+    NAK_BAD_FRAME, NAK_BAD_IMAGE, NAK_BAD_PAYLOAD, NAK_FLASH, NAK_NOT_STARTED, NAK_OUT_OF_ORDER,
+    NAK_REJECTED, NAK_TOO_BIG, NAK_UNKNOWN, START, VERSION,
+};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A frame which arrived intact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// What sort of frame it is
+    pub kind: u8,
+    /// The payload
+    pub payload: Vec<u8>,
+}
+
+/// Picks frames out of a stream of bytes. Damaged frames are dropped.
+#[derive(Default)]
+pub struct Decoder {
+    decoder: protocol::Decoder,
+}
+
+/// What the boot loader says in reply to `HELLO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    /// The protocol version
+    pub version: u8,
+    /// The slot an image will be written to (0 is A, 1 is B)
+    pub target: u8,
+    /// The slot with the confirmed image, if any
+    pub confirmed: Option<u8>,
+    /// Where the target slot starts in flash
+    pub slot_address: u32,
+    /// How big the target slot is
+    pub slot_size: u32,
+    /// The most image bytes to send in one `DATA` frame
+    pub max_chunk: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Calculate the CRC-32 of some pieces of data, one after the other.
+pub fn crc32_parts(parts: &[&[u8]]) -> u32 {
+    let mut crc = Crc32::new();
+    for part in parts {
+        crc.update(part);
+    }
+    crc.finish()
+}
+
+/// Build a frame. Panics if the payload is longer than `MAX_PAYLOAD`.
+pub fn encode(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = [0u8; MAX_FRAME];
+    let len = protocol::encode(kind, payload, &mut buffer);
+    buffer[..len].to_vec()
+}
+
+/// Describe a `NAK` code.
+pub fn nak_reason(code: u8) -> &'static str {
+    match code {
+        NAK_BAD_FRAME => "the command was damaged",
+        NAK_UNKNOWN => "unknown command",
+        NAK_BAD_PAYLOAD => "bad command payload",
+        NAK_TOO_BIG => "the image is empty or too big for the slot",
+        NAK_NOT_STARTED => "no update in progress",
+        NAK_OUT_OF_ORDER => "data out of order",
+        NAK_BAD_CRC => "the image in flash doesn't match its CRC",
+        NAK_BAD_IMAGE => "the image isn't linked for this slot",
+        NAK_FLASH => "flash failure",
+        NAK_REJECTED => "the image isn't signed with the boot loader's key, or is too old",
+        _ => "unknown error",
+    }
+}
+
+impl Decoder {
+    /// Wait for the start of a frame.
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Take the next byte. Returns a frame when one ends intact.
+    pub fn push(&mut self, byte: u8) -> Option<Frame> {
+        match self.decoder.push(byte) {
+            Some(Ok(frame)) => Some(Frame {
+                kind: frame.kind,
+                payload: frame.payload.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Info {
+    /// Decode the payload of an `INFO` frame.
+    pub fn parse(payload: &[u8]) -> Option<Info> {
+        if payload.len() < 14 {
+            return None;
+        }
+        let word = |i: usize| {
+            u32::from_le_bytes([payload[i], payload[i + 1], payload[i + 2], payload[i + 3]])
+        };
+        Some(Info {
+            version: payload[0],
+            target: payload[1],
+            confirmed: if payload[2] == 0xFF {
+                None
+            } else {
+                Some(payload[2])
+            },
+            slot_address: word(4),
+            slot_size: word(8),
+            max_chunk: usize::from(u16::from_le_bytes([payload[12], payload[13]])),
+        })
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,92 @@
+//! Tests for finding the build-info record, laid out as `build.rs` writes it.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use launchpad_tools::build_info::{self, BuildInfo, RECORD_LEN};
+use launchpad_tools::update::crc32;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn field(record: &mut Vec<u8>, value: &str, len: usize) {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    record.extend_from_slice(&bytes);
+}
+
+/// A record, as `build.rs` makes it.
+fn record() -> Vec<u8> {
+    let mut record = b"LPBI".to_vec();
+    record.extend_from_slice(&2u32.to_le_bytes());
+    field(&mut record, "0.9.0", 16);
+    field(
+        &mut record,
+        "0123456789abcdef0123456789abcdef01234567-dirty",
+        48,
+    );
+    record.extend_from_slice(&1_700_000_000u64.to_le_bytes());
+    field(&mut record, "release", 16);
+    field(&mut record, "thumbv7em-none-eabihf", 40);
+    field(&mut record, "alloc,stack-guard", 256);
+    field(&mut record, "dsp,thumb-mode,vfp4d16sp", 256);
+    field(&mut record, "ek-tm4c123gxl", 20);
+    let crc = crc32(&record);
+    record.extend_from_slice(&crc.to_le_bytes());
+    record
+}
+
+#[test]
+fn finds_the_record() {
+    let mut image = vec![0xAA; 64];
+    // A stray magic number first
+    image[8..12].copy_from_slice(b"LPBI");
+    image.extend_from_slice(&record());
+    image.extend_from_slice(&[0x55; 10]);
+    assert_eq!(
+        build_info::find(&image),
+        Some(BuildInfo {
+            offset: 64,
+            version: String::from("0.9.0"),
+            git_hash: String::from("0123456789abcdef0123456789abcdef01234567-dirty"),
+            timestamp: 1_700_000_000,
+            profile: String::from("release"),
+            target: String::from("thumbv7em-none-eabihf"),
+            features: vec![String::from("alloc"), String::from("stack-guard")],
+            target_features: vec![
+                String::from("dsp"),
+                String::from("thumb-mode"),
+                String::from("vfp4d16sp"),
+            ],
+            board: String::from("ek-tm4c123gxl"),
+        })
+    );
+}
+
+#[test]
+fn skips_damaged_records() {
+    let record = record();
+    assert_eq!(record.len(), RECORD_LEN);
+    let mut damaged = record.clone();
+    damaged[100] ^= 1;
+    assert_eq!(build_info::find(&damaged), None);
+    assert_eq!(build_info::find(&record[..RECORD_LEN - 1]), None);
+    // Another layout, even with a good CRC
+    let mut other = record[..RECORD_LEN - 4].to_vec();
+    other[4] = 1;
+    let crc = crc32(&other);
+    other.extend_from_slice(&crc.to_le_bytes());
+    assert_eq!(build_info::find(&other), None);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
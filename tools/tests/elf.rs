@@ -0,0 +1,190 @@
+//! Tests for the ELF reader and symboliser, against `fixtures/symbols.elf`
+//! (and `fixtures/symbols.bin`, the same file after `objcopy -O binary`).
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use launchpad_tools::elf::{self, Elf, Error, Symbol};
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const FIXTURE: &[u8] = include_bytes!("fixtures/symbols.elf");
+
+/// What `objcopy -O binary` makes of `FIXTURE`
+const FIXTURE_BIN: &[u8] = include_bytes!("fixtures/symbols.bin");
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn symbol(name: &str, address: u32, size: u32, is_function: bool) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        address,
+        size,
+        is_function,
+    }
+}
+
+fn name_at(symbols: &[Symbol], address: u32) -> Option<&str> {
+    elf::lookup(symbols, address).map(|s| s.name.as_str())
+}
+
+#[test]
+fn rejects_other_files() {
+    assert_eq!(
+        Elf::parse(b"not an elf file, but long enough to be one........").err(),
+        Some(Error::NotElf)
+    );
+    let mut elf64 = FIXTURE.to_vec();
+    elf64[4] = 2;
+    assert_eq!(Elf::parse(&elf64).err(), Some(Error::Unsupported));
+}
+
+#[test]
+fn reads_symbols() {
+    let symbols = Elf::parse(FIXTURE).unwrap().symbols().unwrap();
+    let reset = symbols.iter().find(|s| s.name == "reset").unwrap();
+    // The Thumb bit is cleared
+    assert_eq!(*reset, symbol("reset", 0x08, 12, true));
+    let table = symbols.iter().find(|s| s.name == "table").unwrap();
+    assert_eq!(*table, symbol("table", 0x40, 12, false));
+    // Sorted, and without the mapping symbols ($t and friends)
+    assert!(symbols.windows(2).all(|w| w[0].address <= w[1].address));
+    assert!(symbols.iter().all(|s| !s.name.starts_with('$')));
+}
+
+#[test]
+fn reads_segments() {
+    let segments = Elf::parse(FIXTURE).unwrap().segments().unwrap();
+    let addresses: Vec<(u32, u32, usize)> = segments
+        .iter()
+        .map(|s| (s.virtual_address, s.physical_address, s.data.len()))
+        .collect();
+    assert_eq!(
+        addresses,
+        [
+            (0x00, 0x00, 8),
+            (0x08, 0x08, 0x1A),
+            (0x40, 0x40, 12),
+            (0x2000_0000, 0x50, 4)
+        ]
+    );
+    assert_eq!(segments[3].data, [0x78, 0x56, 0x34, 0x12]);
+}
+
+#[test]
+fn the_flash_image_matches_objcopy() {
+    let (base, image) = Elf::parse(FIXTURE).unwrap().flash_image().unwrap();
+    assert_eq!(base, 0);
+    assert_eq!(image, FIXTURE_BIN);
+    // Including the gaps between segments
+    assert!(image[0x22..0x40].iter().all(|&b| b == 0));
+    assert!(image[0x4c..0x50].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn refuses_segments_past_the_end_of_memory() {
+    // Move the `.data` initialisers (loaded at 0x50) to the top of memory
+    let mut elf = FIXTURE.to_vec();
+    let u32_at = |elf: &[u8], offset: usize| {
+        u32::from_le_bytes([
+            elf[offset],
+            elf[offset + 1],
+            elf[offset + 2],
+            elf[offset + 3],
+        ])
+    };
+    let phoff = u32_at(&elf, 0x1C) as usize;
+    let phentsize = usize::from(u16::from_le_bytes([elf[0x2A], elf[0x2B]]));
+    let phnum = usize::from(u16::from_le_bytes([elf[0x2C], elf[0x2D]]));
+    let header = (0..phnum)
+        .map(|i| phoff + i * phentsize)
+        .find(|&header| u32_at(&elf, header + 12) == 0x50)
+        .unwrap();
+    elf[header + 12..header + 16].copy_from_slice(&0xFFFF_FFFEu32.to_le_bytes());
+    assert_eq!(
+        Elf::parse(&elf).unwrap().flash_image().err(),
+        Some(Error::BadAddress)
+    );
+}
+
+#[test]
+fn symbolises_the_fixture() {
+    let symbols = Elf::parse(FIXTURE).unwrap().symbols().unwrap();
+    assert_eq!(name_at(&symbols, 0x04), None);
+    assert_eq!(name_at(&symbols, 0x08), Some("reset"));
+    // A PC with the Thumb bit set
+    assert_eq!(name_at(&symbols, 0x11), Some("reset"));
+    assert_eq!(name_at(&symbols, 0x14), Some("first"));
+    // `second` shares its address with four zero-sized objects
+    for address in 0x1A..0x22 {
+        assert_eq!(
+            name_at(&symbols, address),
+            Some("second"),
+            "0x{:x}",
+            address
+        );
+    }
+    assert_eq!(name_at(&symbols, 0x22), None);
+    // Data isn't code
+    assert_eq!(name_at(&symbols, 0x40), None);
+}
+
+#[test]
+fn finds_a_function_among_symbols_at_the_same_address() {
+    // Whichever of the symbols at 0x110 a binary search lands on, the
+    // function must be found
+    for before in 0..6 {
+        for after in 0..6 {
+            let mut symbols = vec![symbol("a", 0x100, 0x10, true)];
+            for i in 0..before {
+                symbols.push(symbol(&format!("before{}", i), 0x110, 0, false));
+            }
+            symbols.push(symbol("b", 0x110, 0x10, true));
+            for i in 0..after {
+                symbols.push(symbol(&format!("after{}", i), 0x110, 0, false));
+            }
+            symbols.push(symbol("c", 0x120, 0x10, true));
+            for address in 0x110..0x120 {
+                assert_eq!(
+                    name_at(&symbols, address),
+                    Some("b"),
+                    "{} {} 0x{:x}",
+                    before,
+                    after,
+                    address
+                );
+            }
+            assert_eq!(name_at(&symbols, 0x10F), Some("a"));
+            assert_eq!(name_at(&symbols, 0x120), Some("c"));
+        }
+    }
+}
+
+#[test]
+fn zero_sized_functions_cover_their_own_address() {
+    let symbols = [
+        symbol("a", 0x100, 0, true),
+        symbol("b", 0xFFFF_FFF0, 0x10, true),
+    ];
+    assert_eq!(name_at(&symbols, 0x100), Some("a"));
+    assert_eq!(name_at(&symbols, 0x102), None);
+    assert_eq!(name_at(&symbols, 0xFFFF_FFFE), Some("b"));
+    assert_eq!(name_at(&[], 0x100), None);
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
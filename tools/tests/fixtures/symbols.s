@@ -0,0 +1,76 @@
+@ A tiny Cortex-M image for the tools' tests. Rebuild symbols.elf with:
+@
+@   llvm-mc -triple thumbv7em-none-eabi -filetype obj symbols.s -o symbols.o
+@   ld.lld --nmagic -e reset -T symbols.ld symbols.o -o symbols.elf
+@
+@ and symbols.bin (what the flash image must match) with:
+@
+@   llvm-objcopy -O binary symbols.elf symbols.bin
+
+    .syntax unified
+    .thumb
+
+    .section .vector_table, "a"
+    .word 0x20008000
+    .word reset
+
+    .text
+
+    .global reset
+    .type reset, %function
+    .thumb_func
+reset:
+    bl first
+    bl second
+    b reset
+    .size reset, . - reset
+
+    .global first
+    .type first, %function
+    .thumb_func
+first:
+    movs r0, #1
+    movs r1, #2
+    bx lr
+    .size first, . - first
+
+@ Several symbols at the same address, with the function in the middle
+    .global marker_a
+    .type marker_a, %object
+    .size marker_a, 0
+marker_a:
+    .global marker_b
+    .type marker_b, %object
+    .size marker_b, 0
+marker_b:
+    .global second
+    .type second, %function
+    .thumb_func
+second:
+    .global marker_c
+    .type marker_c, %object
+    .size marker_c, 0
+marker_c:
+    .global marker_d
+    .type marker_d, %object
+    .size marker_d, 0
+marker_d:
+    movs r0, #3
+    movs r1, #4
+    movs r2, #5
+    bx lr
+    .size second, . - second
+
+    .section .rodata, "a"
+    .global table
+    .type table, %object
+table:
+    .word 1, 2, 3
+    .size table, . - table
+
+    .data
+    .global counter
+    .type counter, %object
+counter:
+    .word 0x12345678
+    .size counter, . - counter
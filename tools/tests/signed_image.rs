@@ -0,0 +1,140 @@
+//! Signs images with `signed_image::sign`, and checks them with the boot
+//! loader's own `common::signed_image` code.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use ed25519_compact::{KeyPair, Seed};
+use embedded_storage::nor_flash::{NorFlash, NorFlashErrorKind};
+use launchpad_tools::signed_image::{self, HEADER_LEN};
+use stellaris_launchpad::common::crc;
+use stellaris_launchpad::common::ram_flash::RamFlash;
+use stellaris_launchpad::common::signed_image::{self as firmware, Error, SignatureCheck};
+use stellaris_launchpad::common::update::{self, Slot, Updater, LAUNCHPAD};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The Launchpad's 256 KiB of flash
+type Flash = RamFlash<0x4_0000>;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn key_pair(seed: u8) -> KeyPair {
+    KeyPair::from_seed(Seed::new([seed; Seed::BYTES]))
+}
+
+/// An image linked for `slot`, with a length which isn't a whole number of
+/// words.
+fn image(slot: Slot) -> Vec<u8> {
+    let mut image: Vec<u8> = (0..1001u32).map(|i| (i * 13) as u8).collect();
+    image[0..4].copy_from_slice(&0x2000_8000u32.to_le_bytes());
+    let reset = LAUNCHPAD.slot(slot).start + 0x101;
+    image[4..8].copy_from_slice(&reset.to_le_bytes());
+    image
+}
+
+/// Put a signed image in flash at `start`, and check it.
+fn verify(
+    signed: &[u8],
+    start: u32,
+    key: &KeyPair,
+    minimum: u32,
+) -> Result<firmware::Header, Error<NorFlashErrorKind>> {
+    let mut flash = Box::new(Flash::new());
+    let mut padded = signed.to_vec();
+    padded.resize((signed.len() + 3) / 4 * 4, 0xFF);
+    flash.write(start, &padded).unwrap();
+    firmware::verify(&mut *flash, start, signed.len() as u32, &key.pk, minimum)
+}
+
+#[test]
+fn the_boot_loader_accepts_what_we_sign() {
+    let key = key_pair(1);
+    let start = LAUNCHPAD.slots[0].start;
+    let signed = signed_image::sign(&image(Slot::A), 7, start, &key.sk);
+    assert_eq!(signed.len(), 1004 + HEADER_LEN);
+    assert_eq!(&signed[1001..1004], &[0xFF; 3]);
+
+    let header = verify(&signed, start, &key, 7).unwrap();
+    assert_eq!(signed_image::parse(&signed), Some(header));
+    assert_eq!(header.version, 7);
+    assert_eq!(header.size, 1004);
+    assert_eq!(header.load_address, start);
+}
+
+#[test]
+fn the_boot_loader_refuses_bad_images() {
+    let key = key_pair(2);
+    let start = LAUNCHPAD.slots[1].start;
+    let signed = signed_image::sign(&image(Slot::B), 5, start, &key.sk);
+
+    assert_eq!(
+        verify(&signed, start, &key, 6),
+        Err(Error::TooOld {
+            version: 5,
+            minimum: 6,
+        })
+    );
+    assert_eq!(
+        verify(&signed, start, &key_pair(3), 0),
+        Err(Error::BadSignature)
+    );
+    assert_eq!(
+        verify(&signed, LAUNCHPAD.slots[0].start, &key, 0),
+        Err(Error::WrongAddress)
+    );
+    let mut damaged = signed.clone();
+    damaged[100] ^= 1;
+    assert_eq!(verify(&damaged, start, &key, 0), Err(Error::BadHash));
+    // A different version, without a new signature
+    let mut damaged = signed.clone();
+    damaged[1004 + 4] ^= 1;
+    assert_eq!(verify(&damaged, start, &key, 0), Err(Error::BadSignature));
+    assert_eq!(
+        verify(&signed[..1004], start, &key, 0),
+        Err(Error::NoHeader)
+    );
+    assert_eq!(signed_image::parse(&signed[..1004]), None);
+}
+
+#[test]
+fn an_update_older_than_the_minimum_is_rejected() {
+    let key = key_pair(4);
+    let mut flash = Box::new(Flash::new());
+    let check = SignatureCheck::new(*key.pk, 3);
+    let mut updater = Updater::open_with(&mut *flash, LAUNCHPAD, check).unwrap();
+    let start = LAUNCHPAD.slots[0].start;
+
+    let install = |updater: &mut Updater<_, _>, version| {
+        let signed = signed_image::sign(&image(Slot::A), version, start, &key.sk);
+        updater.begin(signed.len() as u32, crc::crc32(&signed))?;
+        updater.write(0, &signed)?;
+        updater.finish()
+    };
+    assert_eq!(install(&mut updater, 3), Ok(Slot::A));
+    // As the boot loader does once an image has confirmed itself
+    updater.verifier_mut().raise_minimum(4);
+    assert_eq!(updater.verifier().minimum_version(), 4);
+    assert_eq!(install(&mut updater, 3), Err(update::Error::Rejected));
+    // It never goes down
+    updater.verifier_mut().raise_minimum(1);
+    assert_eq!(updater.verifier().minimum_version(), 4);
+    assert_eq!(install(&mut updater, 4), Ok(Slot::A));
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,132 @@
+//! Checks the host end of the update protocol against the boot loader's
+//! end: frames built by `update::encode` go to the firmware's `Server`, and
+//! its replies are read back with `update::Decoder`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use launchpad_tools::update::{self, Decoder, Frame, Info};
+use stellaris_launchpad::common::ram_flash::RamFlash;
+use stellaris_launchpad::common::update::{Slot, Updater, LAUNCHPAD};
+use stellaris_launchpad::common::update_protocol::{Event, Server, MAX_FRAME};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The Launchpad's 256 KiB of flash
+type Flash = RamFlash<0x4_0000>;
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Send a command, and wait for the reply.
+fn command(server: &mut Server<&mut Flash>, kind: u8, payload: &[u8]) -> (Frame, bool) {
+    let mut reply = [0u8; MAX_FRAME];
+    let mut decoder = Decoder::new();
+    for byte in update::encode(kind, payload) {
+        let (len, boot) = match server.receive(byte, &mut reply) {
+            Event::Nothing => continue,
+            Event::Reply(len) => (len, false),
+            Event::Boot(len) => (len, true),
+        };
+        let frames: Vec<Frame> = reply[..len]
+            .iter()
+            .filter_map(|b| decoder.push(*b))
+            .collect();
+        assert_eq!(frames.len(), 1);
+        return (frames[0].clone(), boot);
+    }
+    panic!("no reply");
+}
+
+fn ack(server: &mut Server<&mut Flash>, kind: u8, payload: &[u8]) -> Vec<u8> {
+    let (reply, _) = command(server, kind, payload);
+    assert_eq!(reply.kind, update::ACK, "{:?}", reply);
+    reply.payload
+}
+
+#[test]
+fn sends_an_image_the_way_lp_update_does() {
+    let mut flash = Box::new(Flash::new());
+    let updater = Updater::open(&mut *flash, LAUNCHPAD).unwrap();
+    let mut server = Server::new(updater);
+
+    let (reply, _) = command(&mut server, update::HELLO, &[]);
+    assert_eq!(reply.kind, update::INFO);
+    let info = Info::parse(&reply.payload).unwrap();
+    assert_eq!(
+        info,
+        Info {
+            version: update::VERSION,
+            target: 0,
+            confirmed: None,
+            slot_address: LAUNCHPAD.slots[0].start,
+            slot_size: LAUNCHPAD.slots[0].size,
+            max_chunk: update::MAX_CHUNK,
+        }
+    );
+
+    let mut image: Vec<u8> = (0..3001u32).map(|i| (i * 7) as u8).collect();
+    image[0..4].copy_from_slice(&0x2000_8000u32.to_le_bytes());
+    image[4..8].copy_from_slice(&(info.slot_address + 0x101).to_le_bytes());
+
+    let mut begin = (image.len() as u32).to_le_bytes().to_vec();
+    begin.extend_from_slice(&update::crc32(&image).to_le_bytes());
+    assert_eq!(ack(&mut server, update::BEGIN, &begin), vec![0]);
+    let mut offset = 0u32;
+    for chunk in image.chunks(info.max_chunk.min(update::MAX_PAYLOAD - 4)) {
+        let mut data = offset.to_le_bytes().to_vec();
+        data.extend_from_slice(chunk);
+        offset += chunk.len() as u32;
+        assert_eq!(ack(&mut server, update::DATA, &data), offset.to_le_bytes());
+    }
+    assert_eq!(ack(&mut server, update::FINISH, &[]), vec![0]);
+    let (reply, boot) = command(&mut server, update::BOOT, &[]);
+    assert_eq!((reply.kind, boot), (update::ACK, true));
+
+    assert_eq!(server.free().select(3), Ok(Some(Slot::A)));
+}
+
+#[test]
+fn damaged_frames_are_dropped() {
+    let mut decoder = Decoder::new();
+    let mut frame = update::encode(update::ACK, &[1, 2, 3]);
+    let good = frame.clone();
+    frame[5] ^= 1;
+    let frames: Vec<Frame> = frame
+        .iter()
+        .chain(good.iter())
+        .filter_map(|b| decoder.push(*b))
+        .collect();
+    assert_eq!(
+        frames,
+        vec![Frame {
+            kind: update::ACK,
+            payload: vec![1, 2, 3],
+        }]
+    );
+}
+
+#[test]
+fn nak_codes_are_described() {
+    assert_eq!(
+        update::nak_reason(update::NAK_BAD_CRC),
+        "the image in flash doesn't match its CRC"
+    );
+    assert_eq!(update::nak_reason(0xEE), "unknown error");
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************